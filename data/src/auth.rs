@@ -0,0 +1,251 @@
+//! Checking that players logging into an online mode server own their account
+//!
+//! After encryption is enabled, the client tells Mojang's session server that it is joining the server, and the server asks the session server whether it did with `hasJoined`. Cracked clients can't do that, so they are rejected. The request is made on its own thread with `SessionService::spawn_has_joined`, like vanilla's authenticator threads, so that a slow session server doesn't hold up the tick.
+//!
+//! Offline mode servers skip this and give players a UUID made from their name with `GameProfile::offline`.
+
+use md5::{Digest, Md5};
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use shipyard::{Component, Unique};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io::Read;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use url::form_urlencoded;
+use uuid::{Builder, Uuid};
+
+/// The address of Mojang's session server
+pub const MOJANG_SESSION_SERVER: &str = "https://sessionserver.mojang.com";
+
+/// A player's account, which is added to their connection's entity once they have logged in
+#[derive(Component, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct GameProfile {
+    pub id: Uuid,
+    pub name: String,
+    #[serde(default)]
+    pub properties: Vec<ProfileProperty>,
+}
+
+impl GameProfile {
+    /// Creates the profile that an offline mode server gives a player, whose UUID is made from their name
+    pub fn offline(name: impl Into<String>) -> Self {
+        let name = name.into();
+
+        Self {
+            id: offline_uuid(&name),
+            name,
+            properties: Vec::new(),
+        }
+    }
+
+    /// Returns the base64 encoded skin and cape, if the session server sent them
+    pub fn textures(&self) -> Option<&ProfileProperty> {
+        self.properties.iter().find(|property| property.name == "textures")
+    }
+}
+
+/// A property of a profile, like its skin, which is signed by Mojang when it comes from the session server
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct ProfileProperty {
+    pub name: String,
+    pub value: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+/// Returns the version 3 UUID that vanilla gives `name` in offline mode
+///
+/// Java's `UUID.nameUUIDFromBytes` hashes `OfflinePlayer:<name>` on its own, without the namespace that `Uuid::new_v3` hashes in front of it.
+pub fn offline_uuid(name: &str) -> Uuid {
+    let hash = Md5::digest(format!("OfflinePlayer:{name}"));
+
+    Builder::from_md5_bytes(hash.into()).into_uuid()
+}
+
+/// Computes the server hash that both the client and `hasJoined` use, from the server id, the shared secret and the server's public key
+///
+/// This is the SHA-1 digest as a signed two's complement number in hexadecimal, the way Java's `BigInteger.toString(16)` prints it, so it can start with `-` and has no leading zeros.
+pub fn server_hash(server_id: &str, shared_secret: &[u8], public_key_der: &[u8]) -> String {
+    let mut digest: [u8; 20] = Sha1::new().chain_update(server_id).chain_update(shared_secret).chain_update(public_key_der).finalize().into();
+
+    let negative = digest[0] & 0x80 != 0;
+    if negative {
+        let mut carry = true;
+        for byte in digest.iter_mut().rev() {
+            (*byte, carry) = (!*byte).overflowing_add(carry as u8);
+        }
+    }
+
+    let hex = digest.iter().map(|byte| format!("{byte:02x}")).collect::<String>();
+    let hex = match hex.trim_start_matches('0') {
+        "" => "0",
+        hex => hex,
+    };
+
+    match negative {
+        true => format!("-{hex}"),
+        false => hex.to_string(),
+    }
+}
+
+/// A response from an HTTP backend
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub body: Vec<u8>,
+}
+
+/// Something that can make HTTP GET requests, so that tests and offline LAN setups can stand in for the session server
+pub trait HttpBackend: Send + Sync + 'static {
+    /// Requests `url`, returning an error only if no response was received
+    fn get(&self, url: &str) -> Result<HttpResponse, String>;
+}
+
+/// Makes real HTTP requests with `ureq`
+#[derive(Debug)]
+pub struct UreqBackend {
+    agent: ureq::Agent,
+}
+
+impl UreqBackend {
+    pub fn new() -> Self {
+        Self { agent: ureq::AgentBuilder::new().timeout(Duration::from_secs(10)).build() }
+    }
+}
+
+impl Default for UreqBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HttpBackend for UreqBackend {
+    fn get(&self, url: &str) -> Result<HttpResponse, String> {
+        let response = match self.agent.get(url).call() {
+            Ok(response) | Err(ureq::Error::Status(_, response)) => response,
+            Err(error) => return Err(error.to_string()),
+        };
+
+        let status = response.status();
+        let mut body = Vec::new();
+        response.into_reader().read_to_end(&mut body).map_err(|error| error.to_string())?;
+
+        Ok(HttpResponse { status, body })
+    }
+}
+
+/// A session server that logins are checked against, which the login flow reads as a `Unique`
+#[derive(Unique, Debug)]
+pub struct SessionService<B: HttpBackend = UreqBackend> {
+    backend: Arc<B>,
+    url: Arc<str>,
+    /// Whether the session server should check that players log in from the address that they joined from, like vanilla's `prevent-proxy-connections`
+    pub prevent_proxy_connections: bool,
+}
+
+impl<B: HttpBackend> Clone for SessionService<B> {
+    fn clone(&self) -> Self {
+        Self {
+            backend: self.backend.clone(),
+            url: self.url.clone(),
+            prevent_proxy_connections: self.prevent_proxy_connections,
+        }
+    }
+}
+
+impl SessionService<UreqBackend> {
+    /// Checks logins against Mojang's session server
+    pub fn mojang() -> Self {
+        Self::new(UreqBackend::new(), MOJANG_SESSION_SERVER)
+    }
+}
+
+impl<B: HttpBackend> SessionService<B> {
+    /// Checks logins against the session server at `url`, using `backend` to make the requests
+    pub fn new(backend: B, url: impl Into<String>) -> Self {
+        Self {
+            backend: Arc::new(backend),
+            url: url.into().trim_end_matches('/').into(),
+            prevent_proxy_connections: false,
+        }
+    }
+
+    /// Calls `has_joined` on a new thread, passing `ip` on only if `prevent_proxy_connections` is on
+    pub fn spawn_has_joined(&self, username: String, server_hash: String, ip: Option<IpAddr>) -> PendingAuth {
+        let service = self.clone();
+        let ip = ip.filter(|_| self.prevent_proxy_connections);
+
+        PendingAuth(std::thread::spawn(move || service.has_joined(&username, &server_hash, ip)))
+    }
+
+    /// Asks the session server whether `username` has joined the server with `server_hash`, returning their profile if they have
+    ///
+    /// If `ip` is given, the session server also checks that it is the address that the client joined from, which is what `prevent-proxy-connections` does.
+    pub fn has_joined(&self, username: &str, server_hash: &str, ip: Option<IpAddr>) -> Result<GameProfile, AuthError> {
+        let mut query = form_urlencoded::Serializer::new(String::new());
+        query.append_pair("username", username).append_pair("serverId", server_hash);
+        if let Some(ip) = ip {
+            query.append_pair("ip", &ip.to_string());
+        }
+        let url = format!("{}/session/minecraft/hasJoined?{}", self.url, query.finish());
+
+        let response = self.backend.get(&url).map_err(AuthError::Http)?;
+        match response.status {
+            200 => {}
+            204 => return Err(AuthError::NotAuthenticated),
+            status => return Err(AuthError::UnexpectedStatus(status)),
+        }
+
+        let profile: GameProfile = serde_json::from_slice(&response.body).map_err(|error| AuthError::InvalidResponse(error.to_string()))?;
+        if profile.name != username {
+            return Err(AuthError::InvalidResponse(format!("expected the profile of {username}, but got {}", profile.name)));
+        }
+
+        Ok(profile)
+    }
+}
+
+/// A `hasJoined` request that is running on its own thread, which is added to a connection's entity until the answer arrives
+#[derive(Component, Debug)]
+pub struct PendingAuth(JoinHandle<Result<GameProfile, AuthError>>);
+
+impl PendingAuth {
+    /// Returns whether the session server has answered, after which `join` doesn't block
+    pub fn is_finished(&self) -> bool {
+        self.0.is_finished()
+    }
+
+    /// Waits for the session server's answer
+    pub fn join(self) -> Result<GameProfile, AuthError> {
+        self.0.join().unwrap_or_else(|_| Err(AuthError::Http("the request panicked".to_string())))
+    }
+}
+
+/// The reason that a login couldn't be verified
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum AuthError {
+    /// The session server hasn't seen the player join, which happens with cracked accounts
+    NotAuthenticated,
+    /// The request didn't get a response
+    Http(String),
+    UnexpectedStatus(u16),
+    /// The response wasn't a valid profile
+    InvalidResponse(String),
+}
+
+impl Display for AuthError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotAuthenticated => write!(f, "player hasn't joined through the session server"),
+            Self::Http(error) => write!(f, "request to the session server failed: {error}"),
+            Self::UnexpectedStatus(status) => write!(f, "session server responded with status {status}"),
+            Self::InvalidResponse(error) => write!(f, "invalid response from the session server: {error}"),
+        }
+    }
+}
+
+impl Error for AuthError {}
@@ -0,0 +1,129 @@
+//! Encryption for online mode connections
+//!
+//! The server sends its RSA public key and a random verify token in Encryption Request, and the client answers with a shared secret and the verify token, both encrypted with that key. From then on, both directions of the connection are encrypted with AES-128 in CFB8 mode, using the shared secret as both the key and the IV.
+
+use aes::cipher::inout::InOutBuf;
+use aes::cipher::{BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use aes::Aes128;
+use rand::RngCore;
+use rsa::pkcs8::EncodePublicKey;
+use rsa::{Pkcs1v15Encrypt, RsaPrivateKey};
+use shipyard::Unique;
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+
+/// The size of the key that vanilla generates
+pub const KEY_BITS: usize = 1024;
+
+/// The server's RSA key pair, which only has to be generated once when the server starts
+#[derive(Unique)]
+pub struct ServerKey {
+    private_key: RsaPrivateKey,
+    public_key_der: Vec<u8>,
+}
+
+impl ServerKey {
+    /// Generates a new 1024 bit key pair
+    pub fn generate() -> Result<Self, EncryptionError> {
+        let private_key = RsaPrivateKey::new(&mut rand::thread_rng(), KEY_BITS).map_err(|_| EncryptionError::Rsa)?;
+        let public_key_der = private_key.to_public_key().to_public_key_der().map_err(|_| EncryptionError::Rsa)?.into_vec();
+
+        Ok(Self { private_key, public_key_der })
+    }
+
+    /// Returns the public key in the DER encoded X.509 format that Encryption Request sends it in
+    pub fn public_key_der(&self) -> &[u8] {
+        &self.public_key_der
+    }
+
+    /// Decrypts something that the client encrypted with the public key
+    pub fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>, EncryptionError> {
+        self.private_key.decrypt(Pkcs1v15Encrypt, data).map_err(|_| EncryptionError::Rsa)
+    }
+
+    /// Decrypts the shared secret from Encryption Response, after checking that the client sent back the verify token from Encryption Request
+    pub fn decrypt_response(&self, shared_secret: &[u8], verify_token: &[u8], expected_token: &[u8]) -> Result<[u8; 16], EncryptionError> {
+        if self.decrypt(verify_token)? != expected_token {
+            return Err(EncryptionError::VerifyTokenMismatch);
+        }
+
+        let shared_secret = self.decrypt(shared_secret)?;
+        shared_secret.as_slice().try_into().map_err(|_| EncryptionError::InvalidSecretLength(shared_secret.len()))
+    }
+}
+
+impl Debug for ServerKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ServerKey").finish_non_exhaustive()
+    }
+}
+
+/// Generates a random token for Encryption Request
+pub fn verify_token() -> [u8; 4] {
+    let mut token = [0; 4];
+    rand::thread_rng().fill_bytes(&mut token);
+    token
+}
+
+/// Encrypts the bytes sent to a connection, which has to see every byte in order
+pub struct StreamEncryptor(cfb8::Encryptor<Aes128>);
+
+impl StreamEncryptor {
+    pub fn new(shared_secret: &[u8; 16]) -> Self {
+        Self(cfb8::Encryptor::new(shared_secret.into(), shared_secret.into()))
+    }
+
+    pub fn encrypt(&mut self, bytes: &mut [u8]) {
+        let (blocks, _) = InOutBuf::from(bytes).into_chunks();
+        self.0.encrypt_blocks_inout_mut(blocks);
+    }
+}
+
+/// Decrypts the bytes read from a connection, which has to see every byte in order
+pub struct StreamDecryptor(cfb8::Decryptor<Aes128>);
+
+impl StreamDecryptor {
+    pub fn new(shared_secret: &[u8; 16]) -> Self {
+        Self(cfb8::Decryptor::new(shared_secret.into(), shared_secret.into()))
+    }
+
+    pub fn decrypt(&mut self, bytes: &mut [u8]) {
+        let (blocks, _) = InOutBuf::from(bytes).into_chunks();
+        self.0.decrypt_blocks_inout_mut(blocks);
+    }
+}
+
+impl Debug for StreamEncryptor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StreamEncryptor").finish_non_exhaustive()
+    }
+}
+
+impl Debug for StreamDecryptor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StreamDecryptor").finish_non_exhaustive()
+    }
+}
+
+/// The reason that the encryption handshake failed
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum EncryptionError {
+    /// The key couldn't be generated, or the client sent something that wasn't encrypted with it
+    Rsa,
+    /// The shared secret wasn't 16 bytes long
+    InvalidSecretLength(usize),
+    /// The client didn't send back the verify token, so it may not have the key that was sent
+    VerifyTokenMismatch,
+}
+
+impl Display for EncryptionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Rsa => write!(f, "RSA encryption failed"),
+            Self::InvalidSecretLength(len) => write!(f, "shared secret is {len} bytes long, but it should be 16"),
+            Self::VerifyTokenMismatch => write!(f, "verify token doesn't match"),
+        }
+    }
+}
+
+impl Error for EncryptionError {}
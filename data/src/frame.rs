@@ -0,0 +1,351 @@
+//! Splitting a connection's byte stream into packets
+//!
+//! Every packet is sent as a frame, which is its length as a VarInt followed by that many bytes, starting with the packet id as a VarInt.
+//!
+//! Once the server has sent Set Compression, every frame starts with the length of its packet once uncompressed instead. That length is 0 for packets under the threshold, which are sent as they are, and the rest of the packet is zlib compressed otherwise.
+//!
+//! Encryption sits underneath framing, so once it is enabled, bytes are decrypted as they are pushed onto a `FrameDecoder` and encrypted as a `FrameEncoder` writes them.
+
+use crate::encryption::{StreamDecryptor, StreamEncryptor};
+use crate::queue::Queue;
+use crate::writer::ByteWriter;
+use bytes::{Bytes, BytesMut};
+use flate2::bufread::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::io::{Read, Write};
+
+/// The longest frame that vanilla accepts, which is the most that a 3 byte VarInt can hold
+pub const MAX_FRAME_LEN: usize = (1 << 21) - 1;
+
+/// The most bytes that a length prefix can take up, since vanilla never sends a frame longer than `MAX_FRAME_LEN`
+const MAX_PREFIX_LEN: usize = 3;
+
+/// The longest that vanilla lets a compressed packet be once it is decompressed
+pub const MAX_UNCOMPRESSED_LEN: usize = 8 * 1024 * 1024;
+
+const SEGMENT_BITS: u8 = 0x7F;
+const CONTINUE_BIT: u8 = 0x80;
+
+/// A packet id along with the rest of the packet's bytes
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Frame {
+    pub id: i32,
+    pub body: Bytes,
+}
+
+impl Frame {
+    pub fn new(id: i32, body: impl Into<Bytes>) -> Self {
+        Self { id, body: body.into() }
+    }
+
+    /// Returns a queue over the body, for deserializing the packet from
+    pub fn queue(&self) -> Queue {
+        self.body.clone().into()
+    }
+}
+
+/// Collects bytes as they are read from a connection, and splits complete frames off of them
+///
+/// Bytes can arrive in any sized pieces, so anything after the last complete frame, even half of a length prefix, is kept until the rest of it arrives.
+#[derive(Debug)]
+pub struct FrameDecoder {
+    buffer: BytesMut,
+    max_len: usize,
+    compression_threshold: Option<usize>,
+    decryptor: Option<StreamDecryptor>,
+    position: usize,
+}
+
+impl FrameDecoder {
+    pub fn new() -> Self {
+        Self::with_max_len(MAX_FRAME_LEN)
+    }
+
+    /// Creates a decoder that rejects frames longer than `max_len`
+    pub fn with_max_len(max_len: usize) -> Self {
+        Self {
+            buffer: BytesMut::new(),
+            max_len,
+            compression_threshold: None,
+            decryptor: None,
+            position: 0,
+        }
+    }
+
+    /// Starts or stops expecting compressed frames, which should be changed right after Set Compression is sent
+    pub fn set_compression(&mut self, threshold: Option<usize>) {
+        self.compression_threshold = threshold;
+    }
+
+    /// Decrypts every byte from now on, including any that are already buffered
+    ///
+    /// The client starts encrypting right after Encryption Response, so this should be called as soon as that frame has been decoded.
+    pub fn enable_encryption(&mut self, mut decryptor: StreamDecryptor) {
+        decryptor.decrypt(&mut self.buffer);
+        self.decryptor = Some(decryptor);
+    }
+
+    /// Adds bytes read from the connection, without copying them if nothing is buffered
+    pub fn push(&mut self, mut bytes: BytesMut) {
+        if let Some(decryptor) = &mut self.decryptor {
+            decryptor.decrypt(&mut bytes);
+        }
+
+        self.buffer.unsplit(bytes);
+    }
+
+    pub fn extend_from_slice(&mut self, bytes: &[u8]) {
+        let start = self.buffer.len();
+        self.buffer.extend_from_slice(bytes);
+
+        if let Some(decryptor) = &mut self.decryptor {
+            decryptor.decrypt(&mut self.buffer[start..]);
+        }
+    }
+
+    /// Returns the number of bytes that have been split off into frames so far
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Returns the number of bytes that are waiting for the rest of their frame
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Splits the next frame off of the buffer, returning `None` if it hasn't fully arrived yet
+    ///
+    /// After an error the stream can't be trusted to be at the start of a frame, so the connection should be closed.
+    pub fn decode(&mut self) -> Result<Option<Frame>, FrameError> {
+        let Some((len, prefix_len)) = self.read_len()? else {
+            return Ok(None);
+        };
+
+        // the buffer only grows as the bytes arrive, since the length alone could make it reserve 2 MiB for every connection
+        if self.buffer.len() < prefix_len + len {
+            return Ok(None);
+        }
+
+        let _ = self.buffer.split_to(prefix_len);
+        let mut queue = Queue::from(self.buffer.split_to(len));
+        let offset = self.position;
+        self.position += prefix_len + len;
+
+        if let Some(threshold) = self.compression_threshold {
+            queue = decompress(queue, threshold).map_err(|kind| FrameError::new(kind, offset))?;
+        }
+
+        let id = queue.read_var_int().ok_or(FrameError::new(FrameErrorKind::MissingId, offset))?;
+
+        Ok(Some(Frame::new(id, queue)))
+    }
+
+    /// Reads the length prefix without removing it, returning the length and how many bytes it took up
+    fn read_len(&self) -> Result<Option<(usize, usize)>, FrameError> {
+        let mut len = 0;
+        for (i, byte) in self.buffer.iter().take(MAX_PREFIX_LEN).enumerate() {
+            len |= ((byte & SEGMENT_BITS) as usize) << (i * 7);
+
+            if byte & CONTINUE_BIT == 0 {
+                if len > self.max_len {
+                    return Err(FrameError::new(FrameErrorKind::TooLong { len, max: self.max_len }, self.position));
+                }
+
+                return Ok(Some((len, i + 1)));
+            }
+        }
+
+        match self.buffer.len() {
+            len if len >= MAX_PREFIX_LEN => Err(FrameError::new(FrameErrorKind::InvalidLength, self.position)),
+            _ => Ok(None),
+        }
+    }
+}
+
+impl Default for FrameDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Checks the uncompressed length at the start of a compressed frame before inflating the rest of it
+fn decompress(mut queue: Queue, threshold: usize) -> Result<Queue, FrameErrorKind> {
+    let len = queue.read_var_int().ok_or(FrameErrorKind::InvalidLength)?;
+    let len = usize::try_from(len).map_err(|_| FrameErrorKind::InvalidLength)?;
+    if len == 0 {
+        return Ok(queue);
+    }
+    if len < threshold {
+        return Err(FrameErrorKind::BelowThreshold { len, threshold });
+    }
+    if len > MAX_UNCOMPRESSED_LEN {
+        return Err(FrameErrorKind::TooLong { len, max: MAX_UNCOMPRESSED_LEN });
+    }
+
+    // Reading one byte more than was claimed catches packets that inflate to more than their length, and the output only grows as it is inflated so that a tiny frame can't make the server allocate 8 MiB
+    let compressed = Bytes::from(queue);
+    let mut out = Vec::new();
+    ZlibDecoder::new(&compressed[..]).take(len as u64 + 1).read_to_end(&mut out).map_err(|error| FrameErrorKind::Decompression(error.kind()))?;
+    if out.len() != len {
+        return Err(FrameErrorKind::Decompression(io::ErrorKind::InvalidData));
+    }
+
+    Ok(out.into())
+}
+
+/// Writes frames onto the end of a `ByteWriter`, to be sent to a connection
+#[derive(Debug)]
+pub struct FrameEncoder {
+    max_len: usize,
+    compression_threshold: Option<usize>,
+    encryptor: Option<StreamEncryptor>,
+}
+
+impl FrameEncoder {
+    pub fn new() -> Self {
+        Self::with_max_len(MAX_FRAME_LEN)
+    }
+
+    /// Creates an encoder that refuses to write frames longer than `max_len`
+    pub fn with_max_len(max_len: usize) -> Self {
+        Self { max_len, compression_threshold: None, encryptor: None }
+    }
+
+    /// Starts or stops compressing frames whose packet is at least `threshold` bytes long, which should be changed right after Set Compression is sent
+    pub fn set_compression(&mut self, threshold: Option<usize>) {
+        self.compression_threshold = threshold;
+    }
+
+    /// Encrypts every byte that is written from now on, which should be enabled right after the client's Encryption Response is handled
+    pub fn enable_encryption(&mut self, encryptor: StreamEncryptor) {
+        self.encryptor = Some(encryptor);
+    }
+
+    /// Writes `frame` with its length prefix, writing nothing if it is too long
+    pub fn encode(&mut self, frame: &Frame, writer: &mut ByteWriter) -> Result<(), FrameError> {
+        let start = writer.len();
+        self.write_frame(frame, writer)?;
+
+        if let Some(encryptor) = &mut self.encryptor {
+            encryptor.encrypt(&mut writer.as_mut_slice()[start..]);
+        }
+
+        Ok(())
+    }
+
+    fn write_frame(&self, frame: &Frame, writer: &mut ByteWriter) -> Result<(), FrameError> {
+        let data_len = var_int_len(frame.id) + frame.body.len();
+        let too_long = |len, max| Err(FrameError::new(FrameErrorKind::TooLong { len, max }, writer.len()));
+
+        match self.compression_threshold {
+            None => {
+                if data_len > self.max_len {
+                    return too_long(data_len, self.max_len);
+                }
+
+                writer.write_var_int(data_len as i32);
+            }
+            Some(threshold) if data_len < threshold => {
+                if data_len + 1 > self.max_len {
+                    return too_long(data_len + 1, self.max_len);
+                }
+
+                writer.write_var_int(data_len as i32 + 1);
+                writer.write_var_int(0);
+            }
+            Some(_) => {
+                if data_len > MAX_UNCOMPRESSED_LEN {
+                    return too_long(data_len, MAX_UNCOMPRESSED_LEN);
+                }
+
+                let mut id = ByteWriter::with_capacity(5);
+                id.write_var_int(frame.id);
+                let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(id.as_slice()).and_then(|_| encoder.write_all(&frame.body)).expect("writing to a Vec can't fail");
+                let compressed = encoder.finish().expect("writing to a Vec can't fail");
+
+                let len = var_int_len(data_len as i32) + compressed.len();
+                if len > self.max_len {
+                    return too_long(len, self.max_len);
+                }
+
+                writer.write_var_int(len as i32);
+                writer.write_var_int(data_len as i32);
+                writer.write_bytes(&compressed);
+                return Ok(());
+            }
+        }
+
+        writer.write_var_int(frame.id);
+        writer.write_bytes(&frame.body);
+
+        Ok(())
+    }
+}
+
+impl Default for FrameEncoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn var_int_len(value: i32) -> usize {
+    match value as u32 {
+        0..0x80 => 1,
+        0x80..0x4000 => 2,
+        0x4000..0x200000 => 3,
+        0x200000..0x10000000 => 4,
+        _ => 5,
+    }
+}
+
+/// The reason that a frame couldn't be read or written
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum FrameErrorKind {
+    /// The length prefix was longer than 3 bytes, or the uncompressed length wasn't a valid VarInt or was negative
+    InvalidLength,
+    TooLong { len: usize, max: usize },
+    /// A compressed packet was shorter than the compression threshold, which vanilla rejects
+    BelowThreshold { len: usize, threshold: usize },
+    /// A compressed packet wasn't valid zlib, or didn't inflate to the length that it claimed
+    Decompression(io::ErrorKind),
+    /// The frame didn't start with a complete packet id
+    MissingId,
+}
+
+/// An error from framing a stream, along with the position in the stream where the frame started
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct FrameError {
+    pub kind: FrameErrorKind,
+    pub offset: usize,
+}
+
+impl FrameError {
+    pub fn new(kind: FrameErrorKind, offset: usize) -> Self {
+        Self { kind, offset }
+    }
+}
+
+impl Display for FrameErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidLength => write!(f, "invalid frame length"),
+            Self::TooLong { len, max } => write!(f, "frame is {len} bytes long, but the maximum is {max}"),
+            Self::BelowThreshold { len, threshold } => write!(f, "compressed packet is {len} bytes long, but the threshold is {threshold}"),
+            Self::Decompression(kind) => write!(f, "failed to decompress packet: {kind}"),
+            Self::MissingId => write!(f, "frame has no packet id"),
+        }
+    }
+}
+
+impl Display for FrameError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at byte {}", self.kind, self.offset)
+    }
+}
+
+impl Error for FrameError {}
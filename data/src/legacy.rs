@@ -0,0 +1,55 @@
+//! The server list ping from before 1.7, which old launchers and monitoring tools still send
+//!
+//! It isn't framed like the current protocol, it starts with a 0xFE byte, which a current client never sends first since it would be the start of a huge Handshake. The server answers with a kick packet holding the status as a UTF-16BE string, and then closes the connection.
+
+use crate::status::ServerStatus;
+use crate::writer::ByteWriter;
+
+/// The protocol version that vanilla claims in its legacy response, which old clients show as incompatible
+const LEGACY_PROTOCOL_VERSION: i32 = 127;
+
+/// The kinds of legacy ping that a connection can start with
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LegacyPing {
+    /// Beta 1.8 to 1.3, which only sends 0xFE
+    Beta,
+    /// 1.4 and 1.5, which send 0xFE 0x01
+    V1_4,
+    /// 1.6, which sends 0xFE 0x01 followed by an MC|PingHost plugin message
+    V1_6,
+}
+
+impl LegacyPing {
+    /// Checks whether the first bytes that a connection sent are a legacy ping, returning `None` if they should be read as current packets
+    ///
+    /// Like vanilla, this assumes that the whole ping arrived in the first read.
+    pub fn detect(bytes: &[u8]) -> Option<Self> {
+        match bytes {
+            [0xFE] => Some(Self::Beta),
+            [0xFE, 0x01] => Some(Self::V1_4),
+            [0xFE, 0x01, 0xFA, ..] => Some(Self::V1_6),
+            _ => None,
+        }
+    }
+
+    /// Encodes the kick packet that answers this ping with `status`
+    pub fn response(self, status: &ServerStatus) -> ByteWriter {
+        let motd = status.description.plain_text();
+        let message = match self {
+            // § separates the fields, so it can't be in the MOTD
+            Self::Beta => format!("{}§{}§{}", motd.replace('§', ""), status.online_players, status.max_players),
+            // NUL separates the fields here, so it is stripped from the version name and MOTD
+            Self::V1_4 | Self::V1_6 => format!("§1\0{LEGACY_PROTOCOL_VERSION}\0{}\0{}\0{}\0{}", status.version_name.replace('\0', ""), motd.replace('\0', ""), status.online_players, status.max_players),
+        };
+
+        let message = message.encode_utf16().collect::<Vec<_>>();
+        let mut writer = ByteWriter::with_capacity(3 + message.len() * 2);
+        writer.write_be(0xFFu8);
+        writer.write_be(message.len() as u16);
+        for unit in message {
+            writer.write_be(unit);
+        }
+
+        writer
+    }
+}
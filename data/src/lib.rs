@@ -1,19 +1,55 @@
 pub mod nbt;
 pub mod queue;
+pub mod writer;
+pub mod frame;
+pub mod encryption;
+pub mod auth;
 pub mod text;
 pub mod resource;
 pub mod transform;
 pub mod network;
+pub mod status;
+pub mod legacy;
+pub mod region;
+
+// Lets `#[derive(NbtCompound)]`, which refers to `data::nbt`, be used inside this crate
+extern crate self as data;
 
 #[cfg(test)]
 mod tests {
-    use crate::nbt::tag::NBTTag;
-    use crate::nbt::NBT;
+    use crate::nbt::codec::{BedrockCodec, BedrockNetworkCodec, JavaCodec};
+    use crate::nbt::compound::NBTCompound;
+    use crate::nbt::diff::NBTPatchErrorKind;
+    use crate::nbt::schema::{NBTCompoundSchema, NBTSchema};
+    use crate::nbt::convert::{NBTConvertErrorKind, NBTValue, NbtCompound};
+    use crate::nbt::tag::{NBTDecodeOptions, NBTTag, NBTTagDeserializeErrorKind, NBTTagSerializeErrorKind};
+    use crate::nbt::borrowed::NBTRef;
+    use crate::nbt::mutf8;
+    use crate::nbt::snbt::SNBTParseErrorKind;
+    use crate::nbt::path::{NBTPath, NBTPathParseErrorKind};
+    use crate::nbt::{NBTCompression, NBT};
+    use crate::auth::{offline_uuid, server_hash, AuthError, GameProfile, HttpBackend, HttpResponse, SessionService};
+    use crate::encryption::{verify_token, EncryptionError, ServerKey, StreamDecryptor, StreamEncryptor};
+    use crate::frame::{Frame, FrameDecoder, FrameEncoder, FrameErrorKind, MAX_FRAME_LEN, MAX_UNCOMPRESSED_LEN};
+    use crate::queue::Queue;
+    use crate::legacy::LegacyPing;
+    use crate::network::Connection;
+    use crate::region::RegionFile;
+    use crate::status::ServerStatus;
+    use crate::text::{ClickEvent, TextColor, TextComponent};
+    use crate::writer::ByteWriter;
+    use serde::{Deserialize, Serialize};
+    use protocol_proc::NbtCompound;
+    use uuid::Uuid;
+    use std::borrow::Cow;
+    use std::collections::HashMap;
+    use flate2::bufread::GzDecoder;
     use std::fs;
+    use std::io::Read;
 
     #[test]
     fn test_nbt_save() {
-        let nbt = NBT::new("hello world", NBTTag::Compound(vec![("name".to_string(), NBTTag::String("Bananrama".to_string()))]));
+        let nbt = NBT::new("hello world", NBTTag::Compound(vec![("name".to_string(), NBTTag::String("Bananrama".to_string()))].into()));
         let serialize = nbt.as_bytes();
 
         fs::write("test_files/test_write.nbt", serialize.clone()).expect("failed");
@@ -24,8 +60,865 @@ mod tests {
     #[test]
     fn test_nbt_read() {
         let bytes = fs::read("test_files/test_read.nbt").expect("failed");
-        let nbt = NBT::from_bytes_disk(bytes, false).expect("fad");
+        let nbt = NBT::from_bytes_disk(bytes).expect("fad");
+
+        assert_eq!(nbt, NBT::new("hello world", NBTTag::Compound(vec![("name".to_string(), NBTTag::String("Bananramas".to_string())), ("my bytes".to_string(), NBTTag::ByteArray(vec![0, 0, 0, 0, 0, 0])), ("10000".to_string(), NBTTag::Short(10000))].into())))
+    }
+
+    #[test]
+    fn test_nbt_read_truncated() {
+        let mut bytes = NBT::new("hello world", NBTTag::Compound(vec![("list".to_string(), NBTTag::List(vec![NBTTag::Compound(vec![("name".to_string(), NBTTag::String("Bananrama".to_string()))].into())]))].into())).as_bytes();
+        bytes.truncate(bytes.len() - 6);
+
+        let error = NBT::from_bytes_disk(bytes).expect_err("truncated NBT was read");
+
+        assert_eq!(error.kind, NBTTagDeserializeErrorKind::UnexpectedEnd { needed: 9, remaining: 5 });
+        assert_eq!(error.offset, 35);
+        assert_eq!(error.path, "list[0].name");
+    }
+
+    #[test]
+    fn test_nbt_read_invalid() {
+        let unknown_id = vec![0x0a, 0x00, 0x00, 0x0d, 0x00, 0x01, 0x61, 0x00];
+        let error = NBT::from_bytes_disk(unknown_id).expect_err("unknown tag id was read");
+        assert_eq!(error.kind, NBTTagDeserializeErrorKind::UnknownTagId(13));
+        assert_eq!(error.path, "a");
+
+        let negative_len = vec![0x0a, 0x00, 0x00, 0x07, 0x00, 0x01, 0x61, 0xff, 0xff, 0xff, 0xfe, 0x00];
+        let error = NBT::from_bytes_disk(negative_len).expect_err("negative length was read");
+        assert_eq!(error.kind, NBTTagDeserializeErrorKind::NegativeLength(-2));
+        assert_eq!(error.offset, 7);
+
+        let bad_utf8 = vec![0x0a, 0x00, 0x00, 0x08, 0x00, 0x01, 0x61, 0x00, 0x02, 0xc3, 0x28, 0x00];
+        let error = NBT::from_bytes_disk(bad_utf8).expect_err("invalid UTF-8 was read");
+        assert_eq!(error.kind, NBTTagDeserializeErrorKind::InvalidUtf8);
+
+        let mut nested = vec![0x09, 0x00, 0x00];
+        for _ in 0..1000 {
+            nested.extend([0x09, 0x00, 0x00, 0x00, 0x01]);
+        }
+        let error = NBTRef::from_bytes_disk(&nested).expect_err("deeply nested lists were read");
+        assert_eq!(error.kind, NBTTagDeserializeErrorKind::DepthLimitExceeded(crate::nbt::tag::MAX_DEPTH));
+        let error = NBT::from_bytes_disk(nested).expect_err("deeply nested lists were read");
+        assert_eq!(error.kind, NBTTagDeserializeErrorKind::DepthLimitExceeded(crate::nbt::tag::MAX_DEPTH));
+    }
+
+    #[test]
+    fn test_snbt_round_trip() {
+        let tag = NBTTag::Compound(vec![
+            ("name".to_string(), NBTTag::String("Banan\"rama\n".to_string())),
+            ("minecraft:custom data".to_string(), NBTTag::Compound(vec![].into())),
+            ("numbers".to_string(), NBTTag::List(vec![NBTTag::Double(1.0), NBTTag::Double(-2.5e-3)])),
+            ("values".to_string(), NBTTag::Compound(vec![("b".to_string(), NBTTag::Byte(-1)), ("s".to_string(), NBTTag::Short(2)), ("i".to_string(), NBTTag::Int(3)), ("l".to_string(), NBTTag::Long(4)), ("f".to_string(), NBTTag::Float(1.5))].into())),
+            ("bytes".to_string(), NBTTag::ByteArray(vec![1, 255])),
+            ("ints".to_string(), NBTTag::IntArray(vec![])),
+            ("longs".to_string(), NBTTag::LongArray(vec![7, -8])),
+        ].into());
+
+        let compact = tag.as_snbt();
+        assert_eq!(compact, r#"{name:'Banan"rama\n',"minecraft:custom data":{},numbers:[1.0d,-0.0025d],values:{b:-1b,s:2s,i:3,l:4L,f:1.5f},bytes:[B;1b,-1b],ints:[I;],longs:[L;7L,-8L]}"#);
+        assert_eq!(NBTTag::from_snbt(&compact), Ok(tag.clone()));
+
+        let pretty = tag.as_snbt_pretty();
+        assert!(pretty.contains("\n    values: {\n        b: -1b,\n"));
+        assert_eq!(NBTTag::from_snbt(&pretty), Ok(tag));
+        // NaN isn't equal to itself, so it is compared through its SNBT
+        let non_finite = NBTTag::List(vec![NBTTag::Float(f32::NAN), NBTTag::Float(f32::INFINITY), NBTTag::Float(f32::NEG_INFINITY)]);
+        assert_eq!(non_finite.as_snbt(), "[NaNf,Infinityf,-Infinityf]");
+        assert_eq!(NBTTag::from_snbt("[NaNf,Infinityf,-Infinityf]").map(|tag| tag.as_snbt()).as_deref(), Ok("[NaNf,Infinityf,-Infinityf]"));
+        assert_eq!(NBTTag::from_snbt("[-Infinityd]"), Ok(NBTTag::List(vec![NBTTag::Double(f64::NEG_INFINITY)])));
+        assert_eq!(NBTTag::from_snbt("[NaN]"), Ok(NBTTag::List(vec![NBTTag::String("NaN".to_string())])));
+    }
+
+    #[test]
+    fn test_snbt_parse() {
+        let tag = NBTTag::from_snbt("{ Count: 1b, 'id' : \"minecraft:stone\", flag: true, plain: hello_world, leading: 007, big: 300b, pos: [ 1.5, 2d, 3e2 ] }").expect("failed");
+
+        assert_eq!(tag, NBTTag::Compound(vec![
+            ("Count".to_string(), NBTTag::Byte(1)),
+            ("id".to_string(), NBTTag::String("minecraft:stone".to_string())),
+            ("flag".to_string(), NBTTag::Byte(1)),
+            ("plain".to_string(), NBTTag::String("hello_world".to_string())),
+            ("leading".to_string(), NBTTag::String("007".to_string())),
+            ("big".to_string(), NBTTag::String("300b".to_string())),
+            ("pos".to_string(), NBTTag::List(vec![NBTTag::Double(1.5), NBTTag::Double(2.0), NBTTag::Double(300.0)])),
+        ].into()));
+
+        assert_eq!(NBTTag::from_snbt("[1, 2b]").expect_err("mixed list was parsed").kind, SNBTParseErrorKind::MixedTypes { expected: "Int", found: "Byte" });
+        assert_eq!(NBTTag::from_snbt("[I; 1, 2L]").expect_err("mixed array was parsed").position, 7);
+        assert_eq!(NBTTag::from_snbt("{a:1").expect_err("unclosed compound was parsed").kind, SNBTParseErrorKind::UnexpectedEnd);
+        assert_eq!(NBTTag::from_snbt("{a:1} b").expect_err("trailing data was parsed").kind, SNBTParseErrorKind::TrailingData);
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    #[serde(rename_all = "PascalCase")]
+    struct TestPlayer {
+        name: String,
+        health: f32,
+        level: i32,
+        on_ground: bool,
+        #[serde(rename = "UUID")]
+        uuid: u128,
+        #[serde(with = "crate::nbt::serde::byte_array")]
+        flags: Vec<i8>,
+        #[serde(with = "crate::nbt::serde::int_array")]
+        scores: Vec<i32>,
+        #[serde(with = "crate::nbt::serde::long_array")]
+        states: Vec<i64>,
+        pos: Vec<f64>,
+        spawn: Option<i64>,
+        game_mode: TestGameMode,
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    enum TestGameMode {
+        Survival,
+        Adventure { allowed: Vec<String> },
+    }
+
+    #[test]
+    fn test_nbt_serde() {
+        let player = TestPlayer {
+            name: "Bananrama".to_string(),
+            health: 20.0,
+            level: 3,
+            on_ground: true,
+            uuid: 0x0102030405060708090a0b0c0d0e0f10,
+            flags: vec![-1, 2],
+            scores: vec![],
+            states: vec![5],
+            pos: vec![1.0, 64.0],
+            spawn: None,
+            game_mode: TestGameMode::Adventure { allowed: vec!["stone".to_string()] },
+        };
+
+        let tag = crate::nbt::serde::to_tag(&player).expect("failed");
+        assert_eq!(tag, NBTTag::Compound(vec![
+            ("Name".to_string(), NBTTag::String("Bananrama".to_string())),
+            ("Health".to_string(), NBTTag::Float(20.0)),
+            ("Level".to_string(), NBTTag::Int(3)),
+            ("OnGround".to_string(), NBTTag::Byte(1)),
+            ("UUID".to_string(), NBTTag::IntArray(vec![0x01020304, 0x05060708, 0x090a0b0c, 0x0d0e0f10])),
+            ("Flags".to_string(), NBTTag::ByteArray(vec![255, 2])),
+            ("Scores".to_string(), NBTTag::IntArray(vec![])),
+            ("States".to_string(), NBTTag::LongArray(vec![5])),
+            ("Pos".to_string(), NBTTag::List(vec![NBTTag::Double(1.0), NBTTag::Double(64.0)])),
+            ("GameMode".to_string(), NBTTag::Compound(vec![("Adventure".to_string(), NBTTag::Compound(vec![("allowed".to_string(), NBTTag::List(vec![NBTTag::String("stone".to_string())]))].into()))].into())),
+        ].into()));
+        assert_eq!(crate::nbt::serde::from_tag::<TestPlayer>(&tag), Ok(player));
+
+        let byte_array = NBTTag::ByteArray(vec![0x80, 255, 1]);
+        assert_eq!(crate::nbt::serde::from_tag::<Vec<u8>>(&byte_array), Ok(vec![0x80, 255, 1]));
+        assert_eq!(crate::nbt::serde::from_tag::<Vec<i8>>(&byte_array), Ok(vec![-128, -1, 1]));
+
+        let bytes = crate::nbt::serde::to_bytes(&HashMap::from([("mode", TestGameMode::Survival)]), "root").expect("failed");
+        let map: HashMap<String, TestGameMode> = crate::nbt::serde::from_bytes(bytes).expect("failed");
+        assert_eq!(map, HashMap::from([("mode".to_string(), TestGameMode::Survival)]));
+
+        assert_eq!(crate::nbt::serde::to_tag(&vec![Some(1), None]), Err(crate::nbt::serde::NBTSerdeError::UnsupportedType("None in a list")));
+    }
+
+    #[test]
+    fn test_nbt_borrowed() {
+        let tag = NBTTag::Compound(vec![
+            ("DataVersion".to_string(), NBTTag::Int(3955)),
+            ("Pos".to_string(), NBTTag::List(vec![NBTTag::Double(1.0), NBTTag::Double(64.0)])),
+            ("Tags".to_string(), NBTTag::List(vec![NBTTag::String("a".to_string()), NBTTag::String("b\0".to_string())])),
+            ("sections".to_string(), NBTTag::List(vec![
+                NBTTag::Compound(vec![("Y".to_string(), NBTTag::Byte(-4)), ("data".to_string(), NBTTag::LongArray(vec![1, -2, 3]))].into()),
+                NBTTag::Compound(vec![("Y".to_string(), NBTTag::Byte(-3))].into()),
+            ])),
+            ("Status".to_string(), NBTTag::String("minecraft:full".to_string())),
+            ("Heightmaps".to_string(), NBTTag::Compound(vec![("WORLD_SURFACE".to_string(), NBTTag::ByteArray(vec![7, 8]))].into())),
+        ].into());
+        let bytes = NBT::new("chunk", tag.clone()).as_bytes();
+
+        let borrowed = NBTRef::from_bytes_disk(&bytes).expect("failed");
+        assert_eq!(borrowed.root_name(), Some("chunk"));
+
+        let root = borrowed.root_tag().as_compound().expect("root is not a compound");
+        let [sections, heightmaps, missing] = root.get_many(["sections", "Heightmaps", "missing"]);
+        assert!(missing.is_none());
+        assert_eq!(root.get("Status").and_then(|tag| tag.as_str()).as_deref(), Some("minecraft:full"));
+        assert_eq!(heightmaps.and_then(|tag| tag.get("WORLD_SURFACE")).and_then(|tag| tag.as_byte_array()), Some(&[7u8, 8][..]));
+
+        let sections = sections.and_then(|tag| tag.as_list()).expect("sections is not a list");
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections.iter().map(|section| section.get("Y").and_then(|tag| tag.as_byte())).collect::<Vec<_>>(), vec![Some(-4), Some(-3)]);
+        assert_eq!(root.get("Pos").and_then(|tag| tag.as_list()).and_then(|pos| pos.get(1)).and_then(|tag| tag.as_double()), Some(64.0));
+        assert_eq!(root.get("Tags").and_then(|tag| tag.as_list()).and_then(|tags| tags.get(1)).and_then(|tag| tag.as_str()).as_deref(), Some("b\0"));
+        assert_eq!(sections.get(0).and_then(|tag| tag.get("data")).and_then(|tag| tag.as_long_array()).map(|data| data.to_vec()), Some(vec![1, -2, 3]));
+
+        assert_eq!(borrowed.root_tag().to_tag(), Ok(tag));
+
+        let error = NBTRef::from_bytes_disk(&bytes[..bytes.len() - 3]).expect_err("truncated NBT was read");
+        assert_eq!(error.path, "Heightmaps.WORLD_SURFACE");
+    }
+
+    #[test]
+    fn test_nbt_write() {
+        let nbt = NBT::new("hello world", NBTTag::Compound(vec![("name".to_string(), NBTTag::String("Bananrama".to_string())), ("longs".to_string(), NBTTag::LongArray(vec![1, 2, 3]))].into()));
+
+        for compression in [NBTCompression::None, NBTCompression::Gzip, NBTCompression::Zlib] {
+            let mut out = Vec::new();
+            nbt.write_compressed_to(&mut out, compression).expect("failed");
+
+            assert_eq!(NBT::from_bytes_disk(out), Ok(NBT::new("hello world", NBTTag::Compound(vec![("name".to_string(), NBTTag::String("Bananrama".to_string())), ("longs".to_string(), NBTTag::LongArray(vec![1, 2, 3]))].into()))));
+        }
+
+        let mixed = NBT::new("", NBTTag::Compound(vec![("list".to_string(), NBTTag::List(vec![NBTTag::Int(1), NBTTag::Int(2), NBTTag::Byte(3)]))].into()));
+        let error = mixed.write_to(&mut Vec::new()).expect_err("a mixed list was written");
+        assert!(matches!(error.kind, NBTTagSerializeErrorKind::MixedList { expected: "Int", found: "Byte" }));
+        assert_eq!(error.path, "list[2]");
+
+        let long = NBTTag::Compound(vec![("a".repeat(70000), NBTTag::Byte(0))].into());
+        let error = long.write_to(&mut Vec::new()).expect_err("a name that is too long was written");
+        assert!(matches!(error.kind, NBTTagSerializeErrorKind::StringTooLong(70000)));
+    }
+
+    #[test]
+    fn test_nbt_modified_utf8() {
+        // written by test_files/WriteMutf8.java on Java 17, through the DataOutputStream over a GZIPOutputStream that vanilla's NbtIo.writeCompressed uses
+        let bytes = fs::read("test_files/test_mutf8.nbt").expect("failed");
+        let mut java_bytes = Vec::new();
+        GzDecoder::new(&bytes[..]).read_to_end(&mut java_bytes).expect("failed");
+
+        let expected = NBT::new("mutf8", NBTTag::Compound(vec![
+            ("emoji".to_string(), NBTTag::String("Steve \u{1F3AE}\u{1F34C}".to_string())),
+            ("nul".to_string(), NBTTag::String("a\0b".to_string())),
+            ("\u{540D}\u{524D}".to_string(), NBTTag::String("\u{30D0}\u{30CA}\u{30CA}".to_string())),
+            ("\u{1F600} key".to_string(), NBTTag::Int(1)),
+            ("names".to_string(), NBTTag::List(vec![NBTTag::String("\u{1F34C}".to_string()), NBTTag::String("plain".to_string())])),
+        ].into()));
+
+        let nbt = NBT::from_bytes_disk(bytes).expect("failed");
+        assert_eq!(nbt, expected);
+        assert_eq!(nbt.as_bytes(), java_bytes);
+
+        let borrowed = NBTRef::from_bytes_disk(&java_bytes).expect("failed");
+        let root = borrowed.root_tag();
+        assert_eq!(root.get("emoji").and_then(|tag| tag.as_str()).as_deref(), Some("Steve \u{1F3AE}\u{1F34C}"));
+        assert_eq!(root.get("\u{1F600} key").and_then(|tag| tag.as_int()), Some(1));
+        assert_eq!(root.as_compound().map(|compound| compound.iter().map(|(name, _)| name.into_owned()).collect::<Vec<_>>()), Some(vec!["emoji".to_string(), "nul".to_string(), "\u{540D}\u{524D}".to_string(), "\u{1F600} key".to_string(), "names".to_string()]));
+
+        assert_eq!(mutf8::decode(&[0xED, 0xA0, 0xBD]).as_deref(), Some("\u{FFFD}"));
+        assert_eq!(mutf8::decode(&[0x61, 0xC0]), None);
+        assert!(mutf8::is_valid(&[0x61, 0xC0, 0x80, 0xED, 0xA0, 0xBD]));
+        assert!(!mutf8::is_valid(&[0x61, 0xC0]));
+        assert!(!mutf8::is_valid(&[0xF7, 0xBF, 0xBF, 0xBF]));
+    }
+
+    #[test]
+    fn test_nbt_path() {
+        let mut player = NBTTag::from_snbt(r#"{Pos:[1.0d,64.0d,-3.5d],UUID:[I;1,2,3,4],Inventory:[{Slot:0b,id:"minecraft:stick"},{Slot:1b,id:"minecraft:apple",components:{"minecraft:custom_name":"Lunch"}}]}"#).unwrap();
+
+        let path = NBTPath::parse(r#"Inventory[{Slot:1b}].components."minecraft:custom_name""#).unwrap();
+        assert_eq!(path.to_string(), r#"Inventory[{Slot:1b}].components."minecraft:custom_name""#);
+        assert_eq!(path.get(&player), vec![Cow::Borrowed(&NBTTag::String("Lunch".to_string()))]);
+        assert_eq!(NBTPath::parse("Pos[-2]").unwrap().get(&player), vec![Cow::Borrowed(&NBTTag::Double(64.0))]);
+        assert_eq!(NBTPath::parse("UUID[]").unwrap().get(&player).len(), 4);
+        assert_eq!(NBTPath::parse("Inventory[].id").unwrap().get(&player).len(), 2);
+
+        assert_eq!(NBTPath::parse("Inventory[0].components.lore[]").unwrap().set(&mut player, &NBTTag::String("Sharp".to_string())), 1);
+        assert_eq!(player["Inventory"], NBTTag::from_snbt(r#"[{Slot:0b,id:"minecraft:stick",components:{lore:["Sharp"]}},{Slot:1b,id:"minecraft:apple",components:{"minecraft:custom_name":"Lunch"}}]"#).unwrap());
+        assert_eq!(NBTPath::parse("Pos[0]").unwrap().set(&mut player, &NBTTag::Int(1)), 0);
+
+        assert_eq!(NBTPath::parse("Inventory[].components").unwrap().merge(&mut player, &NBTTag::from_snbt("{damage:3}").unwrap()), 2);
+        assert_eq!(NBTPath::parse("Inventory[{Slot:1b}].components.damage").unwrap().get(&player), vec![Cow::Borrowed(&NBTTag::Int(3))]);
+
+        assert_eq!(NBTPath::parse("Inventory[{id:\"minecraft:stick\"}]").unwrap().remove(&mut player), 1);
+        assert_eq!(NBTPath::parse("UUID[0]").unwrap().remove(&mut player), 1);
+        assert_eq!(player.as_snbt(), r#"{Pos:[1.0d,64.0d,-3.5d],UUID:[I;2,3,4],Inventory:[{Slot:1b,id:"minecraft:apple",components:{"minecraft:custom_name":"Lunch",damage:3}}]}"#);
+
+        assert_eq!(NBTPath::parse("Pos[x]").unwrap_err().kind, NBTPathParseErrorKind::InvalidIndex("x".to_string()));
+        assert_eq!(NBTPath::parse("Pos.").unwrap_err().kind, NBTPathParseErrorKind::UnexpectedEnd);
+    }
+
+    #[test]
+    fn test_nbt_compound() {
+        let mut compound = NBTCompound::from(vec![("z".to_string(), NBTTag::Int(1)), ("a".to_string(), NBTTag::Int(2)), ("m".to_string(), NBTTag::Int(3))]);
+        assert_eq!(compound.insert("z", NBTTag::Int(4)), Some(NBTTag::Int(1)));
+        assert_eq!(compound.remove("a"), Some(NBTTag::Int(2)));
+        *compound.entry("count").or_insert(NBTTag::Int(0)) = NBTTag::Int(5);
+        assert_eq!(compound.keys().collect::<Vec<_>>(), vec!["z", "m", "count"]);
+        assert_eq!(compound["count"], NBTTag::Int(5));
+
+        compound.insert("nested", NBTTag::from_snbt("{a:1,b:{c:2}}").unwrap());
+        compound.merge(&NBTCompound::from(vec![("nested".to_string(), NBTTag::from_snbt("{b:{d:3},e:4}").unwrap())]));
+        assert_eq!(compound["nested"].as_snbt(), "{a:1,b:{c:2,d:3},e:4}");
+
+        // a compound written with the same key twice keeps the last value, like vanilla
+        let bytes = [0x0a, 0x00, 0x00, 0x01, 0x00, 0x01, 0x6b, 0x01, 0x01, 0x00, 0x01, 0x6b, 0x02, 0x00];
+        let nbt = NBT::from_bytes_disk(bytes.to_vec()).unwrap();
+        assert_eq!(nbt, NBT::new("", NBTTag::Compound(vec![("k".to_string(), NBTTag::Byte(2))].into())));
+    }
+
+    #[test]
+    fn test_region_file() {
+        let directory = std::env::temp_dir().join(format!("data_test_region_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("r.1.-1.mca");
+
+        let small = NBT::new("", NBTTag::from_snbt("{DataVersion:4189,Status:\"minecraft:full\",xPos:32,zPos:-1}").unwrap());
+        let big = NBT::new("", NBTTag::Compound(vec![("data".to_string(), NBTTag::ByteArray(vec![7; 1_100_000]))].into()));
+
+        let mut region = RegionFile::open(&path).unwrap();
+        region.write_chunk(32, -1, &small).unwrap();
+        region.set_compression(NBTCompression::Lz4);
+        region.write_chunk(63, -32, &small).unwrap();
+        region.set_compression(NBTCompression::None);
+        region.write_chunk(40, -10, &big).unwrap();
+        assert!(directory.join("c.40.-10.mcc").exists());
+        drop(region);
+
+        let mut region = RegionFile::open(&path).unwrap();
+        assert_eq!(region.read_chunk(32, -1).unwrap().as_ref(), Some(&small));
+        assert_eq!(region.read_chunk(63, -32).unwrap().map(|nbt| nbt.as_snbt()), Some(small.as_snbt()));
+        assert_eq!(region.read_chunk(40, -10).unwrap().map(|nbt| nbt.as_bytes().len()), Some(big.as_bytes().len()));
+        assert_eq!(region.read_chunk(33, -1).unwrap(), None);
+        assert!(region.timestamp(32, -1) > 0);
+
+        // the big chunk now fits in the region file, so the external file is removed
+        region.write_chunk(40, -10, &small).unwrap();
+        assert!(!directory.join("c.40.-10.mcc").exists());
+
+        // new sectors are allocated before the old ones are freed, so rewriting a chunk reuses the sectors freed by the last write
+        let len = fs::metadata(&path).unwrap().len();
+        region.write_chunk(32, -1, &small).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().len(), len);
+
+        region.remove_chunk(63, -32).unwrap();
+        assert!(!region.has_chunk(63, -32));
+        assert!(RegionFile::open(&path).unwrap().read_chunk(63, -32).unwrap().is_none());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_nbt_bedrock() {
+        let nbt = || NBT::new("hi", NBTTag::from_snbt("{n:300,s:\"é\",l:[-1L]}").unwrap());
+
+        let little_endian = vec![0x0a, 0x02, 0x00, b'h', b'i', 0x03, 0x01, 0x00, b'n', 0x2c, 0x01, 0x00, 0x00, 0x08, 0x01, 0x00, b's', 0x02, 0x00, 0xc3, 0xa9, 0x09, 0x01, 0x00, b'l', 0x04, 0x01, 0x00, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00];
+        let mut out = Vec::new();
+        nbt().write_with::<BedrockCodec, _>(&mut out).unwrap();
+        assert_eq!(out, little_endian);
+        assert_eq!(NBT::from_bytes_with::<BedrockCodec>(&mut little_endian.into()), Ok(nbt()));
+
+        // ints, longs and lengths are zigzag varints, but string lengths aren't zigzagged
+        let network = vec![0x0a, 0x02, b'h', b'i', 0x03, 0x01, b'n', 0xd8, 0x04, 0x08, 0x01, b's', 0x02, 0xc3, 0xa9, 0x09, 0x01, b'l', 0x04, 0x02, 0x01, 0x00];
+        let mut out = Vec::new();
+        nbt().write_with::<BedrockNetworkCodec, _>(&mut out).unwrap();
+        assert_eq!(out, network);
+        assert_eq!(NBT::from_bytes_with::<BedrockNetworkCodec>(&mut network.into()), Ok(nbt()));
+
+        let overlong = vec![0x03, 0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01];
+        assert_eq!(NBT::from_bytes_with::<BedrockNetworkCodec>(&mut overlong.into()).unwrap_err().kind, NBTTagDeserializeErrorKind::InvalidVarInt);
+    }
+
+    #[test]
+    fn test_nbt_json() {
+        let tag = NBTTag::from_snbt("{name:\"Steve\",Health:20.0f,Pos:[0.5d,64.0d],UUID:[I;1,2,3,4],Time:9007199254740993L,Nan:NaNd,Empty:[]}").unwrap();
+        let tag = match tag {
+            NBTTag::Compound(mut c) => {
+                c.insert("Nan", NBTTag::Double(f64::NAN));
+                NBTTag::Compound(c)
+            }
+            _ => unreachable!(),
+        };
+
+        assert_eq!(tag.to_json().to_string(), r#"{"name":"Steve","Health":20.0,"Pos":[0.5,64.0],"UUID":[1,2,3,4],"Time":9007199254740993,"Nan":null,"Empty":[]}"#);
+
+        let lossless = tag.to_json_lossless();
+        assert_eq!(lossless["value"]["Time"].to_string(), r#"{"type":"Long","value":"9007199254740993"}"#);
+        let back = NBTTag::from_json_lossless(&serde_json::from_str(&lossless.to_string()).unwrap()).unwrap();
+        assert!(matches!(back["Nan"], NBTTag::Double(d) if d.is_nan()));
+        assert_eq!(back.as_snbt().replace("NaNd", ""), tag.as_snbt().replace("NaNd", ""));
+
+        let json = serde_json::json!({"flag": true, "small": 5, "big": 70000, "half": 0.5, "tenth": 0.1, "bytes": [1, 2], "mixed": [1, "a", {"b": 2}], "skip": null});
+        assert_eq!(NBTTag::from_json(&json).unwrap().as_snbt(), r#"{flag:1b,small:5b,big:70000,half:0.5f,tenth:0.1d,bytes:[B;1b,2b],mixed:[{"":1b},{"":"a"},{b:2b}]}"#);
+
+        let invalid = serde_json::json!({"type": "Compound", "value": {"a": {"type": "Byte", "value": 300}}});
+        assert_eq!(NBTTag::from_json_lossless(&invalid).unwrap_err().to_string(), "value isn't a valid Byte (in 'a')");
+    }
+
+    #[test]
+    fn test_nbt_derive() {
+        #[derive(NbtCompound, PartialEq, Debug)]
+        #[nbt(rename_all = "PascalCase")]
+        struct Entity {
+            #[nbt(rename = "id")]
+            kind: String,
+            #[nbt(rename = "UUID")]
+            uuid: Uuid,
+            pos: Vec<f64>,
+            on_ground: bool,
+            custom_name: Option<String>,
+            #[nbt(default = "default_air")]
+            air: i16,
+            #[nbt(flatten)]
+            living: Living,
+            #[nbt(int_array, rename = "Colors")]
+            colors: Vec<i32>,
+            #[nbt(default)]
+            tick_times: Vec<i64>,
+        }
+
+        #[derive(NbtCompound, PartialEq, Debug)]
+        struct Living {
+            #[nbt(rename = "Health")]
+            health: f32,
+            #[nbt(default, rename = "active_effects")]
+            effects: Vec<Effect>,
+        }
+
+        #[derive(NbtCompound, PartialEq, Debug)]
+        #[nbt(rename_all = "camelCase")]
+        struct Effect {
+            effect_id: String,
+            show_icon: bool,
+        }
+
+        fn default_air() -> i16 {
+            300
+        }
+
+        let entity = Entity {
+            kind: "minecraft:pig".to_string(),
+            uuid: Uuid::from_u128(0x0000000100000002_0000000300000004),
+            pos: vec![0.5, 64.0, -0.5],
+            on_ground: true,
+            custom_name: None,
+            air: 20,
+            living: Living { health: 10.0, effects: vec![Effect { effect_id: "minecraft:speed".to_string(), show_icon: false }] },
+            colors: vec![0xff0000],
+            tick_times: vec![5, 6],
+        };
+
+        let tag = entity.to_tag();
+        assert_eq!(tag.as_snbt(), r#"{id:"minecraft:pig",UUID:[I;1,2,3,4],Pos:[0.5d,64.0d,-0.5d],OnGround:1b,Air:20s,Health:10.0f,active_effects:[{effectId:"minecraft:speed",showIcon:0b}],Colors:[I;16711680],TickTimes:[5L,6L]}"#);
+        assert_eq!(Entity::from_tag(&tag).unwrap(), entity);
+
+        let minimal = NBTTag::from_snbt(r#"{id:"minecraft:cow",UUID:[I;0,0,0,1],Pos:[0d,0d,0d],OnGround:0b,Health:7,Colors:[1,2]}"#).unwrap();
+        let cow = Entity::from_tag(&minimal).unwrap();
+        assert_eq!((cow.air, cow.living.health, cow.living.effects.len(), cow.custom_name), (300, 7.0, 0, None));
+        assert_eq!((cow.colors, cow.tick_times), (vec![1, 2], vec![]));
+        let array = NBTTag::from_snbt(r#"{id:"minecraft:cow",UUID:[I;0,0,0,1],Pos:[0d,0d,0d],OnGround:0b,Health:7,Colors:[I;1],TickTimes:[L;1L]}"#).unwrap();
+        assert_eq!(Entity::from_tag(&array).unwrap_err().to_string(), "expected List but found LongArray (in 'TickTimes')");
+
+        let NBTTag::Compound(mut compound) = minimal else { unreachable!() };
+        compound.remove("Health");
+        let error = Entity::from_compound(&compound).unwrap_err();
+        assert_eq!((error.kind, error.path.as_str()), (NBTConvertErrorKind::Missing, "Health"));
+
+        compound.insert("Pos", NBTTag::from_snbt(r#"["up"]"#).unwrap());
+        let error = Entity::from_compound(&compound).unwrap_err();
+        assert_eq!(error.to_string(), "expected Double but found String (in 'Pos[0]')");
+    }
+
+    #[test]
+    fn test_nbt_diff() {
+        let old = NBTTag::from_snbt("{Health:20.0f,Pos:[0.0d,64.0d,0.0d],Tags:[a,b],Inventory:[{Slot:0b,id:stone}],Fire:-20s}").unwrap();
+        let new = NBTTag::from_snbt("{Health:18.5f,Pos:[0.0d,65.0d,0.0d],Tags:[a,b,c],Inventory:[{Slot:0b,id:dirt,count:2}],Air:300s}").unwrap();
+
+        let diff = old.diff(&new);
+        assert_eq!(diff.to_string(), [
+            "~ Health: 20.0f -> 18.5f",
+            "~ Pos[1]: 64.0d -> 65.0d",
+            "~ Tags: [\"a\",\"b\"] -> [\"a\",\"b\",\"c\"]",
+            "~ Inventory[0].id: \"stone\" -> \"dirt\"",
+            "+ Inventory[0].count: 2",
+            "- Fire: -20s",
+            "+ Air: 300s",
+        ].join("\n"));
+        assert!(new.diff(&new).is_empty());
+
+        let mut patched = old.clone();
+        diff.apply(&mut patched).unwrap();
+        assert!(patched.diff(&new).is_empty());
+        diff.reversed().apply(&mut patched).unwrap();
+        assert_eq!(patched, old);
+
+        let error = diff.apply(&mut NBTTag::from_snbt("{Health:5.0f}").unwrap()).unwrap_err();
+        assert_eq!(error.kind, NBTPatchErrorKind::Conflict { expected: Some(Box::new(NBTTag::Float(20.0))), found: Some(Box::new(NBTTag::Float(5.0))) });
+        assert_eq!(error.path, "Health");
+
+        // the last change conflicts, so the ones before it are undone, keeping the order of the entries
+        let snbt = r#"{Health:20.0f,Pos:[0.0d,64.0d,0.0d],Tags:["a","b"],Inventory:[{Slot:0b,id:"stone"}],Fire:-20s,Air:0s}"#;
+        let mut conflicting = NBTTag::from_snbt(snbt).unwrap();
+        let error = diff.apply(&mut conflicting).unwrap_err();
+        assert_eq!((error.path.as_str(), conflicting.as_snbt()), ("Air", snbt.to_string()));
+
+        let root = NBTTag::Int(1).diff(&NBTTag::String("one".to_string()));
+        let mut tag = NBTTag::Int(1);
+        root.apply(&mut tag).unwrap();
+        assert_eq!((root.to_string(), tag), ("~ root: 1 -> \"one\"".to_string(), NBTTag::String("one".to_string())));
+    }
+
+    #[test]
+    fn test_nbt_schema() {
+        let item = NBTCompoundSchema::new()
+            .required("Slot", NBTSchema::Byte(0..=35))
+            .required("id", NBTSchema::String)
+            .optional("count", NBTSchema::Int(1..=99))
+            .deny_unknown();
+        let player = NBTSchema::from(NBTCompoundSchema::new()
+            .required("Health", NBTSchema::Float(0.0..=20.0))
+            .required("Pos", NBTSchema::list(NBTSchema::double()))
+            .required("Inventory", NBTSchema::list(item.into()))
+            .optional("playerGameType", NBTSchema::Int(0..=3)));
+
+        let valid = NBTTag::from_snbt("{Health:20.0f,Pos:[0.5d,64.0d,0.5d],Inventory:[{Slot:0b,id:stone,count:64}],Score:7}").unwrap();
+        assert!(valid.validate(&player).is_empty());
+        assert!(NBT::new("", valid).validate(&player).is_empty());
+
+        let invalid = NBTTag::from_snbt("{Health:25.0f,Pos:[0.5d,64.0d],Inventory:[{Slot:0b,id:stone},{Slot:40b,count:0,Damage:3}],playerGameType:1b}").unwrap();
+        let violations = invalid.validate(&player).iter().map(ToString::to_string).collect::<Vec<_>>();
+        assert_eq!(violations, [
+            "25.0f is out of range (in 'Health')",
+            "40b is out of range (in 'Inventory[1].Slot')",
+            "missing required tag (in 'Inventory[1].id')",
+            "0 is out of range (in 'Inventory[1].count')",
+            "unknown tag (in 'Inventory[1].Damage')",
+            "expected Int but found Byte (in 'playerGameType')",
+        ]);
+    }
+
+    #[test]
+    fn test_nbt_decode_limits() {
+        let mut long_list = vec![0x09, 0x01];
+        long_list.extend(70_000i32.to_be_bytes());
+        long_list.extend([0; 70_000]);
+
+        let error = NBT::from_bytes_network(&mut long_list.clone().into()).expect_err("a list over the network limit was read");
+        assert_eq!((error.kind, error.offset), (NBTTagDeserializeErrorKind::LengthLimitExceeded { len: 70_000, max: 64 * 1024 }, 2));
+        assert!(NBT::from_bytes_network_with_options(&mut long_list.into(), &NBTDecodeOptions::DISK).is_ok());
+
+        let mut ints = vec![0x0b];
+        ints.extend(1000i32.to_be_bytes());
+        ints.extend([0; 4000]);
+        let small = NBTDecodeOptions { max_bytes: 1000, ..NBTDecodeOptions::NETWORK };
+        let error = NBTTag::from_bytes_with_options::<JavaCodec>(&mut ints[1..].to_vec().into(), 0x0b, &small).expect_err("an array over the size limit was read");
+        assert_eq!(error.kind, NBTTagDeserializeErrorKind::SizeLimitExceeded(1000));
+
+        let mut nested = vec![0x0a, 0x00, 0x00];
+        for _ in 0..5 {
+            nested.extend([0x0a, 0x00, 0x01, b'a']);
+        }
+        nested.extend([0x00; 6]);
+        let shallow = NBTDecodeOptions { max_depth: 4, ..NBTDecodeOptions::DISK };
+        let error = NBT::from_bytes_with_options::<JavaCodec>(&mut nested.clone().into(), &shallow).expect_err("tags nested past the limit were read");
+        assert_eq!((error.kind, error.path.as_str()), (NBTTagDeserializeErrorKind::DepthLimitExceeded(4), "a.a.a.a"));
+        assert!(NBT::from_bytes_disk(nested).is_ok());
+    }
+
+    #[test]
+    fn test_queue() {
+        let mut queue = Queue::from(vec![0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f, 0x10, 0xff, 0xfe, 0x3f, 0x80, 0x00, 0x00]);
+        assert_eq!(queue.peek_be::<u16>(), Some(0x0102));
+        assert_eq!(queue.peek_le::<u16>(), Some(0x0201));
+        assert_eq!(queue.read_be::<u128>(), Some(0x0102030405060708090a0b0c0d0e0f10));
+        assert_eq!(queue.read_le::<i16>(), Some(-257));
+        assert_eq!(queue.read_be::<f32>(), Some(1.0));
+        assert_eq!(queue.read_be::<u8>(), None);
+
+        let mut queue = Queue::from(vec![0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0xaa]);
+        assert_eq!(queue.read_le::<i128>(), Some(0x01000000_00000000_00000000_00000080));
+        assert_eq!(queue.read_be::<i32>(), None);
+        assert_eq!(queue.position(), 16);
+        assert_eq!(queue.read_bytes(2), None);
+        assert_eq!(queue.read_bytes(1), Some(&[0xaa][..]));
+        assert_eq!(queue.read_bytes(0), Some(&[][..]));
+        assert_eq!(queue.bytes_left(), 0);
+    }
+
+    #[test]
+    fn test_byte_writer() {
+        let mut writer = ByteWriter::new();
+        writer.write_be(0x0102u16);
+        writer.write_le(0x0102u16);
+        writer.write_be(-1i128);
+        writer.write_var_int(300);
+        writer.write_var_int(-1);
+        writer.write_var_long(i64::MIN);
+        let len = writer.reserve(4);
+        writer.write_bytes(b"abc");
+        writer.patch_be(len, 3u32);
+
+        let mut queue = Queue::from(Vec::from(writer.clone()));
+        assert_eq!((queue.read_be::<u16>(), queue.read_le::<u16>(), queue.read_be::<i128>()), (Some(0x0102), Some(0x0102), Some(-1)));
+        assert_eq!(queue.read_bytes(3), Some(&[0xac, 0x02, 0xff][..]));
+        assert_eq!(queue.read_bytes(4), Some(&[0xff, 0xff, 0xff, 0x0f][..]));
+        assert_eq!(queue.read_bytes(10), Some(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x01][..]));
+        assert_eq!((queue.read_be::<u32>(), queue.read_bytes(3)), (Some(3), Some(&b"abc"[..])));
+
+        let mut writer = ByteWriter::new();
+        writer.write_length_prefixed(|writer| writer.write_bytes(&[7; 5]));
+        writer.write_length_prefixed(|writer| writer.write_bytes(&[9; 200]));
+        let bytes = Vec::from(writer);
+        assert_eq!(&bytes[..6], &[5, 7, 7, 7, 7, 7]);
+        assert_eq!(&bytes[6..9], &[0xc8, 0x01, 9]);
+        assert_eq!(bytes.len(), 6 + 2 + 200);
+    }
+
+    #[test]
+    fn test_queue_rollback() {
+        let mut writer = ByteWriter::new();
+        writer.write_var_int(-2);
+        writer.write_var_long(1 << 40);
+        writer.write_var_int(25565);
+        let bytes = Vec::from(writer);
+
+        let mut partial = Queue::from(bytes[..7].to_vec());
+        assert_eq!(partial.read_var_int(), Some(-2));
+        assert_eq!(partial.read_var_long(), None);
+        assert_eq!(partial.position(), 5);
+
+        let mut queue = Queue::from(bytes);
+        let mark = queue.mark();
+        assert_eq!((queue.read_var_int(), queue.read_var_long(), queue.read_var_int()), (Some(-2), Some(1 << 40), Some(25565)));
+        queue.reset(mark);
+        assert_eq!(queue.try_read(|queue| queue.read_var_int().zip(queue.read_be::<u128>())), None);
+        assert_eq!(queue.position(), 0);
+
+        let mut too_long = Queue::from(vec![0xff; 6]);
+        assert_eq!((too_long.read_var_int(), too_long.position()), (None, 0));
+    }
+
+    #[test]
+    fn test_bytes_integration() {
+        use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+        let mut writer = ByteWriter::new();
+        writer.put_u16(0x1234);
+        writer.put_slice(b"frame");
+        writer.write_be(7u32);
+        let frame = writer.split();
+        assert!(writer.is_empty());
+        assert_eq!(&frame[..], &[0x12, 0x34, b'f', b'r', b'a', b'm', b'e', 0, 0, 0, 7]);
+
+        let mut queue = Queue::from(BytesMut::from(&frame[..]));
+        assert_eq!(queue.get_u16(), 0x1234);
+        let mut inner = queue.split_to(5).unwrap();
+        assert_eq!(inner.copy_to_bytes(5), Bytes::from_static(b"frame"));
+        assert!(queue.split_to(5).is_none());
+        assert_eq!(Bytes::from(queue), Bytes::from_static(&[0, 0, 0, 7]));
+    }
+
+    #[test]
+    fn test_frame_codec() {
+        let frames = [Frame::new(0, vec![]), Frame::new(0x2a, vec![7; 300]), Frame::new(-1, b"last".to_vec())];
+        let mut writer = ByteWriter::new();
+        for frame in &frames {
+            FrameEncoder::new().encode(frame, &mut writer).unwrap();
+        }
+        let stream = writer.split();
+        assert_eq!(&stream[..5], &[1, 0, 0xad, 0x02, 0x2a]);
+
+        let decode_all = |decoder: &mut FrameDecoder, decoded: &mut Vec<Frame>| {
+            while let Some(frame) = decoder.decode().unwrap() {
+                decoded.push(frame);
+            }
+        };
+        for split in 0..=stream.len() {
+            let mut decoder = FrameDecoder::new();
+            let mut decoded = Vec::new();
+            decoder.extend_from_slice(&stream[..split]);
+            decode_all(&mut decoder, &mut decoded);
+            decoder.extend_from_slice(&stream[split..]);
+            decode_all(&mut decoder, &mut decoded);
+            assert_eq!(decoded, frames, "split at {split}");
+            assert_eq!(decoder.buffered(), 0);
+        }
+
+        let mut decoder = FrameDecoder::new();
+        let mut decoded = Vec::new();
+        for byte in stream.iter() {
+            decoder.extend_from_slice(&[*byte]);
+            decode_all(&mut decoder, &mut decoded);
+        }
+        assert_eq!(decoded, frames);
+
+        let mut too_long = FrameDecoder::with_max_len(100);
+        too_long.extend_from_slice(&[0xc8, 0x01]);
+        assert_eq!(too_long.decode().unwrap_err().kind, FrameErrorKind::TooLong { len: 200, max: 100 });
+        let mut largest = FrameDecoder::new();
+        largest.extend_from_slice(&[0xff, 0xff, 0x7f]);
+        assert_eq!((largest.decode(), largest.buffered()), (Ok(None), 3));
+        let mut smaller = FrameDecoder::with_max_len(MAX_FRAME_LEN - 1);
+        smaller.extend_from_slice(&[0xff, 0xff, 0x7f]);
+        assert_eq!(smaller.decode().unwrap_err().kind, FrameErrorKind::TooLong { len: MAX_FRAME_LEN, max: MAX_FRAME_LEN - 1 });
+        for prefix in [&[0xff; 3][..], &[0x80, 0x80, 0x80, 0x00]] {
+            let mut invalid = FrameDecoder::new();
+            invalid.extend_from_slice(prefix);
+            assert_eq!(invalid.decode().unwrap_err().kind, FrameErrorKind::InvalidLength);
+        }
+        assert!(FrameEncoder::with_max_len(4).encode(&frames[2], &mut writer).is_err());
+        assert!(writer.is_empty());
+    }
+
+    #[test]
+    fn test_frame_compression() {
+        let frames = [Frame::new(1, vec![3; 10]), Frame::new(2, vec![4; 5000]), Frame::new(3, vec![])];
+        let mut encoder = FrameEncoder::new();
+        encoder.set_compression(Some(64));
+        let mut writer = ByteWriter::new();
+        for frame in &frames {
+            encoder.encode(frame, &mut writer).unwrap();
+        }
+        let stream = writer.split();
+        assert_eq!(&stream[..3], &[12, 0, 1]);
+        assert!(stream.len() < 200);
+
+        for split in 0..=stream.len() {
+            let mut decoder = FrameDecoder::new();
+            decoder.set_compression(Some(64));
+            let mut decoded = Vec::new();
+            decoder.extend_from_slice(&stream[..split]);
+            while let Some(frame) = decoder.decode().unwrap() {
+                decoded.push(frame);
+            }
+            decoder.extend_from_slice(&stream[split..]);
+            while let Some(frame) = decoder.decode().unwrap() {
+                decoded.push(frame);
+            }
+            assert_eq!(decoded, frames, "split at {split}");
+        }
+
+        let decode = |frame: &[u8]| {
+            let mut decoder = FrameDecoder::new();
+            decoder.set_compression(Some(64));
+            decoder.extend_from_slice(frame);
+            decoder.decode().map(|frame| frame.map(|frame| frame.id))
+        };
+        let mut compressed = stream[13..].to_vec();
+        assert_eq!(decode(&compressed), Ok(Some(2)));
+        compressed[2] += 1;
+        assert_eq!(decode(&compressed).unwrap_err().kind, FrameErrorKind::Decompression(std::io::ErrorKind::InvalidData));
+        assert_eq!(decode(&[2, 10, 1]).unwrap_err().kind, FrameErrorKind::BelowThreshold { len: 10, threshold: 64 });
+        assert_eq!(decode(&[5, 0x80, 0x80, 0x80, 0x08, 0]).unwrap_err().kind, FrameErrorKind::TooLong { len: 1 << 24, max: MAX_UNCOMPRESSED_LEN });
+    }
+
+    #[test]
+    fn test_frame_encryption() {
+        use rsa::pkcs8::DecodePublicKey;
+
+        let key = ServerKey::generate().unwrap();
+        let public_key = rsa::RsaPublicKey::from_public_key_der(key.public_key_der()).unwrap();
+        let token = verify_token();
+        let secret = [7; 16];
+        let encrypt = |data: &[u8]| public_key.encrypt(&mut rand::thread_rng(), rsa::Pkcs1v15Encrypt, data).unwrap();
+        assert_eq!(key.decrypt_response(&encrypt(&secret), &encrypt(&token), &token), Ok(secret));
+        assert_eq!(key.decrypt_response(&encrypt(&secret), &encrypt(&[0; 4]), &token), Err(EncryptionError::VerifyTokenMismatch));
+
+        // the client encrypts everything after its Encryption Response, which can arrive in the same read
+        let mut client = StreamEncryptor::new(&secret);
+        let mut stream = ByteWriter::new();
+        FrameEncoder::new().encode(&Frame::new(1, b"response".to_vec()), &mut stream).unwrap();
+        let start = stream.len();
+        FrameEncoder::new().encode(&Frame::new(2, vec![9; 300]), &mut stream).unwrap();
+        FrameEncoder::new().encode(&Frame::new(3, vec![]), &mut stream).unwrap();
+        client.encrypt(&mut stream.as_mut_slice()[start..]);
+        let stream = stream.split();
+
+        let mut connection = Connection::default();
+        connection.decoder.extend_from_slice(&stream[..start + 1]);
+        assert_eq!(connection.decoder.decode().unwrap().unwrap().id, 1);
+        connection.enable_encryption(&secret);
+        for chunk in stream[start + 1..].chunks(7) {
+            connection.decoder.push(chunk.into());
+        }
+        assert_eq!(connection.decoder.decode().unwrap(), Some(Frame::new(2, vec![9; 300])));
+        assert_eq!(connection.decoder.decode().unwrap(), Some(Frame::new(3, vec![])));
+
+        let mut sent = ByteWriter::new();
+        connection.encoder.encode(&Frame::new(4, b"hello".to_vec()), &mut sent).unwrap();
+        assert_ne!(&sent.as_slice()[2..], b"hello");
+        let mut client_decoder = FrameDecoder::new();
+        client_decoder.enable_encryption(StreamDecryptor::new(&secret));
+        client_decoder.push(sent.as_slice().into());
+        assert_eq!(client_decoder.decode().unwrap(), Some(Frame::new(4, b"hello".to_vec())));
+    }
+
+    #[test]
+    fn test_session_auth() {
+        assert_eq!(server_hash("Notch", &[], &[]), "4ed1f46bbe04bc756bcb17c0c7ce3e4632f06a48");
+        assert_eq!(server_hash("jeb_", &[], &[]), "-7c9d5b0044c130109a5d7b5fb5c317c02b4e28c1");
+        assert_eq!(server_hash("simon", &[], &[]), "88e16a1019277b15d58faf0541e11910eb756f6");
+        assert_eq!(offline_uuid("Notch").to_string(), "b50ad385-829d-3141-a216-7e7d7539ba7f");
+        assert_eq!(GameProfile::offline("Notch").id.get_version_num(), 3);
+
+        struct StandIn;
+        impl HttpBackend for StandIn {
+            fn get(&self, url: &str) -> Result<HttpResponse, String> {
+                let body = match url {
+                    "http://localhost/session/minecraft/hasJoined?username=Notch&serverId=abc&ip=127.0.0.1"
+                    | "http://localhost/session/minecraft/hasJoined?username=Notch&serverId=-abc&ip=%3A%3A1" => r#"{"id":"069a79f444e94726a5befca90e38aaf5","name":"Notch","properties":[{"name":"textures","value":"e30=","signature":"c2ln"}]}"#,
+                    _ => return Ok(HttpResponse { status: 204, body: Vec::new() }),
+                };
+
+                Ok(HttpResponse { status: 200, body: body.into() })
+            }
+        }
+
+        let service = SessionService::new(StandIn, "http://localhost/");
+        let profile = service.has_joined("Notch", "abc", Some([127, 0, 0, 1].into())).unwrap();
+        assert_eq!(profile.id.to_string(), "069a79f4-44e9-4726-a5be-fca90e38aaf5");
+        assert_eq!(profile.textures().and_then(|textures| textures.signature.as_deref()), Some("c2ln"));
+        assert_eq!(service.has_joined("Cracked", "abc", None), Err(AuthError::NotAuthenticated));
+        // the query is percent-encoded, so names can't add parameters of their own
+        assert!(service.has_joined("Notch", "-abc", Some(std::net::Ipv6Addr::LOCALHOST.into())).is_ok());
+        assert_eq!(service.has_joined("Notch&serverId=abc&ip=127.0.0.1", "x", None), Err(AuthError::NotAuthenticated));
+
+        // the address is only checked with prevent-proxy-connections
+        let mut service = service;
+        let localhost = Some([127, 0, 0, 1].into());
+        assert_eq!(service.spawn_has_joined("Notch".into(), "abc".into(), localhost).join(), Err(AuthError::NotAuthenticated));
+        service.prevent_proxy_connections = true;
+        assert_eq!(service.spawn_has_joined("Notch".into(), "abc".into(), localhost).join().map(|profile| profile.name), Ok("Notch".to_string()));
+    }
+
+    #[test]
+    fn test_server_status() {
+        let motd = TextComponent::text("Hello ").with_color(TextColor::Gold).bold()
+            .append(TextComponent::text("world").with_color(TextColor::Custom(0xff)).not_bold().on_click(ClickEvent::OpenURL("https://example.com".into())));
+        assert_eq!(motd.to_string(), r##"{"text":"Hello ","color":"gold","bold":true,"extra":[{"text":"world","color":"#0000ff","bold":false,"clickEvent":{"action":"open_url","value":"https://example.com"}}]}"##);
+
+        let mut status = ServerStatus::new(motd, 100);
+        status.online_players = 1;
+        status.sample.push(("Notch".into(), uuid::Uuid::from_u128(0x069a79f444e94726a5befca90e38aaf5)));
+        status.set_favicon(&[0x89, b'P', b'N', b'G']);
+
+        let json = status.to_json();
+        assert_eq!(json["version"]["protocol"], 769);
+        assert_eq!(json["players"]["sample"][0]["id"], "069a79f4-44e9-4726-a5be-fca90e38aaf5");
+        assert_eq!(json["description"]["extra"][0]["text"], "world");
+        assert_eq!(json["favicon"], "data:image/png;base64,iVBORw==");
+        assert_eq!(json["enforcesSecureChat"], false);
+    }
+
+    #[test]
+    fn test_legacy_ping() {
+        assert_eq!(LegacyPing::detect(&[0xFE]), Some(LegacyPing::Beta));
+        assert_eq!(LegacyPing::detect(&[0xFE, 0x01]), Some(LegacyPing::V1_4));
+        assert_eq!(LegacyPing::detect(&[0xFE, 0x01, 0xFA, 0x00, 0x0B]), Some(LegacyPing::V1_6));
+        assert_eq!(LegacyPing::detect(&[0x10, 0x00]), None);
+
+        let mut status = ServerStatus::new(TextComponent::text("A §server").append(TextComponent::text("!")), 20);
+        status.online_players = 3;
+
+        let utf16 = |message: &str| message.encode_utf16().flat_map(u16::to_be_bytes).collect::<Vec<_>>();
+        let beta = Vec::from(LegacyPing::Beta.response(&status));
+        assert_eq!(&beta[..3], &[0xFF, 0, 14]);
+        assert_eq!(&beta[3..], &utf16("A server!§3§20")[..]);
+
+        let v1_6 = Vec::from(LegacyPing::V1_6.response(&status));
+        let message = "§1\x00127\x001.21.4\x00A §server!\x003\x0020";
+        assert_eq!(&v1_6[1..3], &(message.encode_utf16().count() as u16).to_be_bytes());
+        assert_eq!(&v1_6[3..], &utf16(message)[..]);
 
-        assert_eq!(nbt, NBT::new("hello world", NBTTag::Compound(vec![("name".to_string(), NBTTag::String("Bananramas".to_string())), ("my bytes".to_string(), NBTTag::ByteArray(vec![0, 0, 0, 0, 0, 0])), ("10000".to_string(), NBTTag::Short(10000))])))
+        status.description = TextComponent::text("A\0B");
+        let v1_4 = Vec::from(LegacyPing::V1_4.response(&status));
+        assert_eq!(&v1_4[3..], &utf16("§1\x00127\x001.21.4\x00AB\x003\x0020")[..]);
     }
 }
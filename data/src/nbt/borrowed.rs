@@ -0,0 +1,502 @@
+use crate::nbt::mutf8;
+use crate::nbt::tag::{NBTTag, NBTTagDeserializeError, NBTTagDeserializeErrorKind, MAX_DEPTH};
+use crate::queue::Queue;
+use std::borrow::Cow;
+use std::marker::PhantomData;
+
+/// A borrowed view of binary NBT that is only parsed as it is accessed
+///
+/// Creating the view walks over the data once to check that it is valid, without allocating anything. After that, strings and arrays are returned as slices of the original data, and compound lookups only skip over the entries before the one that was asked for, using the length prefixes without checking anything again.
+///
+/// The data has to already be decompressed, since a view can't borrow from data that it decompressed itself.
+#[derive(Clone, Debug)]
+pub struct NBTRef<'a> {
+    root_name: Option<Cow<'a, str>>,
+    root_tag: NBTTagRef<'a>,
+}
+
+impl<'a> NBTRef<'a> {
+    /// Reads a named NBT, like the ones that are stored on disk
+    pub fn from_bytes_disk(bytes: &'a [u8]) -> Result<Self, NBTTagDeserializeError> {
+        let id = read_u8(bytes, 0)?;
+        let (name, start) = read_str(bytes, 1)?;
+        let name = mutf8::decode(name).expect("the name was checked when it was read");
+
+        Ok(Self {
+            root_name: Some(name),
+            root_tag: NBTTagRef::from_bytes(bytes, start, id)?,
+        })
+    }
+
+    /// Reads a nameless network NBT
+    pub fn from_bytes_network(bytes: &'a [u8]) -> Result<Self, NBTTagDeserializeError> {
+        let id = read_u8(bytes, 0)?;
+
+        Ok(Self {
+            root_name: None,
+            root_tag: NBTTagRef::from_bytes(bytes, 1, id)?,
+        })
+    }
+
+    pub fn root_name(&self) -> Option<&str> {
+        self.root_name.as_deref()
+    }
+
+    pub fn root_tag(&self) -> NBTTagRef<'a> {
+        self.root_tag
+    }
+}
+
+/// A borrowed view of a single NBT tag's payload
+#[derive(Clone, Copy, Debug)]
+pub struct NBTTagRef<'a> {
+    id: u8,
+    payload: &'a [u8],
+}
+
+impl<'a> NBTTagRef<'a> {
+    /// Checks the tag with the given id that starts at `start` in `bytes`, returning a view of it
+    fn from_bytes(bytes: &'a [u8], start: usize, id: u8) -> Result<Self, NBTTagDeserializeError> {
+        let end = skip(bytes, start, id, 0)?;
+
+        Ok(Self { id, payload: &bytes[start..end] })
+    }
+
+    /// Makes a view of a tag that is already known to be valid, starting at `start` in `bytes`
+    fn from_valid(bytes: &'a [u8], start: usize, id: u8) -> (Self, usize) {
+        let end = skip_valid(bytes, start, id);
+
+        (Self { id, payload: &bytes[start..end] }, end)
+    }
+
+    pub fn id(&self) -> u8 {
+        self.id
+    }
+
+    /// Returns the raw big-endian payload of this tag
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.payload
+    }
+
+    pub fn as_byte(&self) -> Option<i8> {
+        (self.id == 1).then(|| self.payload[0] as i8)
+    }
+
+    pub fn as_short(&self) -> Option<i16> {
+        (self.id == 2).then(|| i16::from_be_bytes(self.payload.try_into().expect("a short is 2 bytes")))
+    }
+
+    pub fn as_int(&self) -> Option<i32> {
+        (self.id == 3).then(|| i32::from_be_bytes(self.payload.try_into().expect("an int is 4 bytes")))
+    }
+
+    pub fn as_long(&self) -> Option<i64> {
+        (self.id == 4).then(|| i64::from_be_bytes(self.payload.try_into().expect("a long is 8 bytes")))
+    }
+
+    pub fn as_float(&self) -> Option<f32> {
+        (self.id == 5).then(|| f32::from_be_bytes(self.payload.try_into().expect("a float is 4 bytes")))
+    }
+
+    pub fn as_double(&self) -> Option<f64> {
+        (self.id == 6).then(|| f64::from_be_bytes(self.payload.try_into().expect("a double is 8 bytes")))
+    }
+
+    pub fn as_byte_array(&self) -> Option<&'a [u8]> {
+        (self.id == 7).then(|| &self.payload[4..])
+    }
+
+    /// Returns the string, which is only copied if it had to be decoded from Modified UTF-8
+    pub fn as_str(&self) -> Option<Cow<'a, str>> {
+        if self.id != 8 {
+            return None;
+        }
+
+        mutf8::decode(&self.payload[2..])
+    }
+
+    pub fn as_list(&self) -> Option<ListRef<'a>> {
+        if self.id != 9 {
+            return None;
+        }
+
+        Some(ListRef {
+            element_id: self.payload[0],
+            len: valid_len(self.payload, 1),
+            elements: &self.payload[5..],
+        })
+    }
+
+    pub fn as_compound(&self) -> Option<CompoundRef<'a>> {
+        (self.id == 10).then_some(CompoundRef { entries: self.payload })
+    }
+
+    pub fn as_int_array(&self) -> Option<ArrayRef<'a, i32>> {
+        (self.id == 11).then(|| ArrayRef::new(&self.payload[4..]))
+    }
+
+    pub fn as_long_array(&self) -> Option<ArrayRef<'a, i64>> {
+        (self.id == 12).then(|| ArrayRef::new(&self.payload[4..]))
+    }
+
+    /// Looks up a tag in this compound, returning `None` if this isn't a compound or the key isn't in it
+    pub fn get(&self, key: &str) -> Option<NBTTagRef<'a>> {
+        self.as_compound()?.get(key)
+    }
+
+    /// Copies this tag into an owned `NBTTag`
+    pub fn to_tag(&self) -> Result<NBTTag, NBTTagDeserializeError> {
+        NBTTag::from_bytes(&mut Queue::from(self.payload.to_vec()), self.id)
+    }
+}
+
+/// A borrowed view of a compound tag's entries
+#[derive(Clone, Copy, Debug)]
+pub struct CompoundRef<'a> {
+    entries: &'a [u8],
+}
+
+impl<'a> CompoundRef<'a> {
+    /// Looks up a single entry, skipping over every entry before it
+    pub fn get(&self, key: &str) -> Option<NBTTagRef<'a>> {
+        let key = mutf8::encode(key);
+
+        self.raw_entries().find(|(name, _)| *name == &key[..]).map(|(_, tag)| tag)
+    }
+
+    /// Looks up several entries while only going through the compound once, which is faster than calling `get` for each key
+    pub fn get_many<const N: usize>(&self, keys: [&str; N]) -> [Option<NBTTagRef<'a>>; N] {
+        let keys = keys.map(mutf8::encode);
+        let mut out = [None; N];
+        let mut left = N;
+
+        for (name, tag) in self.raw_entries() {
+            for (key, found) in keys.iter().zip(&mut out) {
+                if found.is_none() && &key[..] == name {
+                    *found = Some(tag);
+                    left -= 1;
+                }
+            }
+
+            if left == 0 {
+                break;
+            }
+        }
+
+        out
+    }
+
+    /// Iterates over the entries, decoding each name. The names are only copied if they had to be decoded from Modified UTF-8.
+    pub fn iter(&self) -> impl Iterator<Item = (Cow<'a, str>, NBTTagRef<'a>)> {
+        self.raw_entries().map(|(name, tag)| (mutf8::decode(name).expect("the name was checked when the view was created"), tag))
+    }
+
+    /// Iterates over the entries with their names still encoded
+    fn raw_entries(&self) -> CompoundIter<'a> {
+        CompoundIter { entries: self.entries, cursor: 0 }
+    }
+}
+
+struct CompoundIter<'a> {
+    entries: &'a [u8],
+    cursor: usize,
+}
+
+impl<'a> Iterator for CompoundIter<'a> {
+    type Item = (&'a [u8], NBTTagRef<'a>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let id = self.entries[self.cursor];
+        if id == 0 {
+            return None;
+        }
+
+        let (name, start) = str_at(self.entries, self.cursor + 1);
+        let (tag, end) = NBTTagRef::from_valid(self.entries, start, id);
+        self.cursor = end;
+
+        Some((name, tag))
+    }
+}
+
+/// A borrowed view of a list tag's elements
+#[derive(Clone, Copy, Debug)]
+pub struct ListRef<'a> {
+    element_id: u8,
+    len: usize,
+    elements: &'a [u8],
+}
+
+impl<'a> ListRef<'a> {
+    /// The id of every tag in the list
+    pub fn element_id(&self) -> u8 {
+        self.element_id
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the element at `index`, skipping over every element before it
+    pub fn get(&self, index: usize) -> Option<NBTTagRef<'a>> {
+        self.iter().nth(index)
+    }
+
+    pub fn iter(&self) -> ListIter<'a> {
+        ListIter { list: *self, index: 0, cursor: 0 }
+    }
+}
+
+impl<'a> IntoIterator for ListRef<'a> {
+    type Item = NBTTagRef<'a>;
+    type IntoIter = ListIter<'a>;
+
+    fn into_iter(self) -> ListIter<'a> {
+        self.iter()
+    }
+}
+
+pub struct ListIter<'a> {
+    list: ListRef<'a>,
+    index: usize,
+    cursor: usize,
+}
+
+impl<'a> Iterator for ListIter<'a> {
+    type Item = NBTTagRef<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index >= self.list.len {
+            return None;
+        }
+
+        let (tag, end) = NBTTagRef::from_valid(self.list.elements, self.cursor, self.list.element_id);
+        self.index += 1;
+        self.cursor = end;
+
+        Some(tag)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = self.list.len - self.index;
+        (left, Some(left))
+    }
+}
+
+impl ExactSizeIterator for ListIter<'_> {}
+
+/// The numbers that can be read out of an `ArrayRef`
+pub trait ArrayElement: Copy + 'static {
+    const SIZE: usize;
+
+    fn from_be_slice(bytes: &[u8]) -> Self;
+}
+
+impl ArrayElement for i32 {
+    const SIZE: usize = 4;
+
+    fn from_be_slice(bytes: &[u8]) -> Self {
+        i32::from_be_bytes(bytes.try_into().expect("an int is 4 bytes"))
+    }
+}
+
+impl ArrayElement for i64 {
+    const SIZE: usize = 8;
+
+    fn from_be_slice(bytes: &[u8]) -> Self {
+        i64::from_be_bytes(bytes.try_into().expect("a long is 8 bytes"))
+    }
+}
+
+/// A borrowed view of an int or long array. The numbers are big-endian in the data, so they are converted as they are read.
+#[derive(Clone, Copy, Debug)]
+pub struct ArrayRef<'a, T: ArrayElement> {
+    bytes: &'a [u8],
+    _marker: PhantomData<T>,
+}
+
+impl<'a, T: ArrayElement> ArrayRef<'a, T> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, _marker: PhantomData }
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.len() / T::SIZE
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<T> {
+        self.bytes.get(index * T::SIZE..(index + 1) * T::SIZE).map(T::from_be_slice)
+    }
+
+    /// Returns the raw big-endian bytes of the array
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = T> + 'a {
+        self.bytes.chunks_exact(T::SIZE).map(T::from_be_slice)
+    }
+
+    pub fn to_vec(&self) -> Vec<T> {
+        self.iter().collect()
+    }
+}
+
+fn unexpected_end(bytes: &[u8], position: usize, needed: usize) -> NBTTagDeserializeError {
+    NBTTagDeserializeError::new(NBTTagDeserializeErrorKind::UnexpectedEnd { needed, remaining: bytes.len().saturating_sub(position) }, position)
+}
+
+fn take(bytes: &[u8], position: usize, len: usize) -> Result<&[u8], NBTTagDeserializeError> {
+    position.checked_add(len).and_then(|end| bytes.get(position..end)).ok_or_else(|| unexpected_end(bytes, position, len))
+}
+
+fn read_u8(bytes: &[u8], position: usize) -> Result<u8, NBTTagDeserializeError> {
+    Ok(take(bytes, position, 1)?[0])
+}
+
+fn read_i32(bytes: &[u8], position: usize) -> Result<i32, NBTTagDeserializeError> {
+    Ok(i32::from_be_bytes(take(bytes, position, 4)?.try_into().expect("took 4 bytes")))
+}
+
+/// Reads the i32 length prefix of an array or list, failing with `NegativeLength` if it is below zero
+fn read_len(bytes: &[u8], position: usize) -> Result<usize, NBTTagDeserializeError> {
+    let len = read_i32(bytes, position)?;
+
+    usize::try_from(len).map_err(|_| NBTTagDeserializeError::new(NBTTagDeserializeErrorKind::NegativeLength(len), position))
+}
+
+/// Reads a u16 length prefixed string without decoding it, returning it and the position right after it. The string is checked to be valid Modified UTF-8.
+fn read_str(bytes: &[u8], position: usize) -> Result<(&[u8], usize), NBTTagDeserializeError> {
+    let len = u16::from_be_bytes(take(bytes, position, 2)?.try_into().expect("took 2 bytes")) as usize;
+    let str = take(bytes, position + 2, len)?;
+    if !mutf8::is_valid(str) {
+        return Err(NBTTagDeserializeError::new(NBTTagDeserializeErrorKind::InvalidUtf8, position + 2));
+    }
+
+    Ok((str, position + 2 + len))
+}
+
+/// Checks the tag with the given id starting at `position`, returning the position right after it
+fn skip(bytes: &[u8], position: usize, id: u8, depth: usize) -> Result<usize, NBTTagDeserializeError> {
+    let array = |size: usize| {
+        let len = read_len(bytes, position)?;
+        let len = len.checked_mul(size).ok_or_else(|| unexpected_end(bytes, position + 4, usize::MAX))?;
+        take(bytes, position + 4, len)?;
+
+        Ok(position + 4 + len)
+    };
+
+    match id {
+        1 => take(bytes, position, 1).map(|_| position + 1),
+        2 => take(bytes, position, 2).map(|_| position + 2),
+        3 | 5 => take(bytes, position, 4).map(|_| position + 4),
+        4 | 6 => take(bytes, position, 8).map(|_| position + 8),
+        7 => array(1),
+        8 => read_str(bytes, position).map(|(_, end)| end),
+        9 => skip_list(bytes, position, enter(position, depth)?),
+        10 => skip_compound(bytes, position, enter(position, depth)?),
+        11 => array(4),
+        12 => array(8),
+        _ => Err(NBTTagDeserializeError::new(NBTTagDeserializeErrorKind::UnknownTagId(id), position.saturating_sub(1))),
+    }
+}
+
+fn skip_list(bytes: &[u8], position: usize, depth: usize) -> Result<usize, NBTTagDeserializeError> {
+    let element_id = read_u8(bytes, position)?;
+    let len = read_len(bytes, position + 1)?;
+
+    let mut position = position + 5;
+    for i in 0..len {
+        position = skip(bytes, position, element_id, depth).map_err(|e| e.within_index(i))?;
+    }
+
+    Ok(position)
+}
+
+fn skip_compound(bytes: &[u8], position: usize, depth: usize) -> Result<usize, NBTTagDeserializeError> {
+    let mut position = position;
+    loop {
+        let id = read_u8(bytes, position)?;
+        if id == 0 {
+            return Ok(position + 1);
+        }
+
+        let (name, start) = read_str(bytes, position + 1)?;
+        position = skip(bytes, start, id, depth).map_err(|e| e.within_key(&mutf8::decode(name).expect("the name was checked when it was read")))?;
+    }
+}
+
+/// Returns the u16 length prefixed string at `position` in NBT that was already validated, and the position right after it
+fn str_at(bytes: &[u8], position: usize) -> (&[u8], usize) {
+    let end = position + 2 + u16::from_be_bytes([bytes[position], bytes[position + 1]]) as usize;
+
+    (&bytes[position + 2..end], end)
+}
+
+/// Returns the position right after the tag with the given id starting at `position`, in NBT that was already validated
+///
+/// This only follows the length prefixes, so getting at a nested tag doesn't check everything before it again.
+fn skip_valid(bytes: &[u8], position: usize, id: u8) -> usize {
+    let array = |size: usize| position + 4 + valid_len(bytes, position) * size;
+
+    match id {
+        1 => position + 1,
+        2 => position + 2,
+        3 | 5 => position + 4,
+        4 | 6 => position + 8,
+        7 => array(1),
+        8 => str_at(bytes, position).1,
+        9 => {
+            let element_id = bytes[position];
+            let len = valid_len(bytes, position + 1);
+            match element_size(element_id) {
+                Some(size) => position + 5 + len * size,
+                None => (0..len).fold(position + 5, |position, _| skip_valid(bytes, position, element_id)),
+            }
+        }
+        10 => {
+            let mut position = position;
+            while bytes[position] != 0 {
+                let (_, start) = str_at(bytes, position + 1);
+                position = skip_valid(bytes, start, bytes[position]);
+            }
+
+            position + 1
+        }
+        11 => array(4),
+        12 => array(8),
+        _ => unreachable!("the NBT was validated when the view was created"),
+    }
+}
+
+/// Returns the size of a tag with the given id if every tag with it has the same size
+fn element_size(id: u8) -> Option<usize> {
+    match id {
+        0 => Some(0),
+        1 => Some(1),
+        2 => Some(2),
+        3 | 5 => Some(4),
+        4 | 6 => Some(8),
+        _ => None,
+    }
+}
+
+fn valid_len(bytes: &[u8], position: usize) -> usize {
+    i32::from_be_bytes(bytes[position..position + 4].try_into().expect("took 4 bytes")) as usize
+}
+
+fn enter(position: usize, depth: usize) -> Result<usize, NBTTagDeserializeError> {
+    if depth >= MAX_DEPTH {
+        return Err(NBTTagDeserializeError::new(NBTTagDeserializeErrorKind::DepthLimitExceeded(MAX_DEPTH), position));
+    }
+
+    Ok(depth + 1)
+}
@@ -0,0 +1,299 @@
+//! The binary encodings that NBT comes in
+//!
+//! Tag ids, bytes, byte arrays and the layout of lists and compounds are the same everywhere, but the numbers and strings inside of them aren't:
+//! - Java Edition stores everything big endian with Modified UTF-8 strings
+//! - Bedrock Edition stores everything little endian with UTF-8 strings on disk
+//! - Bedrock Edition's network protocol is the same as on disk, except that ints, longs and lengths are zigzag varints
+
+use crate::nbt::tag::{ensure_left, pop, pop_le, pop_string, write_string, NBTTagDeserializeError, NBTTagDeserializeErrorKind, NBTTagSerializeError, NBTTagSerializeErrorKind};
+use crate::queue::Queue;
+use std::io;
+use std::io::Write;
+
+/// How the numbers and strings inside of a tag are encoded, used by `NBTTag::write_with` and `NBTTag::from_bytes_with`
+pub trait NBTCodec {
+    /// The fewest bytes an int can take up, used to check array lengths against the data that is left
+    const MIN_INT_SIZE: usize;
+    /// The fewest bytes a long can take up
+    const MIN_LONG_SIZE: usize;
+
+    fn write_short<W: Write + ?Sized>(writer: &mut W, value: i16) -> io::Result<()>;
+    fn write_int<W: Write + ?Sized>(writer: &mut W, value: i32) -> io::Result<()>;
+    fn write_long<W: Write + ?Sized>(writer: &mut W, value: i64) -> io::Result<()>;
+    fn write_float<W: Write + ?Sized>(writer: &mut W, value: f32) -> io::Result<()>;
+    fn write_double<W: Write + ?Sized>(writer: &mut W, value: f64) -> io::Result<()>;
+    /// Writes the length of a list or array
+    fn write_len<W: Write + ?Sized>(writer: &mut W, len: usize) -> io::Result<()>;
+    /// Writes a length prefixed string, used for both string tags and tag names
+    fn write_string<W: Write + ?Sized>(writer: &mut W, s: &str) -> Result<(), NBTTagSerializeError>;
+
+    fn read_short(queue: &mut Queue) -> Result<i16, NBTTagDeserializeError>;
+    fn read_int(queue: &mut Queue) -> Result<i32, NBTTagDeserializeError>;
+    fn read_long(queue: &mut Queue) -> Result<i64, NBTTagDeserializeError>;
+    fn read_float(queue: &mut Queue) -> Result<f32, NBTTagDeserializeError>;
+    fn read_double(queue: &mut Queue) -> Result<f64, NBTTagDeserializeError>;
+    /// Reads the length of a list or array, failing with `NegativeLength` if it is below zero
+    fn read_len(queue: &mut Queue) -> Result<usize, NBTTagDeserializeError>;
+    fn read_string(queue: &mut Queue) -> Result<String, NBTTagDeserializeError>;
+}
+
+/// Java Edition's big endian NBT with Modified UTF-8 strings
+pub struct JavaCodec;
+
+/// Bedrock Edition's little endian NBT, used by level.dat, structure files and the world database
+pub struct BedrockCodec;
+
+/// Bedrock Edition's network NBT, which is little endian with zigzag varint ints, longs and lengths
+pub struct BedrockNetworkCodec;
+
+impl NBTCodec for JavaCodec {
+    const MIN_INT_SIZE: usize = 4;
+    const MIN_LONG_SIZE: usize = 8;
+
+    fn write_short<W: Write + ?Sized>(writer: &mut W, value: i16) -> io::Result<()> {
+        writer.write_all(&value.to_be_bytes())
+    }
+
+    fn write_int<W: Write + ?Sized>(writer: &mut W, value: i32) -> io::Result<()> {
+        writer.write_all(&value.to_be_bytes())
+    }
+
+    fn write_long<W: Write + ?Sized>(writer: &mut W, value: i64) -> io::Result<()> {
+        writer.write_all(&value.to_be_bytes())
+    }
+
+    fn write_float<W: Write + ?Sized>(writer: &mut W, value: f32) -> io::Result<()> {
+        writer.write_all(&value.to_be_bytes())
+    }
+
+    fn write_double<W: Write + ?Sized>(writer: &mut W, value: f64) -> io::Result<()> {
+        writer.write_all(&value.to_be_bytes())
+    }
+
+    fn write_len<W: Write + ?Sized>(writer: &mut W, len: usize) -> io::Result<()> {
+        writer.write_all(&(len as i32).to_be_bytes())
+    }
+
+    fn write_string<W: Write + ?Sized>(writer: &mut W, s: &str) -> Result<(), NBTTagSerializeError> {
+        write_string(writer, s)
+    }
+
+    fn read_short(queue: &mut Queue) -> Result<i16, NBTTagDeserializeError> {
+        pop(queue)
+    }
+
+    fn read_int(queue: &mut Queue) -> Result<i32, NBTTagDeserializeError> {
+        pop(queue)
+    }
+
+    fn read_long(queue: &mut Queue) -> Result<i64, NBTTagDeserializeError> {
+        pop(queue)
+    }
+
+    fn read_float(queue: &mut Queue) -> Result<f32, NBTTagDeserializeError> {
+        pop(queue)
+    }
+
+    fn read_double(queue: &mut Queue) -> Result<f64, NBTTagDeserializeError> {
+        pop(queue)
+    }
+
+    fn read_len(queue: &mut Queue) -> Result<usize, NBTTagDeserializeError> {
+        let offset = queue.position();
+        check_len(pop(queue)?, offset)
+    }
+
+    fn read_string(queue: &mut Queue) -> Result<String, NBTTagDeserializeError> {
+        pop_string(queue)
+    }
+}
+
+impl NBTCodec for BedrockCodec {
+    const MIN_INT_SIZE: usize = 4;
+    const MIN_LONG_SIZE: usize = 8;
+
+    fn write_short<W: Write + ?Sized>(writer: &mut W, value: i16) -> io::Result<()> {
+        writer.write_all(&value.to_le_bytes())
+    }
+
+    fn write_int<W: Write + ?Sized>(writer: &mut W, value: i32) -> io::Result<()> {
+        writer.write_all(&value.to_le_bytes())
+    }
+
+    fn write_long<W: Write + ?Sized>(writer: &mut W, value: i64) -> io::Result<()> {
+        writer.write_all(&value.to_le_bytes())
+    }
+
+    fn write_float<W: Write + ?Sized>(writer: &mut W, value: f32) -> io::Result<()> {
+        writer.write_all(&value.to_le_bytes())
+    }
+
+    fn write_double<W: Write + ?Sized>(writer: &mut W, value: f64) -> io::Result<()> {
+        writer.write_all(&value.to_le_bytes())
+    }
+
+    fn write_len<W: Write + ?Sized>(writer: &mut W, len: usize) -> io::Result<()> {
+        writer.write_all(&(len as i32).to_le_bytes())
+    }
+
+    fn write_string<W: Write + ?Sized>(writer: &mut W, s: &str) -> Result<(), NBTTagSerializeError> {
+        writer.write_all(&string_len(s)?.to_le_bytes())?;
+        writer.write_all(s.as_bytes())?;
+
+        Ok(())
+    }
+
+    fn read_short(queue: &mut Queue) -> Result<i16, NBTTagDeserializeError> {
+        pop_le(queue)
+    }
+
+    fn read_int(queue: &mut Queue) -> Result<i32, NBTTagDeserializeError> {
+        pop_le(queue)
+    }
+
+    fn read_long(queue: &mut Queue) -> Result<i64, NBTTagDeserializeError> {
+        pop_le(queue)
+    }
+
+    fn read_float(queue: &mut Queue) -> Result<f32, NBTTagDeserializeError> {
+        pop_le(queue)
+    }
+
+    fn read_double(queue: &mut Queue) -> Result<f64, NBTTagDeserializeError> {
+        pop_le(queue)
+    }
+
+    fn read_len(queue: &mut Queue) -> Result<usize, NBTTagDeserializeError> {
+        let offset = queue.position();
+        check_len(Self::read_int(queue)?, offset)
+    }
+
+    fn read_string(queue: &mut Queue) -> Result<String, NBTTagDeserializeError> {
+        let len = pop_le::<u16>(queue)? as usize;
+        read_utf8(queue, len)
+    }
+}
+
+impl NBTCodec for BedrockNetworkCodec {
+    const MIN_INT_SIZE: usize = 1;
+    const MIN_LONG_SIZE: usize = 1;
+
+    fn write_short<W: Write + ?Sized>(writer: &mut W, value: i16) -> io::Result<()> {
+        BedrockCodec::write_short(writer, value)
+    }
+
+    fn write_int<W: Write + ?Sized>(writer: &mut W, value: i32) -> io::Result<()> {
+        write_varint(writer, ((value << 1) ^ (value >> 31)) as u32 as u64)
+    }
+
+    fn write_long<W: Write + ?Sized>(writer: &mut W, value: i64) -> io::Result<()> {
+        write_varint(writer, ((value << 1) ^ (value >> 63)) as u64)
+    }
+
+    fn write_float<W: Write + ?Sized>(writer: &mut W, value: f32) -> io::Result<()> {
+        BedrockCodec::write_float(writer, value)
+    }
+
+    fn write_double<W: Write + ?Sized>(writer: &mut W, value: f64) -> io::Result<()> {
+        BedrockCodec::write_double(writer, value)
+    }
+
+    fn write_len<W: Write + ?Sized>(writer: &mut W, len: usize) -> io::Result<()> {
+        Self::write_int(writer, len as i32)
+    }
+
+    fn write_string<W: Write + ?Sized>(writer: &mut W, s: &str) -> Result<(), NBTTagSerializeError> {
+        // unlike every other length, string lengths aren't zigzag encoded
+        write_varint(writer, string_len(s)? as u64)?;
+        writer.write_all(s.as_bytes())?;
+
+        Ok(())
+    }
+
+    fn read_short(queue: &mut Queue) -> Result<i16, NBTTagDeserializeError> {
+        BedrockCodec::read_short(queue)
+    }
+
+    fn read_int(queue: &mut Queue) -> Result<i32, NBTTagDeserializeError> {
+        let value = read_varint(queue, 5)? as u32;
+        Ok((value >> 1) as i32 ^ -((value & 1) as i32))
+    }
+
+    fn read_long(queue: &mut Queue) -> Result<i64, NBTTagDeserializeError> {
+        let value = read_varint(queue, 10)?;
+        Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+
+    fn read_float(queue: &mut Queue) -> Result<f32, NBTTagDeserializeError> {
+        BedrockCodec::read_float(queue)
+    }
+
+    fn read_double(queue: &mut Queue) -> Result<f64, NBTTagDeserializeError> {
+        BedrockCodec::read_double(queue)
+    }
+
+    fn read_len(queue: &mut Queue) -> Result<usize, NBTTagDeserializeError> {
+        let offset = queue.position();
+        check_len(Self::read_int(queue)?, offset)
+    }
+
+    fn read_string(queue: &mut Queue) -> Result<String, NBTTagDeserializeError> {
+        let offset = queue.position();
+        let len = read_varint(queue, 5)?;
+        let len = u32::try_from(len).map_err(|_| NBTTagDeserializeError::new(NBTTagDeserializeErrorKind::InvalidVarInt, offset))?;
+
+        read_utf8(queue, len as usize)
+    }
+}
+
+fn check_len(len: i32, offset: usize) -> Result<usize, NBTTagDeserializeError> {
+    usize::try_from(len).map_err(|_| NBTTagDeserializeError::new(NBTTagDeserializeErrorKind::NegativeLength(len), offset))
+}
+
+/// Bedrock uses the same u16 string length limit as Java, but counts plain UTF-8 bytes
+fn string_len(s: &str) -> Result<u16, NBTTagSerializeError> {
+    u16::try_from(s.len()).map_err(|_| NBTTagSerializeError::new(NBTTagSerializeErrorKind::StringTooLong(s.len())))
+}
+
+fn read_utf8(queue: &mut Queue, len: usize) -> Result<String, NBTTagDeserializeError> {
+    ensure_left(queue, len)?;
+
+    let offset = queue.position();
+    let bytes = queue.pop_bytes(len).expect("the queue was checked to have enough bytes left");
+
+    String::from_utf8(bytes).map_err(|_| NBTTagDeserializeError::new(NBTTagDeserializeErrorKind::InvalidUtf8, offset))
+}
+
+fn write_varint<W: Write + ?Sized>(writer: &mut W, mut value: u64) -> io::Result<()> {
+    let mut buf = [0; 10];
+    let mut len = 0;
+    loop {
+        buf[len] = (value & 0x7F) as u8;
+        value >>= 7;
+        len += 1;
+
+        if value == 0 {
+            break;
+        }
+        buf[len - 1] |= 0x80;
+    }
+
+    writer.write_all(&buf[..len])
+}
+
+/// Reads an unsigned LEB128 varint of at most `max_len` bytes
+fn read_varint(queue: &mut Queue, max_len: usize) -> Result<u64, NBTTagDeserializeError> {
+    let offset = queue.position();
+
+    let mut value = 0;
+    for i in 0..max_len {
+        let byte = pop::<u8>(queue)?;
+        value |= ((byte & 0x7F) as u64) << (i * 7);
+
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+
+    Err(NBTTagDeserializeError::new(NBTTagDeserializeErrorKind::InvalidVarInt, offset))
+}
@@ -0,0 +1,163 @@
+use crate::nbt::tag::NBTTag;
+use indexmap::IndexMap;
+use std::ops::{Index, IndexMut};
+
+pub use indexmap::map::{Entry, IntoIter, Iter, IterMut};
+
+/// The entries of a compound tag, kept in the order they were inserted with constant time lookup by name
+///
+/// Like vanilla, a compound can't have two entries with the same name, and two compounds are equal if they have the same entries in any order.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct NBTCompound(IndexMap<String, NBTTag>);
+
+impl NBTCompound {
+    pub fn new() -> Self {
+        Self(IndexMap::new())
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self(IndexMap::with_capacity(capacity))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&NBTTag> {
+        self.0.get(key)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut NBTTag> {
+        self.0.get_mut(key)
+    }
+
+    /// Returns the entry at `index` in insertion order
+    pub fn get_index(&self, index: usize) -> Option<(&str, &NBTTag)> {
+        self.0.get_index(index).map(|(key, tag)| (key.as_str(), tag))
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.0.contains_key(key)
+    }
+
+    /// Inserts an entry, returning the old value if there was one. A replaced entry keeps its place in the order.
+    pub fn insert(&mut self, key: impl Into<String>, tag: NBTTag) -> Option<NBTTag> {
+        self.0.insert(key.into(), tag)
+    }
+
+    /// Removes an entry and returns its value. The entries after it are shifted down to keep their order, which makes this linear time.
+    pub fn remove(&mut self, key: &str) -> Option<NBTTag> {
+        self.0.shift_remove(key)
+    }
+
+    /// Removes an entry like `remove`, also returning where it was in the order
+    pub fn remove_full(&mut self, key: &str) -> Option<(usize, NBTTag)> {
+        self.0.shift_remove_full(key).map(|(index, _, tag)| (index, tag))
+    }
+
+    /// Inserts an entry at `index` in the order, shifting the entries after it up. An existing entry is moved there and its old value is returned.
+    pub fn insert_at(&mut self, index: usize, key: impl Into<String>, tag: NBTTag) -> Option<NBTTag> {
+        self.0.shift_insert(index, key.into(), tag)
+    }
+
+    /// Keeps only the entries that `keep` returns true for
+    pub fn retain(&mut self, mut keep: impl FnMut(&str, &mut NBTTag) -> bool) {
+        self.0.retain(|key, tag| keep(key, tag));
+    }
+
+    pub fn entry(&mut self, key: impl Into<String>) -> Entry<'_, String, NBTTag> {
+        self.0.entry(key.into())
+    }
+
+    /// Copies every entry of `other` into this compound, the same way `/data merge` does. Entries that are compounds on both sides are merged recursively, anything else is replaced.
+    pub fn merge(&mut self, other: &NBTCompound) {
+        for (key, value) in other {
+            match (self.0.get_mut(key), value) {
+                (Some(NBTTag::Compound(existing)), NBTTag::Compound(value)) => existing.merge(value),
+                _ => {
+                    self.0.insert(key.clone(), value.clone());
+                }
+            }
+        }
+    }
+
+    pub fn iter(&self) -> Iter<'_, String, NBTTag> {
+        self.0.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, String, NBTTag> {
+        self.0.iter_mut()
+    }
+
+    pub fn keys(&self) -> impl ExactSizeIterator<Item = &str> {
+        self.0.keys().map(String::as_str)
+    }
+
+    pub fn values(&self) -> impl ExactSizeIterator<Item = &NBTTag> {
+        self.0.values()
+    }
+}
+
+impl Index<&str> for NBTCompound {
+    type Output = NBTTag;
+
+    fn index(&self, index: &str) -> &Self::Output {
+        self.get(index).unwrap_or_else(|| panic!("Could not find tag '{}' in NBT Compound.", index))
+    }
+}
+
+impl IndexMut<&str> for NBTCompound {
+    fn index_mut(&mut self, index: &str) -> &mut Self::Output {
+        self.get_mut(index).unwrap_or_else(|| panic!("Could not find tag '{}' in NBT Compound.", index))
+    }
+}
+
+impl FromIterator<(String, NBTTag)> for NBTCompound {
+    fn from_iter<T: IntoIterator<Item = (String, NBTTag)>>(iter: T) -> Self {
+        Self(IndexMap::from_iter(iter))
+    }
+}
+
+impl Extend<(String, NBTTag)> for NBTCompound {
+    fn extend<T: IntoIterator<Item = (String, NBTTag)>>(&mut self, iter: T) {
+        self.0.extend(iter);
+    }
+}
+
+/// Later entries replace earlier ones with the same name
+impl From<Vec<(String, NBTTag)>> for NBTCompound {
+    fn from(value: Vec<(String, NBTTag)>) -> Self {
+        value.into_iter().collect()
+    }
+}
+
+impl IntoIterator for NBTCompound {
+    type Item = (String, NBTTag);
+    type IntoIter = IntoIter<String, NBTTag>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a NBTCompound {
+    type Item = (&'a String, &'a NBTTag);
+    type IntoIter = Iter<'a, String, NBTTag>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut NBTCompound {
+    type Item = (&'a String, &'a mut NBTTag);
+    type IntoIter = IterMut<'a, String, NBTTag>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter_mut()
+    }
+}
@@ -0,0 +1,354 @@
+//! Conversion between Rust types and NBT tags
+//!
+//! `NBTValue` converts a single value to and from a tag, and `NbtCompound` converts a struct to and from the entries of a compound. `NbtCompound` is usually implemented with `#[derive(NbtCompound)]` from `protocol_proc`, which also implements `NBTValue` so that structs can be nested.
+//!
+//! Numbers are read the way vanilla's `getInt` and friends read them, so any numeric tag is cast to the type of the field.
+//!
+//! A `Vec` is always a `List`, like serde's mapping. Typed arrays are opt-in with the `byte_array`, `int_array` and `long_array` modules, which the derive uses for fields marked `#[nbt(byte_array)]` and so on.
+
+use crate::nbt::compound::NBTCompound;
+use crate::nbt::tag::{path_within_index, path_within_key, NBTTag};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use uuid::Uuid;
+
+/// The reason that converting a tag into a Rust value failed
+#[derive(Clone, PartialEq, Debug)]
+pub enum NBTConvertErrorKind {
+    /// A required key wasn't in the compound
+    Missing,
+    /// The tag had a different type than the value needs
+    WrongType { expected: &'static str, found: &'static str },
+    /// The tag had the right type but its contents can't be converted, e.g. a UUID int array without exactly 4 ints
+    InvalidValue { expected: &'static str },
+}
+
+/// An error from converting a tag into a Rust value, along with the path of the tag that couldn't be converted
+#[derive(Clone, PartialEq, Debug)]
+pub struct NBTConvertError {
+    pub kind: NBTConvertErrorKind,
+    pub path: String,
+}
+
+impl NBTConvertError {
+    pub fn new(kind: NBTConvertErrorKind) -> Self {
+        Self { kind, path: String::new() }
+    }
+
+    pub fn wrong_type(expected: &'static str, found: &NBTTag) -> Self {
+        Self::new(NBTConvertErrorKind::WrongType { expected, found: found.name() })
+    }
+
+    /// Prefixes the path with a compound key, used as the error is passed up out of a compound
+    pub fn within_key(mut self, key: &str) -> Self {
+        self.path = path_within_key(key, &self.path);
+        self
+    }
+
+    /// Prefixes the path with a list index, used as the error is passed up out of a list
+    pub fn within_index(mut self, index: usize) -> Self {
+        self.path = path_within_index(index, &self.path);
+        self
+    }
+}
+
+impl Display for NBTConvertErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Missing => write!(f, "missing required tag"),
+            Self::WrongType { expected, found } => write!(f, "expected {expected} but found {found}"),
+            Self::InvalidValue { expected } => write!(f, "value isn't a valid {expected}"),
+        }
+    }
+}
+
+impl Display for NBTConvertError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if !self.path.is_empty() {
+            write!(f, " (in '{}')", self.path)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Error for NBTConvertError {}
+
+/// A value that can be converted to and from a single tag
+pub trait NBTValue: Sized {
+    fn to_tag(&self) -> NBTTag;
+
+    fn from_tag(tag: &NBTTag) -> Result<Self, NBTConvertError>;
+
+    /// Inserts this value into a compound, which `Option` overrides to leave out `None`
+    fn write_field(&self, compound: &mut NBTCompound, key: &str) {
+        compound.insert(key, self.to_tag());
+    }
+
+    /// Reads this value from a compound, which `Option` overrides to allow the key to be missing
+    fn read_field(compound: &NBTCompound, key: &str) -> Result<Self, NBTConvertError> {
+        match compound.get(key) {
+            Some(tag) => Self::from_tag(tag).map_err(|e| e.within_key(key)),
+            None => Err(NBTConvertError::new(NBTConvertErrorKind::Missing).within_key(key)),
+        }
+    }
+}
+
+/// A struct that is stored as the entries of a compound
+pub trait NbtCompound: Sized {
+    /// Inserts this struct's entries into `compound`, which lets flattened structs share their parent's compound
+    fn write_compound(&self, compound: &mut NBTCompound);
+
+    fn from_compound(compound: &NBTCompound) -> Result<Self, NBTConvertError>;
+
+    fn to_compound(&self) -> NBTCompound {
+        let mut compound = NBTCompound::new();
+        self.write_compound(&mut compound);
+        compound
+    }
+
+    /// Reads this struct from a tag, failing if it isn't a compound
+    fn from_compound_tag(tag: &NBTTag) -> Result<Self, NBTConvertError> {
+        match tag {
+            NBTTag::Compound(compound) => Self::from_compound(compound),
+            _ => Err(NBTConvertError::wrong_type("Compound", tag)),
+        }
+    }
+}
+
+macro_rules! numeric_value {
+    ($($ty:ty => $variant:ident),*) => {
+        $(
+            impl NBTValue for $ty {
+                fn to_tag(&self) -> NBTTag {
+                    NBTTag::$variant(*self)
+                }
+
+                fn from_tag(tag: &NBTTag) -> Result<Self, NBTConvertError> {
+                    match *tag {
+                        NBTTag::Byte(b) => Ok(b as $ty),
+                        NBTTag::Short(s) => Ok(s as $ty),
+                        NBTTag::Int(i) => Ok(i as $ty),
+                        NBTTag::Long(l) => Ok(l as $ty),
+                        NBTTag::Float(f) => Ok(f as $ty),
+                        NBTTag::Double(d) => Ok(d as $ty),
+                        _ => Err(NBTConvertError::wrong_type(stringify!($variant), tag)),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+numeric_value!(i16 => Short, f32 => Float, f64 => Double);
+
+impl NBTValue for i8 {
+    fn to_tag(&self) -> NBTTag {
+        NBTTag::Byte(*self)
+    }
+
+    fn from_tag(tag: &NBTTag) -> Result<Self, NBTConvertError> {
+        i64::from_tag(tag).map(|l| l as i8).map_err(|_| NBTConvertError::wrong_type("Byte", tag))
+    }
+}
+
+impl NBTValue for i32 {
+    fn to_tag(&self) -> NBTTag {
+        NBTTag::Int(*self)
+    }
+
+    fn from_tag(tag: &NBTTag) -> Result<Self, NBTConvertError> {
+        i64::from_tag(tag).map(|l| l as i32).map_err(|_| NBTConvertError::wrong_type("Int", tag))
+    }
+}
+
+impl NBTValue for i64 {
+    fn to_tag(&self) -> NBTTag {
+        NBTTag::Long(*self)
+    }
+
+    fn from_tag(tag: &NBTTag) -> Result<Self, NBTConvertError> {
+        match *tag {
+            NBTTag::Byte(b) => Ok(b as i64),
+            NBTTag::Short(s) => Ok(s as i64),
+            NBTTag::Int(i) => Ok(i as i64),
+            NBTTag::Long(l) => Ok(l),
+            NBTTag::Float(f) => Ok(f as i64),
+            NBTTag::Double(d) => Ok(d as i64),
+            _ => Err(NBTConvertError::wrong_type("Long", tag)),
+        }
+    }
+}
+
+fn list_from_list<T: NBTValue>(tag: &NBTTag) -> Result<Vec<T>, NBTConvertError> {
+    match tag {
+        NBTTag::List(tags) => tags.iter().enumerate()
+            .map(|(i, tag)| T::from_tag(tag).map_err(|e| e.within_index(i)))
+            .collect(),
+        _ => Err(NBTConvertError::wrong_type("List", tag)),
+    }
+}
+
+/// Reads a field with a conversion that isn't an `NBTValue`, like the typed array modules
+fn read_field_with<T>(compound: &NBTCompound, key: &str, from_tag: fn(&NBTTag) -> Result<T, NBTConvertError>) -> Result<T, NBTConvertError> {
+    match compound.get(key) {
+        Some(tag) => from_tag(tag).map_err(|e| e.within_key(key)),
+        None => Err(NBTConvertError::new(NBTConvertErrorKind::Missing).within_key(key)),
+    }
+}
+
+macro_rules! array_conversion {
+    ($($module:ident: $ty:ty => $variant:ident($to:expr, $from:expr)),*) => {
+        $(
+            /// Converts a `Vec` to and from a typed array instead of a `List`, while still reading a `List` of numbers
+            pub mod $module {
+                use super::*;
+
+                pub fn to_tag(values: &[$ty]) -> NBTTag {
+                    NBTTag::$variant(values.iter().map($to).collect())
+                }
+
+                pub fn from_tag(tag: &NBTTag) -> Result<Vec<$ty>, NBTConvertError> {
+                    match tag {
+                        NBTTag::$variant(values) => Ok(values.iter().map($from).collect()),
+                        NBTTag::List(_) => list_from_list(tag),
+                        _ => Err(NBTConvertError::wrong_type(stringify!($variant), tag)),
+                    }
+                }
+
+                pub fn write_field(values: &[$ty], compound: &mut NBTCompound, key: &str) {
+                    compound.insert(key, to_tag(values));
+                }
+
+                pub fn read_field(compound: &NBTCompound, key: &str) -> Result<Vec<$ty>, NBTConvertError> {
+                    read_field_with(compound, key, from_tag)
+                }
+            }
+        )*
+    };
+}
+
+array_conversion!(
+    byte_array: i8 => ByteArray(|b| *b as u8, |b| *b as i8),
+    int_array: i32 => IntArray(|i| *i, |i| *i),
+    long_array: i64 => LongArray(|l| *l, |l| *l)
+);
+
+/// Booleans are stored as a `Byte` of 0 or 1, and any other number is read as `true`
+impl NBTValue for bool {
+    fn to_tag(&self) -> NBTTag {
+        NBTTag::Byte(*self as i8)
+    }
+
+    fn from_tag(tag: &NBTTag) -> Result<Self, NBTConvertError> {
+        i8::from_tag(tag).map(|b| b != 0)
+    }
+}
+
+impl NBTValue for String {
+    fn to_tag(&self) -> NBTTag {
+        NBTTag::String(self.clone())
+    }
+
+    fn from_tag(tag: &NBTTag) -> Result<Self, NBTConvertError> {
+        match tag {
+            NBTTag::String(s) => Ok(s.clone()),
+            _ => Err(NBTConvertError::wrong_type("String", tag)),
+        }
+    }
+}
+
+/// UUIDs are stored as an `IntArray` of 4 ints, most significant first, the way vanilla stores entity UUIDs
+impl NBTValue for Uuid {
+    fn to_tag(&self) -> NBTTag {
+        let bits = self.as_u128();
+        NBTTag::IntArray((0..4).rev().map(|i| (bits >> (i * 32)) as u32 as i32).collect())
+    }
+
+    fn from_tag(tag: &NBTTag) -> Result<Self, NBTConvertError> {
+        match tag {
+            NBTTag::IntArray(ints) if ints.len() == 4 => Ok(Uuid::from_u128(ints.iter().fold(0, |bits, i| bits << 32 | *i as u32 as u128))),
+            NBTTag::IntArray(_) => Err(NBTConvertError::new(NBTConvertErrorKind::InvalidValue { expected: "UUID" })),
+            _ => Err(NBTConvertError::wrong_type("IntArray", tag)),
+        }
+    }
+}
+
+impl NBTValue for NBTTag {
+    fn to_tag(&self) -> NBTTag {
+        self.clone()
+    }
+
+    fn from_tag(tag: &NBTTag) -> Result<Self, NBTConvertError> {
+        Ok(tag.clone())
+    }
+}
+
+impl NBTValue for NBTCompound {
+    fn to_tag(&self) -> NBTTag {
+        NBTTag::Compound(self.clone())
+    }
+
+    fn from_tag(tag: &NBTTag) -> Result<Self, NBTConvertError> {
+        match tag {
+            NBTTag::Compound(compound) => Ok(compound.clone()),
+            _ => Err(NBTConvertError::wrong_type("Compound", tag)),
+        }
+    }
+}
+
+impl<T: NBTValue> NBTValue for Vec<T> {
+    fn to_tag(&self) -> NBTTag {
+        NBTTag::List(self.iter().map(T::to_tag).collect())
+    }
+
+    fn from_tag(tag: &NBTTag) -> Result<Self, NBTConvertError> {
+        list_from_list(tag)
+    }
+}
+
+impl<T: NBTValue> NBTValue for HashMap<String, T> {
+    fn to_tag(&self) -> NBTTag {
+        NBTTag::Compound(self.iter().map(|(key, value)| (key.clone(), value.to_tag())).collect())
+    }
+
+    fn from_tag(tag: &NBTTag) -> Result<Self, NBTConvertError> {
+        match tag {
+            NBTTag::Compound(compound) => compound.iter()
+                .map(|(key, tag)| Ok((key.clone(), T::from_tag(tag).map_err(|e| e.within_key(key))?)))
+                .collect(),
+            _ => Err(NBTConvertError::wrong_type("Compound", tag)),
+        }
+    }
+}
+
+/// Optional values are left out of the compound when they are `None`, and are `None` when their key is missing
+///
+/// There is no tag for nothing, so a `None` that isn't in a compound, e.g. in a `Vec<Option<T>>`, becomes an empty compound.
+impl<T: NBTValue> NBTValue for Option<T> {
+    fn to_tag(&self) -> NBTTag {
+        match self {
+            Some(value) => value.to_tag(),
+            None => NBTTag::Compound(NBTCompound::new()),
+        }
+    }
+
+    fn from_tag(tag: &NBTTag) -> Result<Self, NBTConvertError> {
+        T::from_tag(tag).map(Some)
+    }
+
+    fn write_field(&self, compound: &mut NBTCompound, key: &str) {
+        if let Some(value) = self {
+            value.write_field(compound, key);
+        }
+    }
+
+    fn read_field(compound: &NBTCompound, key: &str) -> Result<Self, NBTConvertError> {
+        match compound.get(key) {
+            Some(tag) => T::from_tag(tag).map(Some).map_err(|e| e.within_key(key)),
+            None => Ok(None),
+        }
+    }
+}
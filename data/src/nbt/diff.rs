@@ -0,0 +1,304 @@
+//! Structural diffs between two tags, which can be applied as a patch
+//!
+//! A diff is a list of the paths that were added, removed or changed. Compounds are compared key by key, and lists with the same length are compared element by element. Anything else that differs, including lists that changed length and typed arrays, is recorded as changing as a whole.
+//!
+//! Deep-merging one compound into another, the way `/data merge` does, is `NBTTag::merge`.
+
+use crate::nbt::path::{resolve_index, NBTPath, NBTPathNode};
+use crate::nbt::tag::NBTTag;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::mem;
+
+/// A single difference between two tags
+///
+/// An empty path means the root tag itself.
+#[derive(Clone, PartialEq, Debug)]
+pub enum NBTChange {
+    Added { path: NBTPath, tag: NBTTag },
+    Removed { path: NBTPath, tag: NBTTag },
+    Changed { path: NBTPath, from: NBTTag, to: NBTTag },
+}
+
+impl NBTChange {
+    pub fn path(&self) -> &NBTPath {
+        match self {
+            Self::Added { path, .. } | Self::Removed { path, .. } | Self::Changed { path, .. } => path,
+        }
+    }
+
+    /// Returns the change that undoes this one
+    pub fn reversed(&self) -> Self {
+        match self.clone() {
+            Self::Added { path, tag } => Self::Removed { path, tag },
+            Self::Removed { path, tag } => Self::Added { path, tag },
+            Self::Changed { path, from, to } => Self::Changed { path, from: to, to: from },
+        }
+    }
+
+    /// The tag that the path points at before and after the change
+    fn before_after(&self) -> (Option<&NBTTag>, Option<&NBTTag>) {
+        match self {
+            Self::Added { tag, .. } => (None, Some(tag)),
+            Self::Removed { tag, .. } => (Some(tag), None),
+            Self::Changed { from, to, .. } => (Some(from), Some(to)),
+        }
+    }
+}
+
+impl Display for NBTChange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let path = match self.path().nodes() {
+            [] => "root".to_string(),
+            _ => self.path().to_string(),
+        };
+
+        match self {
+            Self::Added { tag, .. } => write!(f, "+ {path}: {}", tag.as_snbt()),
+            Self::Removed { tag, .. } => write!(f, "- {path}: {}", tag.as_snbt()),
+            Self::Changed { from, to, .. } => write!(f, "~ {path}: {} -> {}", from.as_snbt(), to.as_snbt()),
+        }
+    }
+}
+
+/// The reason that applying a diff failed
+#[derive(Clone, PartialEq, Debug)]
+pub enum NBTPatchErrorKind {
+    /// The tag at the path wasn't what the diff was made from, where `None` means there was no tag
+    Conflict { expected: Option<Box<NBTTag>>, found: Option<Box<NBTTag>> },
+    /// The path pointed into a typed array, or a tag that isn't a compound or list
+    Unreachable,
+}
+
+/// An error from applying a diff, along with the path of the change that couldn't be applied
+#[derive(Clone, PartialEq, Debug)]
+pub struct NBTPatchError {
+    pub kind: NBTPatchErrorKind,
+    pub path: String,
+}
+
+impl Display for NBTPatchErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let snbt = |tag: &Option<Box<NBTTag>>| tag.as_ref().map_or("nothing".to_string(), |tag| tag.as_snbt());
+
+        match self {
+            Self::Conflict { expected, found } => write!(f, "expected {} but found {}", snbt(expected), snbt(found)),
+            Self::Unreachable => write!(f, "path can't be set"),
+        }
+    }
+}
+
+impl Display for NBTPatchError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if !self.path.is_empty() {
+            write!(f, " (in '{}')", self.path)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Error for NBTPatchError {}
+
+/// The differences between two tags
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct NBTDiff {
+    changes: Vec<NBTChange>,
+}
+
+impl NBTDiff {
+    /// Finds the changes that turn `old` into `new`
+    pub fn between(old: &NBTTag, new: &NBTTag) -> Self {
+        let mut diff = Self::default();
+        diff.compare(&mut Vec::new(), old, new);
+        diff
+    }
+
+    pub fn changes(&self) -> &[NBTChange] {
+        &self.changes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Returns the diff that turns the new tag back into the old one
+    pub fn reversed(&self) -> Self {
+        Self { changes: self.changes.iter().rev().map(NBTChange::reversed).collect() }
+    }
+
+    /// Applies the changes to `root`, checking that every path still holds what the diff was made from
+    ///
+    /// Paths are followed exactly, so they can only be made of compound keys and list indices, which is all that `between` makes. If any of the changes fail, the ones before it are undone with the tags that they replaced, so nothing is changed.
+    pub fn apply(&self, root: &mut NBTTag) -> Result<(), NBTPatchError> {
+        let mut replaced = Vec::with_capacity(self.changes.len());
+        for change in &self.changes {
+            match apply_change(root, change) {
+                Ok(old) => replaced.push(old),
+                Err(kind) => {
+                    for (change, old) in self.changes.iter().zip(replaced).rev() {
+                        undo_change(root, change.path(), old);
+                    }
+
+                    return Err(NBTPatchError { kind, path: change.path().to_string() });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn compare(&mut self, nodes: &mut Vec<NBTPathNode>, old: &NBTTag, new: &NBTTag) {
+        match (old, new) {
+            (NBTTag::Compound(old), NBTTag::Compound(new)) => {
+                for (key, old) in old {
+                    nodes.push(NBTPathNode::Key(key.clone()));
+                    match new.get(key) {
+                        Some(new) => self.compare(nodes, old, new),
+                        None => self.changes.push(NBTChange::Removed { path: nodes.clone().into(), tag: old.clone() }),
+                    }
+                    nodes.pop();
+                }
+
+                for (key, new) in new.iter().filter(|(key, _)| !old.contains_key(key)) {
+                    nodes.push(NBTPathNode::Key(key.clone()));
+                    self.changes.push(NBTChange::Added { path: nodes.clone().into(), tag: new.clone() });
+                    nodes.pop();
+                }
+            }
+            (NBTTag::List(old_list), NBTTag::List(new_list)) if old_list.len() == new_list.len() && same_element_type(old_list, new_list) => {
+                for (i, (old, new)) in old_list.iter().zip(new_list).enumerate() {
+                    nodes.push(NBTPathNode::Index(i as i32));
+                    self.compare(nodes, old, new);
+                    nodes.pop();
+                }
+            }
+            _ if old != new => self.changes.push(NBTChange::Changed { path: nodes.clone().into(), from: old.clone(), to: new.clone() }),
+            _ => {}
+        }
+    }
+}
+
+impl Display for NBTDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, change) in self.changes.iter().enumerate() {
+            if i != 0 {
+                writeln!(f)?;
+            }
+
+            write!(f, "{change}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl NBTTag {
+    /// Finds the changes that turn this tag into `new`, see `NBTDiff::between`
+    pub fn diff(&self, new: &NBTTag) -> NBTDiff {
+        NBTDiff::between(self, new)
+    }
+}
+
+/// Lists can only hold one type of tag, so lists of different types are replaced as a whole
+fn same_element_type(old: &[NBTTag], new: &[NBTTag]) -> bool {
+    match (old.first(), new.first()) {
+        (Some(old), Some(new)) => old.id() == new.id(),
+        _ => true,
+    }
+}
+
+/// What a change replaced, so that it can be undone
+enum Replaced {
+    /// The tag that was at the path
+    Tag(NBTTag),
+    /// Nothing, since a compound entry was added
+    Nothing,
+    /// A compound entry that was removed, along with its place in the order
+    Entry(usize, NBTTag),
+    /// A list element that was removed, along with its index
+    Element(usize, NBTTag),
+}
+
+fn apply_change(root: &mut NBTTag, change: &NBTChange) -> Result<Replaced, NBTPatchErrorKind> {
+    let (expected, replacement) = change.before_after();
+    let conflict = |found: Option<&NBTTag>| NBTPatchErrorKind::Conflict { expected: expected.cloned().map(Box::new), found: found.cloned().map(Box::new) };
+
+    let Some((last, parents)) = change.path().nodes().split_last() else {
+        if Some(&*root) != expected {
+            return Err(conflict(Some(root)));
+        }
+
+        // there is always a root tag, so it can only be changed
+        return replacement.map(|replacement| Replaced::Tag(mem::replace(root, replacement.clone()))).ok_or(NBTPatchErrorKind::Unreachable);
+    };
+
+    let parent = tag_mut(root, parents);
+    let found = parent.as_deref().and_then(|parent| child(parent, last));
+    if found != expected {
+        return Err(conflict(found));
+    }
+
+    match (last, parent, replacement) {
+        (NBTPathNode::Key(key), Some(NBTTag::Compound(c)), Some(replacement)) => Ok(c.insert(key.clone(), replacement.clone()).map_or(Replaced::Nothing, Replaced::Tag)),
+        (NBTPathNode::Key(key), Some(NBTTag::Compound(c)), None) => c.remove_full(key).map(|(i, old)| Replaced::Entry(i, old)).ok_or(NBTPatchErrorKind::Unreachable),
+        (NBTPathNode::Index(index), Some(NBTTag::List(l)), replacement) => match (resolve_index(*index, l.len()), replacement) {
+            (Some(i), Some(replacement)) if l.len() == 1 || l[0].id() == replacement.id() => Ok(Replaced::Tag(mem::replace(&mut l[i], replacement.clone()))),
+            (Some(i), None) => Ok(Replaced::Element(i, l.remove(i))),
+            _ => Err(NBTPatchErrorKind::Unreachable),
+        },
+        _ => Err(NBTPatchErrorKind::Unreachable),
+    }
+}
+
+/// Puts back what `apply_change` replaced. The changes after it have already been undone, so the path leads to the same place that it did then.
+fn undo_change(root: &mut NBTTag, path: &NBTPath, replaced: Replaced) {
+    let nodes = path.nodes();
+    if let Replaced::Tag(old) = replaced {
+        if let Some(tag) = tag_mut(root, nodes) {
+            *tag = old;
+        }
+
+        return;
+    }
+
+    let Some((last, parents)) = nodes.split_last() else {
+        return;
+    };
+    match (last, tag_mut(root, parents), replaced) {
+        (NBTPathNode::Key(key), Some(NBTTag::Compound(c)), Replaced::Nothing) => {
+            c.remove(key);
+        }
+        (NBTPathNode::Key(key), Some(NBTTag::Compound(c)), Replaced::Entry(i, old)) => {
+            c.insert_at(i, key.clone(), old);
+        }
+        (_, Some(NBTTag::List(l)), Replaced::Element(i, old)) => l.insert(i, old),
+        _ => {}
+    }
+}
+
+/// Returns the tag that a key or index points at in `parent`
+fn child<'a>(parent: &'a NBTTag, node: &NBTPathNode) -> Option<&'a NBTTag> {
+    match (node, parent) {
+        (NBTPathNode::Key(key), NBTTag::Compound(c)) => c.get(key),
+        (NBTPathNode::Index(index), NBTTag::List(l)) => resolve_index(*index, l.len()).map(|i| &l[i]),
+        _ => None,
+    }
+}
+
+/// Follows a path of keys and indices from `tag`, returning the tag at the end of it
+fn tag_mut<'a>(mut tag: &'a mut NBTTag, nodes: &[NBTPathNode]) -> Option<&'a mut NBTTag> {
+    for node in nodes {
+        tag = match (node, tag) {
+            (NBTPathNode::Key(key), NBTTag::Compound(c)) => c.get_mut(key)?,
+            (NBTPathNode::Index(index), NBTTag::List(l)) => {
+                let i = resolve_index(*index, l.len())?;
+                &mut l[i]
+            }
+            _ => return None,
+        };
+    }
+
+    Some(tag)
+}
\ No newline at end of file
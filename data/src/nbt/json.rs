@@ -0,0 +1,268 @@
+//! Conversion between `NBTTag`s and JSON
+//!
+//! The lossy conversion works the way vanilla converts between NBT and JSON for text components and registries. Numbers become plain JSON numbers, and when reading JSON each number becomes the smallest tag that holds it, so the original types are lost.
+//!
+//! The lossless conversion wraps every tag in an object with its type, e.g. `{"type":"Short","value":3}`, so that it converts back to exactly the same tag. Longs are written as strings so that JavaScript doesn't round them, and floats that JSON can't hold are written as `"NaN"`, `"Infinity"` and `"-Infinity"`.
+
+use crate::nbt::compound::NBTCompound;
+use crate::nbt::tag::{path_within_index, path_within_key, NBTTag};
+use serde_json::{Map, Number, Value};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+/// The reason that converting JSON into NBT failed
+#[derive(Clone, PartialEq, Debug)]
+pub enum NBTJsonErrorKind {
+    /// A `null` was found somewhere that a tag had to be
+    Null,
+    /// A lossless value wasn't an object with a `type` and `value`
+    MissingTypeHint,
+    /// A lossless `type` wasn't the name of a tag type
+    UnknownType(String),
+    /// A lossless `value` didn't fit in its `type`
+    InvalidValue { expected: &'static str },
+    /// A lossless list had tags of different types in it
+    MixedList { expected: &'static str, found: &'static str },
+}
+
+/// An error from converting JSON into NBT, along with the path of the value that couldn't be converted
+#[derive(Clone, PartialEq, Debug)]
+pub struct NBTJsonError {
+    pub kind: NBTJsonErrorKind,
+    pub path: String,
+}
+
+impl NBTJsonError {
+    pub fn new(kind: NBTJsonErrorKind) -> Self {
+        Self { kind, path: String::new() }
+    }
+
+    fn within_key(mut self, key: &str) -> Self {
+        self.path = path_within_key(key, &self.path);
+        self
+    }
+
+    fn within_index(mut self, index: usize) -> Self {
+        self.path = path_within_index(index, &self.path);
+        self
+    }
+}
+
+impl Display for NBTJsonErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Null => write!(f, "null can't be converted to NBT"),
+            Self::MissingTypeHint => write!(f, "expected an object with a type and value"),
+            Self::UnknownType(ty) => write!(f, "unknown tag type '{ty}'"),
+            Self::InvalidValue { expected } => write!(f, "value isn't a valid {expected}"),
+            Self::MixedList { expected, found } => write!(f, "can't insert {found} into a list of {expected}"),
+        }
+    }
+}
+
+impl Display for NBTJsonError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if !self.path.is_empty() {
+            write!(f, " (in '{}')", self.path)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Error for NBTJsonError {}
+
+impl NBTTag {
+    /// Converts this tag into plain JSON, losing the tag types
+    pub fn to_json(&self) -> Value {
+        match self {
+            NBTTag::Byte(b) => Value::from(*b),
+            NBTTag::Short(s) => Value::from(*s),
+            NBTTag::Int(i) => Value::from(*i),
+            NBTTag::Long(l) => Value::from(*l),
+            NBTTag::Float(f) => float_to_json(*f),
+            NBTTag::Double(d) => Value::from(*d),
+            NBTTag::ByteArray(ba) => Value::Array(ba.iter().map(|b| Value::from(*b as i8)).collect()),
+            NBTTag::String(s) => Value::String(s.clone()),
+            NBTTag::List(l) => Value::Array(l.iter().map(NBTTag::to_json).collect()),
+            NBTTag::Compound(c) => Value::Object(c.iter().map(|(key, tag)| (key.clone(), tag.to_json())).collect()),
+            NBTTag::IntArray(ia) => Value::Array(ia.iter().map(|i| Value::from(*i)).collect()),
+            NBTTag::LongArray(la) => Value::Array(la.iter().map(|l| Value::from(*l)).collect()),
+        }
+    }
+
+    /// Converts plain JSON into a tag the way vanilla does
+    ///
+    /// Booleans become `Byte`s, and whole numbers become the smallest of `Byte`, `Short`, `Int` or `Long` that holds them, and other numbers become a `Float` if that is exact, otherwise a `Double`. Arrays of only bytes, ints or longs become typed arrays, and arrays with different types in them become lists of compounds, with each element that isn't already a compound stored under the key `""`. `null`s in objects and arrays are left out.
+    pub fn from_json(value: &Value) -> Result<NBTTag, NBTJsonError> {
+        match value {
+            Value::Null => Err(NBTJsonError::new(NBTJsonErrorKind::Null)),
+            Value::Bool(b) => Ok(NBTTag::Byte(*b as i8)),
+            Value::Number(n) => Ok(number_from_json(n)),
+            Value::String(s) => Ok(NBTTag::String(s.clone())),
+            Value::Array(values) => {
+                let tags = values.iter().enumerate()
+                    .filter(|(_, value)| !value.is_null())
+                    .map(|(i, value)| NBTTag::from_json(value).map_err(|e| e.within_index(i)))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                Ok(collect_list(tags))
+            }
+            Value::Object(entries) => entries.iter()
+                .filter(|(_, value)| !value.is_null())
+                .map(|(key, value)| Ok((key.clone(), NBTTag::from_json(value).map_err(|e| e.within_key(key))?)))
+                .collect::<Result<NBTCompound, _>>()
+                .map(NBTTag::Compound),
+        }
+    }
+
+    /// Converts this tag into JSON with every tag wrapped in an object that holds its type
+    pub fn to_json_lossless(&self) -> Value {
+        let value = match self {
+            NBTTag::Byte(b) => Value::from(*b),
+            NBTTag::Short(s) => Value::from(*s),
+            NBTTag::Int(i) => Value::from(*i),
+            NBTTag::Long(l) => Value::String(l.to_string()),
+            NBTTag::Float(f) if f.is_finite() => float_to_json(*f),
+            NBTTag::Float(f) => non_finite_to_json(*f as f64),
+            NBTTag::Double(d) if d.is_finite() => Value::from(*d),
+            NBTTag::Double(d) => non_finite_to_json(*d),
+            NBTTag::ByteArray(ba) => Value::Array(ba.iter().map(|b| Value::from(*b as i8)).collect()),
+            NBTTag::String(s) => Value::String(s.clone()),
+            NBTTag::List(l) => Value::Array(l.iter().map(NBTTag::to_json_lossless).collect()),
+            NBTTag::Compound(c) => Value::Object(c.iter().map(|(key, tag)| (key.clone(), tag.to_json_lossless())).collect()),
+            NBTTag::IntArray(ia) => Value::Array(ia.iter().map(|i| Value::from(*i)).collect()),
+            NBTTag::LongArray(la) => Value::Array(la.iter().map(|l| Value::String(l.to_string())).collect()),
+        };
+
+        let mut object = Map::new();
+        object.insert("type".to_string(), Value::String(self.name().to_string()));
+        object.insert("value".to_string(), value);
+        Value::Object(object)
+    }
+
+    /// Converts JSON written by `to_json_lossless` back into a tag. Longs and floats can also be plain JSON numbers.
+    pub fn from_json_lossless(value: &Value) -> Result<NBTTag, NBTJsonError> {
+        let (Some(Value::String(ty)), Some(value)) = (value.get("type"), value.get("value")) else {
+            return Err(NBTJsonError::new(NBTJsonErrorKind::MissingTypeHint));
+        };
+
+        let invalid = |expected: &'static str| NBTJsonError::new(NBTJsonErrorKind::InvalidValue { expected });
+        let tag = match ty.as_str() {
+            "Byte" => NBTTag::Byte(integer(value).ok_or_else(|| invalid("Byte"))?),
+            "Short" => NBTTag::Short(integer(value).ok_or_else(|| invalid("Short"))?),
+            "Int" => NBTTag::Int(integer(value).ok_or_else(|| invalid("Int"))?),
+            "Long" => NBTTag::Long(integer(value).ok_or_else(|| invalid("Long"))?),
+            "Float" => NBTTag::Float(float(value).ok_or_else(|| invalid("Float"))? as f32),
+            "Double" => NBTTag::Double(float(value).ok_or_else(|| invalid("Double"))?),
+            "String" => NBTTag::String(value.as_str().ok_or_else(|| invalid("String"))?.to_string()),
+            "ByteArray" => NBTTag::ByteArray(array(value, |value| integer::<i8>(value).map(|b| b as u8)).ok_or_else(|| invalid("ByteArray"))??),
+            "IntArray" => NBTTag::IntArray(array(value, integer).ok_or_else(|| invalid("IntArray"))??),
+            "LongArray" => NBTTag::LongArray(array(value, integer).ok_or_else(|| invalid("LongArray"))??),
+            "List" => {
+                let values = value.as_array().ok_or_else(|| invalid("List"))?;
+
+                let mut list: Vec<NBTTag> = Vec::with_capacity(values.len());
+                for (i, value) in values.iter().enumerate() {
+                    let tag = NBTTag::from_json_lossless(value).map_err(|e| e.within_index(i))?;
+                    if let Some(first) = list.first().filter(|first| first.id() != tag.id()) {
+                        return Err(NBTJsonError::new(NBTJsonErrorKind::MixedList { expected: first.name(), found: tag.name() }).within_index(i));
+                    }
+
+                    list.push(tag);
+                }
+
+                NBTTag::List(list)
+            }
+            "Compound" => {
+                let entries = value.as_object().ok_or_else(|| invalid("Compound"))?;
+
+                let mut compound = NBTCompound::with_capacity(entries.len());
+                for (key, value) in entries {
+                    compound.insert(key.clone(), NBTTag::from_json_lossless(value).map_err(|e| e.within_key(key))?);
+                }
+
+                NBTTag::Compound(compound)
+            }
+            _ => return Err(NBTJsonError::new(NBTJsonErrorKind::UnknownType(ty.clone()))),
+        };
+
+        Ok(tag)
+    }
+}
+
+/// Goes through the float's shortest decimal form, so that e.g. `0.1f` becomes `0.1` and not `0.10000000149011612`
+fn float_to_json(f: f32) -> Value {
+    f.to_string().parse::<f64>().map_or(Value::Null, Value::from)
+}
+
+fn non_finite_to_json(d: f64) -> Value {
+    let s = if d.is_nan() { "NaN" } else if d > 0.0 { "Infinity" } else { "-Infinity" };
+    Value::String(s.to_string())
+}
+
+fn number_from_json(n: &Number) -> NBTTag {
+    let whole = n.as_i64().or_else(|| n.as_f64().filter(|f| f.fract() == 0.0 && *f >= i64::MIN as f64 && *f < i64::MAX as f64).map(|f| f as i64));
+
+    match whole {
+        Some(l) if i8::try_from(l).is_ok() => NBTTag::Byte(l as i8),
+        Some(l) if i16::try_from(l).is_ok() => NBTTag::Short(l as i16),
+        Some(l) if i32::try_from(l).is_ok() => NBTTag::Int(l as i32),
+        Some(l) => NBTTag::Long(l),
+        None => {
+            let d = n.as_f64().unwrap_or_default();
+            if d as f32 as f64 == d { NBTTag::Float(d as f32) } else { NBTTag::Double(d) }
+        }
+    }
+}
+
+/// Turns the elements of a JSON array into a tag the same way vanilla's list collectors do
+fn collect_list(tags: Vec<NBTTag>) -> NBTTag {
+    let Some(first) = tags.first() else {
+        return NBTTag::List(tags);
+    };
+
+    if tags.iter().any(|tag| tag.id() != first.id()) {
+        return NBTTag::List(tags.into_iter().map(|tag| match tag {
+            NBTTag::Compound(c) if !(c.len() == 1 && c.contains_key("")) => NBTTag::Compound(c),
+            tag => NBTTag::Compound(NBTCompound::from(vec![(String::new(), tag)])),
+        }).collect());
+    }
+
+    match first {
+        NBTTag::Byte(_) => NBTTag::ByteArray(tags.into_iter().filter_map(|tag| if let NBTTag::Byte(b) = tag { Some(b as u8) } else { None }).collect()),
+        NBTTag::Int(_) => NBTTag::IntArray(tags.into_iter().filter_map(|tag| if let NBTTag::Int(i) = tag { Some(i) } else { None }).collect()),
+        NBTTag::Long(_) => NBTTag::LongArray(tags.into_iter().filter_map(|tag| if let NBTTag::Long(l) = tag { Some(l) } else { None }).collect()),
+        _ => NBTTag::List(tags),
+    }
+}
+
+/// Reads an integer from a JSON number or a string, failing if it doesn't fit in `T`
+fn integer<T: TryFrom<i64>>(value: &Value) -> Option<T> {
+    let l = match value {
+        Value::Number(n) => n.as_i64()?,
+        Value::String(s) => s.parse().ok()?,
+        _ => return None,
+    };
+
+    T::try_from(l).ok()
+}
+
+/// Reads a float from a JSON number, or a string so that non-finite floats can be stored
+fn float(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Reads every element of a JSON array, returning `None` if it isn't an array and `Some(Err)` if an element is invalid
+fn array<T>(value: &Value, element: impl Fn(&Value) -> Option<T>) -> Option<Result<Vec<T>, NBTJsonError>> {
+    let values = value.as_array()?;
+
+    Some(values.iter().enumerate().map(|(i, value)| {
+        element(value).ok_or_else(|| NBTJsonError::new(NBTJsonErrorKind::InvalidValue { expected: "array element" }).within_index(i))
+    }).collect())
+}
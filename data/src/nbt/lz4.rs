@@ -0,0 +1,101 @@
+//! The block stream format of lz4-java's `LZ4BlockOutputStream`, which vanilla uses for LZ4 compressed chunks
+//!
+//! The data is split into blocks of up to 64 KiB. Each block has a 21 byte header of the magic `LZ4Block`, a token holding the compression method and block size, the little endian compressed and original lengths, and an XXHash32 checksum of the original data. An empty block marks the end of the stream.
+
+use std::io;
+use xxhash_rust::xxh32::xxh32;
+
+pub const MAGIC: &[u8; 8] = b"LZ4Block";
+
+const HEADER_LEN: usize = 21;
+const BLOCK_SIZE: usize = 64 * 1024;
+/// The block size as a power of 2, minus 10
+const COMPRESSION_LEVEL: u8 = 6;
+const METHOD_RAW: u8 = 0x10;
+const METHOD_LZ4: u8 = 0x20;
+const CHECKSUM_SEED: u32 = 0x9747B28C;
+
+/// lz4-java only keeps the lower 28 bits of the hash
+fn checksum(data: &[u8]) -> u32 {
+    xxh32(data, CHECKSUM_SEED) & 0x0FFF_FFFF
+}
+
+/// Compresses `data` into a block stream, storing blocks that don't get any smaller uncompressed
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() / 2 + HEADER_LEN * 2);
+    for block in data.chunks(BLOCK_SIZE) {
+        let compressed = lz4_flex::block::compress(block);
+        let (method, payload) = if compressed.len() < block.len() { (METHOD_LZ4, &compressed[..]) } else { (METHOD_RAW, block) };
+
+        write_header(&mut out, method, payload.len(), block.len(), checksum(block));
+        out.extend_from_slice(payload);
+    }
+
+    write_header(&mut out, METHOD_RAW, 0, 0, 0);
+    out
+}
+
+fn write_header(out: &mut Vec<u8>, method: u8, compressed_len: usize, original_len: usize, checksum: u32) {
+    out.extend_from_slice(MAGIC);
+    out.push(method | COMPRESSION_LEVEL);
+    out.extend_from_slice(&(compressed_len as u32).to_le_bytes());
+    out.extend_from_slice(&(original_len as u32).to_le_bytes());
+    out.extend_from_slice(&checksum.to_le_bytes());
+}
+
+/// Decompresses a block stream, stopping at the end block like lz4-java does
+pub fn decompress(mut data: &[u8]) -> io::Result<Vec<u8>> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+
+    let mut out = Vec::new();
+    while !data.is_empty() {
+        if data.len() < HEADER_LEN {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        if !data.starts_with(MAGIC) {
+            return Err(invalid("LZ4 block is missing its magic"));
+        }
+
+        let read_u32 = |offset: usize| u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]]);
+        let token = data[8];
+        let compressed_len = read_u32(9) as usize;
+        let original_len = read_u32(13) as usize;
+        let check = read_u32(17);
+
+        let max_len = 1 << (10 + (token & 0x0F));
+        if original_len > max_len || compressed_len > max_len {
+            return Err(invalid("LZ4 block is larger than its block size"));
+        }
+
+        data = &data[HEADER_LEN..];
+        if original_len == 0 && compressed_len == 0 {
+            break;
+        }
+        if data.len() < compressed_len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        let (payload, rest) = data.split_at(compressed_len);
+        let start = out.len();
+        match token & 0xF0 {
+            METHOD_RAW if compressed_len == original_len => out.extend_from_slice(payload),
+            METHOD_LZ4 => {
+                let block = lz4_flex::block::decompress(payload, original_len).map_err(|error| invalid(&error.to_string()))?;
+                if block.len() != original_len {
+                    return Err(invalid("LZ4 block decompressed to the wrong length"));
+                }
+
+                out.extend_from_slice(&block);
+            }
+            _ => return Err(invalid("LZ4 block has an invalid compression method")),
+        }
+
+        if checksum(&out[start..]) != check {
+            return Err(invalid("LZ4 block checksum doesn't match"));
+        }
+
+        data = rest;
+    }
+
+    Ok(out)
+}
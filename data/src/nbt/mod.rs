@@ -1,13 +1,66 @@
-use crate::nbt::tag::NBTTag;
+use crate::nbt::codec::{JavaCodec, NBTCodec};
+use crate::nbt::tag::{NBTDecodeOptions, NBTTag, NBTTagDeserializeError, NBTTagDeserializeErrorKind, NBTTagSerializeError};
 use crate::queue::Queue;
 use flate2::bufread::{GzDecoder, ZlibDecoder};
+use flate2::write::{GzEncoder, ZlibEncoder};
+use flate2::Compression;
 use std::fmt::{Display, Formatter};
-use std::io::Read;
-
-// TODO: better error handling for parsing a Vec<u8>,
-// TODO: SNBT saving and parsing
+use std::io::{Read, Write};
 
 pub mod tag;
+pub mod codec;
+pub mod compound;
+pub mod convert;
+pub mod mutf8;
+pub mod lz4;
+pub mod borrowed;
+pub mod snbt;
+pub mod json;
+pub mod path;
+pub mod diff;
+pub mod schema;
+pub mod serde;
+
+/// The compression that an NBT file can be written with
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum NBTCompression {
+    None,
+    /// Used by player data and level.dat
+    #[default]
+    Gzip,
+    /// Used by chunks in region files
+    Zlib,
+    /// lz4-java's block stream format, which region files can be configured to use
+    Lz4,
+}
+
+impl NBTCompression {
+    /// Guesses the compression of some data from its first bytes, assuming it is `None` if it doesn't look compressed
+    pub fn detect(bytes: &[u8]) -> Self {
+        if bytes.starts_with(&[0x1F, 0x8B]) {
+            Self::Gzip
+        } else if bytes.starts_with(&[0x78, 0x01]) || bytes.starts_with(&[0x78, 0x9C]) || bytes.starts_with(&[0x78, 0xDA]) {
+            Self::Zlib
+        } else if bytes.starts_with(lz4::MAGIC) {
+            Self::Lz4
+        } else {
+            Self::None
+        }
+    }
+
+    /// Decompresses data that was compressed with this compression
+    pub fn decompress(self, bytes: Vec<u8>) -> Result<Vec<u8>, NBTTagDeserializeError> {
+        let mut out = Vec::new();
+        match self {
+            Self::None => return Ok(bytes),
+            Self::Gzip => GzDecoder::new(&bytes[..]).read_to_end(&mut out).map(|_| ()),
+            Self::Zlib => ZlibDecoder::new(&bytes[..]).read_to_end(&mut out).map(|_| ()),
+            Self::Lz4 => lz4::decompress(&bytes).map(|decompressed| out = decompressed),
+        }.map_err(decompression_error)?;
+
+        Ok(out)
+    }
+}
 
 #[derive(Debug, PartialEq)]
 pub struct NBT {
@@ -38,57 +91,110 @@ impl NBT {
         self.root_name = Some(name.into());
     }
 
+    /// Encodes this NBT uncompressed into a new `Vec<u8>`
+    ///
+    /// # Panics
+    /// Panics if a list has tags of different types in it or a string is too long, use `write_to` to get an error instead
     pub fn as_bytes(&self) -> Vec<u8> {
         let mut out = Vec::new();
-        out.push(self.root_tag.id());
-        if let Some(name) = &self.root_name {
-            out.extend((name.len() as u16).to_be_bytes());
-            out.extend(name.bytes());
+        if let Err(error) = self.write_to(&mut out) {
+            panic!("Failed to encode NBT: {error}");
         }
 
-        out.extend(self.root_tag.as_bytes());
-
         out
     }
 
-    pub fn from_bytes_network(queue: &mut Queue) -> Option<Self> {
-        let id = queue.pop::<u8>().expect("");
-        let mut name = None;
-
-        Some(Self {
-            root_name: name,
-            root_tag: NBTTag::from_bytes(queue, id).expect(""),
-        })
+    /// Writes this NBT uncompressed straight into `writer`
+    pub fn write_to<W: Write + ?Sized>(&self, writer: &mut W) -> Result<(), NBTTagSerializeError> {
+        self.write_with::<JavaCodec, W>(writer)
     }
 
-    pub fn from_bytes_disk(bytes: impl Into<Vec<u8>>) -> Option<Self> {
-        let mut bytes = bytes.into();
-        if bytes.starts_with(&[0x1F, 0x8B]) {
-            let copied = bytes.clone();
-            let mut decoder = GzDecoder::new(&copied[..]);
-            bytes.clear();
+    /// Writes this NBT uncompressed like `write_to`, but with the numbers and strings encoded by `C`
+    pub fn write_with<C: NBTCodec, W: Write + ?Sized>(&self, writer: &mut W) -> Result<(), NBTTagSerializeError> {
+        writer.write_all(&[self.root_tag.id()])?;
+        if let Some(name) = &self.root_name {
+            C::write_string(writer, name)?;
+        }
 
-            decoder.read_to_end(&mut bytes).ok()?;
-        } else if bytes.starts_with(&[0x78, 0x01]) || bytes.starts_with(&[0x78, 0x9C]) || bytes.starts_with(&[0x78, 0xDA]) {
-            let copied = bytes.clone();
-            let mut decoder = ZlibDecoder::new(&copied[..]);
-            bytes.clear();
+        self.root_tag.write_with::<C, W>(writer)
+    }
 
-            decoder.read_to_end(&mut bytes).ok()?;
+    /// Writes this NBT into `writer` using the given compression, which `from_bytes_disk` is able to detect when reading it back
+    pub fn write_compressed_to<W: Write>(&self, writer: W, compression: NBTCompression) -> Result<(), NBTTagSerializeError> {
+        match compression {
+            NBTCompression::None => {
+                let mut writer = writer;
+                self.write_to(&mut writer)
+            }
+            NBTCompression::Gzip => {
+                let mut encoder = GzEncoder::new(writer, Compression::default());
+                self.write_to(&mut encoder)?;
+                encoder.finish()?;
+                Ok(())
+            }
+            NBTCompression::Zlib => {
+                let mut encoder = ZlibEncoder::new(writer, Compression::default());
+                self.write_to(&mut encoder)?;
+                encoder.finish()?;
+                Ok(())
+            }
+            NBTCompression::Lz4 => {
+                let mut uncompressed = Vec::new();
+                self.write_to(&mut uncompressed)?;
+
+                let mut writer = writer;
+                writer.write_all(&lz4::compress(&uncompressed))?;
+                Ok(())
+            }
         }
+    }
+
+    /// Reads a nameless network NBT off of the queue, within the strict `NBTDecodeOptions::NETWORK` limits since it comes from a client
+    pub fn from_bytes_network(queue: &mut Queue) -> Result<Self, NBTTagDeserializeError> {
+        Self::from_bytes_network_with_options(queue, &NBTDecodeOptions::NETWORK)
+    }
+
+    /// Reads a nameless network NBT off of the queue within the given limits
+    pub fn from_bytes_network_with_options(queue: &mut Queue, options: &NBTDecodeOptions) -> Result<Self, NBTTagDeserializeError> {
+        let id = queue.read_be::<u8>().ok_or_else(|| missing_id(queue))?;
+
+        Ok(Self {
+            root_name: None,
+            root_tag: NBTTag::from_bytes_with_options::<JavaCodec>(queue, id, options)?,
+        })
+    }
 
-        let mut queue: Queue = bytes.into();
-        let id = queue.pop::<u8>().expect("");
-        let mut name = None;
+    /// Reads an uncompressed named NBT off of the queue, with the numbers and strings decoded by `C`. Bedrock's network NBT has a root name too, unlike Java's.
+    pub fn from_bytes_with<C: NBTCodec>(queue: &mut Queue) -> Result<Self, NBTTagDeserializeError> {
+        Self::from_bytes_with_options::<C>(queue, &NBTDecodeOptions::default())
+    }
 
-        let name_len = queue.pop::<u16>().expect("") as usize;
-        name = Some(queue.pop_str(name_len).expect(""));
+    /// Reads an uncompressed named NBT like `from_bytes_with`, within the given limits
+    pub fn from_bytes_with_options<C: NBTCodec>(queue: &mut Queue, options: &NBTDecodeOptions) -> Result<Self, NBTTagDeserializeError> {
+        let id = queue.read_be::<u8>().ok_or_else(|| missing_id(queue))?;
+        let name = C::read_string(queue)?;
 
-        Some(Self {
-            root_name: name,
-            root_tag: NBTTag::from_bytes(&mut queue, id).expect(""),
+        Ok(Self {
+            root_name: Some(name),
+            root_tag: NBTTag::from_bytes_with_options::<C>(queue, id, options)?,
         })
     }
+
+    /// Reads a named NBT, decompressing it first if it is gzip, zlib or LZ4 compressed
+    pub fn from_bytes_disk(bytes: impl Into<Vec<u8>>) -> Result<Self, NBTTagDeserializeError> {
+        let bytes = bytes.into();
+        let bytes = NBTCompression::detect(&bytes).decompress(bytes)?;
+
+        Self::from_bytes_with::<JavaCodec>(&mut bytes.into())
+    }
+}
+
+fn missing_id(queue: &Queue) -> NBTTagDeserializeError {
+    NBTTagDeserializeError::new(NBTTagDeserializeErrorKind::UnexpectedEnd { needed: 1, remaining: queue.bytes_left() }, queue.position())
+}
+
+fn decompression_error(error: std::io::Error) -> NBTTagDeserializeError {
+    NBTTagDeserializeError::new(NBTTagDeserializeErrorKind::Decompression(error.kind()), 0)
 }
 
 impl Display for NBT {
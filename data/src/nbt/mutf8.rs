@@ -0,0 +1,83 @@
+//! Java's Modified UTF-8, which is how every string and tag name in Java Edition NBT is stored
+//!
+//! It is the same as UTF-8, except that NUL is stored as the two bytes `C0 80`, and characters outside of the Basic Multilingual Plane (like emoji) are stored as a UTF-16 surrogate pair, with each half taking 3 bytes.
+
+use std::borrow::Cow;
+use std::char::REPLACEMENT_CHARACTER;
+
+/// Encodes a string as Modified UTF-8, only allocating if the string has a NUL or a character outside of the BMP in it
+pub fn encode(s: &str) -> Cow<'_, [u8]> {
+    if !s.chars().any(|c| c == '\0' || c as u32 > 0xFFFF) {
+        return Cow::Borrowed(s.as_bytes());
+    }
+
+    let mut out = Vec::with_capacity(s.len() + 2);
+    for c in s.chars() {
+        if c == '\0' {
+            out.extend([0xC0, 0x80]);
+        } else if c as u32 > 0xFFFF {
+            for unit in c.encode_utf16(&mut [0; 2]) {
+                out.extend([0xE0 | (*unit >> 12) as u8, 0x80 | ((*unit >> 6) & 0x3F) as u8, 0x80 | (*unit & 0x3F) as u8]);
+            }
+        } else {
+            out.extend(c.encode_utf8(&mut [0; 4]).as_bytes());
+        }
+    }
+
+    Cow::Owned(out)
+}
+
+/// Decodes a Modified UTF-8 string, returning `None` if the bytes aren't valid
+///
+/// The string is borrowed if the bytes are also valid UTF-8, which is the case for anything without a NUL or a character outside of the BMP. Plain 4 byte UTF-8 characters are also accepted, since some non-Java tools write them. Java allows a surrogate without its other half, but Rust strings don't, so those are replaced with U+FFFD.
+pub fn decode(bytes: &[u8]) -> Option<Cow<'_, str>> {
+    if let Ok(s) = std::str::from_utf8(bytes) {
+        return Some(Cow::Borrowed(s));
+    }
+
+    let mut units = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let (code_point, len) = code_point_at(bytes, i)?;
+        if code_point > 0xFFFF {
+            units.extend(char::from_u32(code_point)?.encode_utf16(&mut [0; 2]).iter());
+        } else {
+            units.push(code_point as u16);
+        }
+
+        i += len;
+    }
+
+    Some(Cow::Owned(char::decode_utf16(units).map(|c| c.unwrap_or(REPLACEMENT_CHARACTER)).collect()))
+}
+
+/// Checks whether `decode` would accept the bytes, without allocating
+pub fn is_valid(bytes: &[u8]) -> bool {
+    if std::str::from_utf8(bytes).is_ok() {
+        return true;
+    }
+
+    let mut i = 0;
+    while i < bytes.len() {
+        match code_point_at(bytes, i) {
+            Some((code_point, len)) if code_point <= 0xFFFF || char::from_u32(code_point).is_some() => i += len,
+            _ => return false,
+        }
+    }
+
+    true
+}
+
+/// Reads the code point that starts at `i`, returning it and how many bytes it took
+fn code_point_at(bytes: &[u8], i: usize) -> Option<(u32, usize)> {
+    let continuation = |offset: usize| bytes.get(i + offset).filter(|b| *b & 0xC0 == 0x80).map(|b| (*b & 0x3F) as u32);
+
+    let b = bytes[i] as u32;
+    match b {
+        0x00..=0x7F => Some((b, 1)),
+        0xC0..=0xDF => Some(((b & 0x1F) << 6 | continuation(1)?, 2)),
+        0xE0..=0xEF => Some(((b & 0x0F) << 12 | continuation(1)? << 6 | continuation(2)?, 3)),
+        0xF0..=0xF7 => Some(((b & 0x07) << 18 | continuation(1)? << 12 | continuation(2)? << 6 | continuation(3)?, 4)),
+        _ => None,
+    }
+}
@@ -0,0 +1,489 @@
+//! NBT paths, the syntax that the `/data` command uses to point at tags inside of other tags, e.g. `Inventory[{Slot:0b}].components."minecraft:custom_name"`
+//!
+//! A path is made of nodes separated by `.`, and `[` starts a node without needing a `.` in front of it:
+//! - `name` or `"quoted name"` is a compound entry, and `name{Count:1b}` only matches it if it contains the filter
+//! - `[0]` is an element of a list or array, where negative indices count back from the end
+//! - `[]` is every element of a list or array, and `[{Slot:0b}]` is every compound in a list that contains the filter
+//! - `{OnGround:1b}` at the very start only matches the root if it contains the filter
+
+use crate::nbt::compound::NBTCompound;
+use crate::nbt::snbt::{parse_compound_at, parse_quoted_at, write_quoted, SNBTParseError};
+use crate::nbt::tag::NBTTag;
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// The reason that parsing an NBT path failed
+#[derive(Clone, PartialEq, Debug)]
+pub enum NBTPathParseErrorKind {
+    /// The path was empty
+    Empty,
+    /// The path ended in the middle of a node
+    UnexpectedEnd,
+    /// A character was found where a different one was expected
+    Expected { expected: &'static str, found: char },
+    /// A list index wasn't a valid `i32`
+    InvalidIndex(String),
+    /// A compound filter or quoted key wasn't valid SNBT
+    InvalidSNBT(SNBTParseError),
+}
+
+/// An error from parsing an NBT path, along with the character offset in the input where it happened
+#[derive(Clone, PartialEq, Debug)]
+pub struct NBTPathParseError {
+    pub kind: NBTPathParseErrorKind,
+    pub position: usize,
+}
+
+impl NBTPathParseError {
+    fn new(kind: NBTPathParseErrorKind, position: usize) -> Self {
+        Self { kind, position }
+    }
+}
+
+impl From<SNBTParseError> for NBTPathParseError {
+    fn from(value: SNBTParseError) -> Self {
+        Self::new(NBTPathParseErrorKind::InvalidSNBT(value.clone()), value.position)
+    }
+}
+
+impl Display for NBTPathParseErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Empty => write!(f, "the path is empty"),
+            Self::UnexpectedEnd => write!(f, "unexpected end of path"),
+            Self::Expected { expected, found } => write!(f, "expected {expected} but found '{found}'"),
+            Self::InvalidIndex(index) => write!(f, "invalid list index '{index}'"),
+            Self::InvalidSNBT(error) => write!(f, "{}", error.kind),
+        }
+    }
+}
+
+impl Display for NBTPathParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at position {}", self.kind, self.position)
+    }
+}
+
+impl Error for NBTPathParseError {}
+
+/// A single step of an NBT path
+#[derive(Clone, PartialEq, Debug)]
+pub enum NBTPathNode {
+    /// `{filter}`, which can only be the first node
+    RootFilter(NBTTag),
+    /// `name`
+    Key(String),
+    /// `name{filter}`
+    KeyFilter(String, NBTTag),
+    /// `[index]`
+    Index(i32),
+    /// `[]`
+    All,
+    /// `[{filter}]`
+    ListFilter(NBTTag),
+}
+
+/// A parsed NBT path that can get, set, remove and merge the tags it points at
+#[derive(Clone, PartialEq, Debug)]
+pub struct NBTPath {
+    nodes: Vec<NBTPathNode>,
+}
+
+impl NBTPath {
+    pub fn parse(path: &str) -> Result<Self, NBTPathParseError> {
+        let chars: Vec<char> = path.chars().collect();
+        if chars.is_empty() {
+            return Err(NBTPathParseError::new(NBTPathParseErrorKind::Empty, 0));
+        }
+
+        let mut nodes = Vec::new();
+        let mut cursor = 0;
+        while cursor < chars.len() {
+            let (node, next) = parse_node(&chars, cursor, nodes.is_empty())?;
+            nodes.push(node);
+            cursor = next;
+
+            match chars.get(cursor) {
+                None | Some('[') => {}
+                Some('.') if cursor + 1 < chars.len() => cursor += 1,
+                Some('.') => return Err(NBTPathParseError::new(NBTPathParseErrorKind::UnexpectedEnd, cursor + 1)),
+                Some(&found) => return Err(NBTPathParseError::new(NBTPathParseErrorKind::Expected { expected: "'.' or '['", found }, cursor)),
+            }
+        }
+
+        Ok(Self { nodes })
+    }
+
+    pub fn nodes(&self) -> &[NBTPathNode] {
+        &self.nodes
+    }
+
+    /// Returns every tag that the path points at
+    ///
+    /// Elements of typed arrays aren't stored as tags, so they are returned owned, and everything else is borrowed from `root`.
+    pub fn get<'a>(&self, root: &'a NBTTag) -> Vec<Cow<'a, NBTTag>> {
+        let mut found = vec![Cow::Borrowed(root)];
+        for node in &self.nodes {
+            found = found.into_iter().flat_map(|tag| match tag {
+                Cow::Borrowed(tag) => children(node, tag),
+                // only array elements are owned, and numbers don't have children
+                Cow::Owned(_) => Vec::new(),
+            }).collect();
+        }
+
+        found
+    }
+
+    /// Sets every tag that the path points at to `value`, and returns how many tags were changed
+    ///
+    /// Missing compound entries on the way are created, as a compound or a list depending on the node after them. Elements of lists and arrays are only replaced if `value` has the same type as the other elements.
+    pub fn set(&self, root: &mut NBTTag, value: &NBTTag) -> usize {
+        let Some((last, parents)) = self.nodes.split_last() else {
+            return 0;
+        };
+
+        walk_mut(root, parents, Some(last), true, &mut |parent| set_child(last, parent, value))
+    }
+
+    /// Removes every tag that the path points at, and returns how many were removed
+    pub fn remove(&self, root: &mut NBTTag) -> usize {
+        let Some((last, parents)) = self.nodes.split_last() else {
+            return 0;
+        };
+
+        walk_mut(root, parents, None, false, &mut |parent| remove_child(last, parent))
+    }
+
+    /// Merges the compound `value` into every compound that the path points at with `NBTTag::merge`, creating them if they are missing. Returns how many compounds were merged into.
+    pub fn merge(&self, root: &mut NBTTag, value: &NBTTag) -> usize {
+        if !matches!(value, NBTTag::Compound(_)) {
+            return 0;
+        }
+
+        walk_mut(root, &self.nodes, None, true, &mut |target| {
+            if !matches!(target, NBTTag::Compound(_)) {
+                return 0;
+            }
+
+            target.merge(value);
+            1
+        })
+    }
+}
+
+impl From<Vec<NBTPathNode>> for NBTPath {
+    fn from(nodes: Vec<NBTPathNode>) -> Self {
+        Self { nodes }
+    }
+}
+
+impl FromStr for NBTPath {
+    type Err = NBTPathParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl Display for NBTPath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut out = String::new();
+        for (i, node) in self.nodes.iter().enumerate() {
+            match node {
+                NBTPathNode::RootFilter(filter) => out.push_str(&filter.as_snbt()),
+                NBTPathNode::Key(key) | NBTPathNode::KeyFilter(key, _) => {
+                    if i != 0 {
+                        out.push('.');
+                    }
+
+                    // `:` and friends are allowed unquoted too, but quoting anything unusual is easier to read
+                    if !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '+')) {
+                        out.push_str(key);
+                    } else {
+                        write_quoted(&mut out, key);
+                    }
+
+                    if let NBTPathNode::KeyFilter(_, filter) = node {
+                        out.push_str(&filter.as_snbt());
+                    }
+                }
+                NBTPathNode::Index(index) => out.push_str(&format!("[{index}]")),
+                NBTPathNode::All => out.push_str("[]"),
+                NBTPathNode::ListFilter(filter) => out.push_str(&format!("[{}]", filter.as_snbt())),
+            }
+        }
+
+        write!(f, "{out}")
+    }
+}
+
+fn is_unquoted_char(c: char) -> bool {
+    !matches!(c, ' ' | '"' | '\'' | '[' | ']' | '.' | '{' | '}')
+}
+
+/// Parses the node starting at `cursor`, returning it and the position after it
+fn parse_node(chars: &[char], cursor: usize, first: bool) -> Result<(NBTPathNode, usize), NBTPathParseError> {
+    match chars[cursor] {
+        '{' if first => {
+            let (filter, next) = parse_compound_at(chars, cursor)?;
+            Ok((NBTPathNode::RootFilter(filter), next))
+        }
+        '[' => parse_list_node(chars, cursor + 1),
+        '"' | '\'' => {
+            let (key, next) = parse_quoted_at(chars, cursor)?;
+            parse_key_filter(chars, key, next)
+        }
+        _ => {
+            let mut next = cursor;
+            while chars.get(next).copied().is_some_and(is_unquoted_char) {
+                next += 1;
+            }
+
+            if next == cursor {
+                return Err(NBTPathParseError::new(NBTPathParseErrorKind::Expected { expected: "a key or '['", found: chars[cursor] }, cursor));
+            }
+
+            parse_key_filter(chars, chars[cursor..next].iter().collect(), next)
+        }
+    }
+}
+
+/// Turns a key into a node, along with the filter after it if there is one
+fn parse_key_filter(chars: &[char], key: String, cursor: usize) -> Result<(NBTPathNode, usize), NBTPathParseError> {
+    if chars.get(cursor) != Some(&'{') {
+        return Ok((NBTPathNode::Key(key), cursor));
+    }
+
+    let (filter, next) = parse_compound_at(chars, cursor)?;
+    Ok((NBTPathNode::KeyFilter(key, filter), next))
+}
+
+/// Parses what comes after a `[`
+fn parse_list_node(chars: &[char], cursor: usize) -> Result<(NBTPathNode, usize), NBTPathParseError> {
+    let (node, next) = match chars.get(cursor) {
+        None => return Err(NBTPathParseError::new(NBTPathParseErrorKind::UnexpectedEnd, cursor)),
+        Some(']') => (NBTPathNode::All, cursor),
+        Some('{') => {
+            let (filter, next) = parse_compound_at(chars, cursor)?;
+            (NBTPathNode::ListFilter(filter), next)
+        }
+        Some(_) => {
+            let end = chars[cursor..].iter().position(|c| *c == ']').map_or(chars.len(), |i| cursor + i);
+            let index: String = chars[cursor..end].iter().collect();
+            let Ok(index) = index.parse() else {
+                return Err(NBTPathParseError::new(NBTPathParseErrorKind::InvalidIndex(index), cursor));
+            };
+
+            (NBTPathNode::Index(index), end)
+        }
+    };
+
+    match chars.get(next) {
+        Some(']') => Ok((node, next + 1)),
+        Some(&found) => Err(NBTPathParseError::new(NBTPathParseErrorKind::Expected { expected: "']'", found }, next)),
+        None => Err(NBTPathParseError::new(NBTPathParseErrorKind::UnexpectedEnd, next)),
+    }
+}
+
+/// Whether `tag` contains everything in `filter`, the same way vanilla compares them. Compounds can have extra entries, and every element of a list in the filter has to match some element of the list in the tag.
+fn matches_filter(filter: &NBTTag, tag: &NBTTag) -> bool {
+    match (filter, tag) {
+        (NBTTag::Compound(filter), NBTTag::Compound(_)) => filter.iter().all(|(key, filter)| tag.get(key.as_str()).is_some_and(|tag| matches_filter(filter, tag))),
+        (NBTTag::List(filter), NBTTag::List(list)) if filter.is_empty() => list.is_empty(),
+        (NBTTag::List(filter), NBTTag::List(list)) => filter.iter().all(|filter| list.iter().any(|tag| matches_filter(filter, tag))),
+        _ => filter == tag,
+    }
+}
+
+/// Turns a possibly negative index into an index into a collection of `len` elements
+pub(crate) fn resolve_index(index: i32, len: usize) -> Option<usize> {
+    let index = if index < 0 { len as i64 + index as i64 } else { index as i64 };
+
+    (0..len as i64).contains(&index).then_some(index as usize)
+}
+
+fn array_len(tag: &NBTTag) -> Option<usize> {
+    match tag {
+        NBTTag::ByteArray(ba) => Some(ba.len()),
+        NBTTag::IntArray(ia) => Some(ia.len()),
+        NBTTag::LongArray(la) => Some(la.len()),
+        _ => None,
+    }
+}
+
+fn array_element(tag: &NBTTag, index: usize) -> Option<NBTTag> {
+    match tag {
+        NBTTag::ByteArray(ba) => Some(NBTTag::Byte(ba[index] as i8)),
+        NBTTag::IntArray(ia) => Some(NBTTag::Int(ia[index])),
+        NBTTag::LongArray(la) => Some(NBTTag::Long(la[index])),
+        _ => None,
+    }
+}
+
+/// Returns the tags that `node` points at inside of `tag`
+fn children<'a>(node: &NBTPathNode, tag: &'a NBTTag) -> Vec<Cow<'a, NBTTag>> {
+    match (node, tag) {
+        (NBTPathNode::RootFilter(filter), _) if matches_filter(filter, tag) => vec![Cow::Borrowed(tag)],
+        (NBTPathNode::Key(key), _) => tag.get(key.as_str()).map(Cow::Borrowed).into_iter().collect(),
+        (NBTPathNode::KeyFilter(key, filter), _) => tag.get(key.as_str()).filter(|tag| matches_filter(filter, tag)).map(Cow::Borrowed).into_iter().collect(),
+        (NBTPathNode::Index(index), NBTTag::List(l)) => resolve_index(*index, l.len()).map(|i| Cow::Borrowed(&l[i])).into_iter().collect(),
+        (NBTPathNode::Index(index), _) => array_len(tag).and_then(|len| resolve_index(*index, len)).and_then(|i| array_element(tag, i)).map(Cow::Owned).into_iter().collect(),
+        (NBTPathNode::All, NBTTag::List(l)) => l.iter().map(Cow::Borrowed).collect(),
+        (NBTPathNode::All, _) => (0..array_len(tag).unwrap_or(0)).filter_map(|i| array_element(tag, i)).map(Cow::Owned).collect(),
+        (NBTPathNode::ListFilter(filter), NBTTag::List(l)) => l.iter().filter(|tag| matches_filter(filter, tag)).map(Cow::Borrowed).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// The tag to create for a missing entry, so that `next` can be applied to it
+fn empty_parent(next: Option<&NBTPathNode>) -> NBTTag {
+    match next {
+        Some(NBTPathNode::Index(_) | NBTPathNode::All | NBTPathNode::ListFilter(_)) => NBTTag::List(Vec::new()),
+        _ => NBTTag::Compound(NBTCompound::new()),
+    }
+}
+
+/// Calls `f` on every tag that `nodes` lead to from `tag`, and adds up what it returns
+///
+/// If `create` is set, missing compound entries and list elements that a filter was looking for are created along the way. `after` is the node that will be applied to the tags passed to `f`, so that the right kind of tag can be created for it.
+fn walk_mut(tag: &mut NBTTag, nodes: &[NBTPathNode], after: Option<&NBTPathNode>, create: bool, f: &mut impl FnMut(&mut NBTTag) -> usize) -> usize {
+    let Some((node, rest)) = nodes.split_first() else {
+        return f(tag);
+    };
+
+    let next = rest.first().or(after);
+    let mut walk = |child: &mut NBTTag| walk_mut(child, rest, after, create, f);
+
+    match (node, tag) {
+        (NBTPathNode::RootFilter(filter), tag) if matches_filter(filter, tag) => walk(tag),
+        (NBTPathNode::Key(key), NBTTag::Compound(c)) => {
+            if create && !c.contains_key(key) {
+                c.insert(key.clone(), empty_parent(next));
+            }
+
+            c.get_mut(key).map_or(0, walk)
+        }
+        (NBTPathNode::KeyFilter(key, filter), NBTTag::Compound(c)) => {
+            if create && !c.contains_key(key) {
+                c.insert(key.clone(), filter.clone());
+            }
+
+            c.get_mut(key).filter(|child| matches_filter(filter, child)).map_or(0, walk)
+        }
+        (NBTPathNode::Index(index), NBTTag::List(l)) => resolve_index(*index, l.len()).map_or(0, |i| walk(&mut l[i])),
+        (NBTPathNode::All, NBTTag::List(l)) => {
+            if create && l.is_empty() {
+                l.push(empty_parent(next));
+            }
+
+            l.iter_mut().map(walk).sum()
+        }
+        (NBTPathNode::ListFilter(filter), NBTTag::List(l)) => {
+            if create && !l.iter().any(|tag| matches_filter(filter, tag)) && l.first().is_none_or(|tag| tag.id() == filter.id()) {
+                l.push(filter.clone());
+            }
+
+            l.iter_mut().filter(|tag| matches_filter(filter, tag)).map(walk).sum()
+        }
+        _ => 0,
+    }
+}
+
+/// Sets the children that `node` points at in `parent` to `value`, returning how many changed
+fn set_child(node: &NBTPathNode, parent: &mut NBTTag, value: &NBTTag) -> usize {
+    let replace = |existing: &mut NBTTag| {
+        if existing == value {
+            return 0;
+        }
+
+        *existing = value.clone();
+        1
+    };
+
+    match (node, parent) {
+        (NBTPathNode::Key(key), NBTTag::Compound(c)) => match c.get_mut(key) {
+            Some(existing) => replace(existing),
+            None => {
+                c.insert(key.clone(), value.clone());
+                1
+            }
+        },
+        (NBTPathNode::KeyFilter(key, filter), NBTTag::Compound(c)) => {
+            c.get_mut(key).filter(|existing| matches_filter(filter, existing)).map_or(0, replace)
+        }
+        (NBTPathNode::Index(index), NBTTag::List(l)) => match resolve_index(*index, l.len()) {
+            Some(i) if l.len() == 1 || l[0].id() == value.id() => replace(&mut l[i]),
+            _ => 0,
+        },
+        (NBTPathNode::All, NBTTag::List(l)) if l.is_empty() => {
+            l.push(value.clone());
+            1
+        }
+        (NBTPathNode::All, NBTTag::List(l)) => l.iter_mut().map(replace).sum(),
+        (NBTPathNode::ListFilter(filter), NBTTag::List(l)) if value.id() == filter.id() => {
+            l.iter_mut().filter(|tag| matches_filter(filter, tag)).map(replace).sum()
+        }
+        (NBTPathNode::Index(index), parent) => {
+            array_len(parent).and_then(|len| resolve_index(*index, len)).map_or(0, |i| set_array_element(parent, i, value))
+        }
+        (NBTPathNode::All, parent) => (0..array_len(parent).unwrap_or(0)).map(|i| set_array_element(parent, i, value)).sum(),
+        _ => 0,
+    }
+}
+
+/// Sets an element of a typed array if `value` is the array's element type, returning whether it changed
+fn set_array_element(array: &mut NBTTag, index: usize, value: &NBTTag) -> usize {
+    match (array, value) {
+        (NBTTag::ByteArray(ba), NBTTag::Byte(b)) if ba[index] != *b as u8 => ba[index] = *b as u8,
+        (NBTTag::IntArray(ia), NBTTag::Int(i)) if ia[index] != *i => ia[index] = *i,
+        (NBTTag::LongArray(la), NBTTag::Long(l)) if la[index] != *l => la[index] = *l,
+        _ => return 0,
+    }
+
+    1
+}
+
+/// Removes the children that `node` points at from `parent`, returning how many were removed
+fn remove_child(node: &NBTPathNode, parent: &mut NBTTag) -> usize {
+    let index = match node {
+        NBTPathNode::Index(index) => Some(*index),
+        _ => None,
+    };
+
+    match (node, parent) {
+        (NBTPathNode::Key(key), NBTTag::Compound(c)) => c.remove(key).map_or(0, |_| 1),
+        (NBTPathNode::KeyFilter(key, filter), NBTTag::Compound(c)) if c.get(key).is_some_and(|tag| matches_filter(filter, tag)) => {
+            c.remove(key);
+            1
+        }
+        (NBTPathNode::ListFilter(filter), NBTTag::List(l)) => retain_count(l, |tag| !matches_filter(filter, tag)),
+        (NBTPathNode::Index(_) | NBTPathNode::All, NBTTag::List(l)) => remove_elements(l, index),
+        (NBTPathNode::Index(_) | NBTPathNode::All, NBTTag::ByteArray(ba)) => remove_elements(ba, index),
+        (NBTPathNode::Index(_) | NBTPathNode::All, NBTTag::IntArray(ia)) => remove_elements(ia, index),
+        (NBTPathNode::Index(_) | NBTPathNode::All, NBTTag::LongArray(la)) => remove_elements(la, index),
+        _ => 0,
+    }
+}
+
+/// Removes one element, or all of them if `index` is `None`
+fn remove_elements<T>(elements: &mut Vec<T>, index: Option<i32>) -> usize {
+    let Some(index) = index else {
+        let len = elements.len();
+        elements.clear();
+        return len;
+    };
+
+    resolve_index(index, elements.len()).map_or(0, |i| {
+        elements.remove(i);
+        1
+    })
+}
+
+fn retain_count<T>(elements: &mut Vec<T>, keep: impl FnMut(&T) -> bool) -> usize {
+    let len = elements.len();
+    elements.retain(keep);
+
+    len - elements.len()
+}
@@ -0,0 +1,244 @@
+//! Schemas that describe the expected shape of a tag, for catching corrupted or hand-edited files when they are loaded
+//!
+//! Validating never stops at the first problem, it returns every violation along with its path, e.g. `Inventory[3].count`.
+
+use crate::nbt::tag::{path_within_key, NBTTag};
+use crate::nbt::NBT;
+use std::fmt::{Display, Formatter};
+use std::ops::RangeInclusive;
+
+/// The expected shape of a tag
+///
+/// Numbers must have exactly the given type and be inside the range, and `NBTSchema::int()` and friends accept the type's whole range.
+#[derive(Clone, PartialEq, Debug)]
+pub enum NBTSchema {
+    /// Accepts any tag
+    Any,
+    Byte(RangeInclusive<i8>),
+    Short(RangeInclusive<i16>),
+    Int(RangeInclusive<i32>),
+    Long(RangeInclusive<i64>),
+    /// NaN is only accepted if the range is unbounded on both sides
+    Float(RangeInclusive<f32>),
+    /// NaN is only accepted if the range is unbounded on both sides
+    Double(RangeInclusive<f64>),
+    ByteArray,
+    String,
+    /// A list where every element matches the schema, which is also accepted when empty
+    List(Box<NBTSchema>),
+    Compound(NBTCompoundSchema),
+    IntArray,
+    LongArray,
+}
+
+impl NBTSchema {
+    pub fn byte() -> Self {
+        Self::Byte(i8::MIN..=i8::MAX)
+    }
+
+    pub fn short() -> Self {
+        Self::Short(i16::MIN..=i16::MAX)
+    }
+
+    pub fn int() -> Self {
+        Self::Int(i32::MIN..=i32::MAX)
+    }
+
+    pub fn long() -> Self {
+        Self::Long(i64::MIN..=i64::MAX)
+    }
+
+    pub fn float() -> Self {
+        Self::Float(f32::NEG_INFINITY..=f32::INFINITY)
+    }
+
+    pub fn double() -> Self {
+        Self::Double(f64::NEG_INFINITY..=f64::INFINITY)
+    }
+
+    pub fn list(element: NBTSchema) -> Self {
+        Self::List(Box::new(element))
+    }
+
+    /// Checks `tag` against this schema, returning every violation, so an empty `Vec` means the tag is valid
+    pub fn validate(&self, tag: &NBTTag) -> Vec<NBTSchemaViolation> {
+        let mut violations = Vec::new();
+        self.check(tag, "", &mut violations);
+        violations
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Any => "any tag",
+            Self::Byte(_) => "Byte",
+            Self::Short(_) => "Short",
+            Self::Int(_) => "Int",
+            Self::Long(_) => "Long",
+            Self::Float(_) => "Float",
+            Self::Double(_) => "Double",
+            Self::ByteArray => "ByteArray",
+            Self::String => "String",
+            Self::List(_) => "List",
+            Self::Compound(_) => "Compound",
+            Self::IntArray => "IntArray",
+            Self::LongArray => "LongArray",
+        }
+    }
+
+    fn check(&self, tag: &NBTTag, path: &str, violations: &mut Vec<NBTSchemaViolation>) {
+        let in_range = match (self, tag) {
+            (Self::Any, _) | (Self::ByteArray, NBTTag::ByteArray(_)) | (Self::String, NBTTag::String(_)) | (Self::IntArray, NBTTag::IntArray(_)) | (Self::LongArray, NBTTag::LongArray(_)) => true,
+            (Self::Byte(range), NBTTag::Byte(b)) => range.contains(b),
+            (Self::Short(range), NBTTag::Short(s)) => range.contains(s),
+            (Self::Int(range), NBTTag::Int(i)) => range.contains(i),
+            (Self::Long(range), NBTTag::Long(l)) => range.contains(l),
+            (Self::Float(range), NBTTag::Float(f)) => range.contains(f) || (f.is_nan() && range.start().is_infinite() && range.end().is_infinite()),
+            (Self::Double(range), NBTTag::Double(d)) => range.contains(d) || (d.is_nan() && range.start().is_infinite() && range.end().is_infinite()),
+            (Self::List(element), NBTTag::List(tags)) => {
+                for (i, tag) in tags.iter().enumerate() {
+                    element.check(tag, &format!("{path}[{i}]"), violations);
+                }
+
+                true
+            }
+            (Self::Compound(schema), NBTTag::Compound(_)) => {
+                schema.check(tag, path, violations);
+                true
+            }
+            _ => {
+                violations.push(NBTSchemaViolation::new(NBTSchemaViolationKind::WrongType { expected: self.name(), found: tag.name() }, path));
+                return;
+            }
+        };
+
+        if !in_range {
+            violations.push(NBTSchemaViolation::new(NBTSchemaViolationKind::OutOfRange(tag.clone()), path));
+        }
+    }
+}
+
+/// The expected entries of a compound
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct NBTCompoundSchema {
+    entries: Vec<(String, NBTSchema, bool)>,
+    deny_unknown: bool,
+}
+
+impl NBTCompoundSchema {
+    /// Creates a schema for a compound that allows any entries
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an entry that must be in the compound
+    pub fn required(mut self, key: impl Into<String>, schema: NBTSchema) -> Self {
+        self.entries.push((key.into(), schema, true));
+        self
+    }
+
+    /// Adds an entry that is checked if it's in the compound
+    pub fn optional(mut self, key: impl Into<String>, schema: NBTSchema) -> Self {
+        self.entries.push((key.into(), schema, false));
+        self
+    }
+
+    /// Reports entries that weren't declared with `required` or `optional`
+    pub fn deny_unknown(mut self) -> Self {
+        self.deny_unknown = true;
+        self
+    }
+
+    fn check(&self, tag: &NBTTag, path: &str, violations: &mut Vec<NBTSchemaViolation>) {
+        let NBTTag::Compound(compound) = tag else {
+            return;
+        };
+
+        for (key, schema, required) in &self.entries {
+            match compound.get(key) {
+                Some(tag) => schema.check(tag, &child_path(path, key), violations),
+                None if *required => violations.push(NBTSchemaViolation::new(NBTSchemaViolationKind::Missing, &child_path(path, key))),
+                None => {}
+            }
+        }
+
+        if self.deny_unknown {
+            for key in compound.keys().filter(|key| !self.entries.iter().any(|(known, _, _)| known == key)) {
+                violations.push(NBTSchemaViolation::new(NBTSchemaViolationKind::Unknown, &child_path(path, key)));
+            }
+        }
+    }
+}
+
+impl From<NBTCompoundSchema> for NBTSchema {
+    fn from(schema: NBTCompoundSchema) -> Self {
+        Self::Compound(schema)
+    }
+}
+
+fn child_path(path: &str, key: &str) -> String {
+    match path {
+        "" => path_within_key(key, ""),
+        _ => format!("{path}.{}", path_within_key(key, "")),
+    }
+}
+
+/// The way that a tag didn't match its schema
+#[derive(Clone, PartialEq, Debug)]
+pub enum NBTSchemaViolationKind {
+    /// A required entry wasn't in its compound
+    Missing,
+    /// An entry wasn't declared in a compound schema that denies unknown entries
+    Unknown,
+    WrongType { expected: &'static str, found: &'static str },
+    /// A number was outside of its range
+    OutOfRange(NBTTag),
+}
+
+/// A single place where a tag didn't match its schema
+#[derive(Clone, PartialEq, Debug)]
+pub struct NBTSchemaViolation {
+    pub kind: NBTSchemaViolationKind,
+    pub path: String,
+}
+
+impl NBTSchemaViolation {
+    fn new(kind: NBTSchemaViolationKind, path: &str) -> Self {
+        Self { kind, path: path.to_string() }
+    }
+}
+
+impl Display for NBTSchemaViolationKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Missing => write!(f, "missing required tag"),
+            Self::Unknown => write!(f, "unknown tag"),
+            Self::WrongType { expected, found } => write!(f, "expected {expected} but found {found}"),
+            Self::OutOfRange(tag) => write!(f, "{} is out of range", tag.as_snbt()),
+        }
+    }
+}
+
+impl Display for NBTSchemaViolation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if !self.path.is_empty() {
+            write!(f, " (in '{}')", self.path)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl NBTTag {
+    /// Checks this tag against `schema`, see `NBTSchema::validate`
+    pub fn validate(&self, schema: &NBTSchema) -> Vec<NBTSchemaViolation> {
+        schema.validate(self)
+    }
+}
+
+impl NBT {
+    /// Checks the root tag against `schema`, see `NBTSchema::validate`
+    pub fn validate(&self, schema: &NBTSchema) -> Vec<NBTSchemaViolation> {
+        schema.validate(&self.root_tag)
+    }
+}
@@ -0,0 +1,48 @@
+pub(super) const BYTE_ARRAY_NAME: &str = "__nbt_byte_array";
+pub(super) const INT_ARRAY_NAME: &str = "__nbt_int_array";
+pub(super) const LONG_ARRAY_NAME: &str = "__nbt_long_array";
+
+/// Stores a `Vec<i8>` field as a `ByteArray` instead of a `List` of `Byte`s
+///
+/// Use it with `#[serde(with = "data::nbt::serde::byte_array")]`
+pub mod byte_array {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &[i8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(super::BYTE_ARRAY_NAME, value)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<i8>, D::Error> {
+        Vec::deserialize(deserializer)
+    }
+}
+
+/// Stores a `Vec<i32>` field as an `IntArray` instead of a `List` of `Int`s
+///
+/// Use it with `#[serde(with = "data::nbt::serde::int_array")]`
+pub mod int_array {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &[i32], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(super::INT_ARRAY_NAME, value)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<i32>, D::Error> {
+        Vec::deserialize(deserializer)
+    }
+}
+
+/// Stores a `Vec<i64>` field as a `LongArray` instead of a `List` of `Long`s
+///
+/// Use it with `#[serde(with = "data::nbt::serde::long_array")]`
+pub mod long_array {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &[i64], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(super::LONG_ARRAY_NAME, value)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<i64>, D::Error> {
+        Vec::deserialize(deserializer)
+    }
+}
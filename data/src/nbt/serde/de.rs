@@ -0,0 +1,264 @@
+use crate::nbt::compound;
+use crate::nbt::serde::NBTSerdeError;
+use crate::nbt::tag::NBTTag;
+use serde::de::value::{BorrowedStrDeserializer, SeqDeserializer};
+use serde::de::{DeserializeSeed, EnumAccess, Error, MapAccess, SeqAccess, Unexpected, VariantAccess, Visitor};
+use serde::forward_to_deserialize_any;
+
+/// A `serde::Deserializer` that reads a value out of a borrowed `NBTTag`
+pub struct Deserializer<'de> {
+    tag: &'de NBTTag,
+}
+
+impl<'de> Deserializer<'de> {
+    pub fn new(tag: &'de NBTTag) -> Self {
+        Self { tag }
+    }
+
+    fn unexpected(&self) -> Unexpected<'de> {
+        match self.tag {
+            NBTTag::Byte(b) => Unexpected::Signed(*b as i64),
+            NBTTag::Short(s) => Unexpected::Signed(*s as i64),
+            NBTTag::Int(i) => Unexpected::Signed(*i as i64),
+            NBTTag::Long(l) => Unexpected::Signed(*l),
+            NBTTag::Float(f) => Unexpected::Float(*f as f64),
+            NBTTag::Double(d) => Unexpected::Float(*d),
+            NBTTag::String(s) => Unexpected::Str(s),
+            NBTTag::ByteArray(_) | NBTTag::List(_) | NBTTag::IntArray(_) | NBTTag::LongArray(_) => Unexpected::Seq,
+            NBTTag::Compound(_) => Unexpected::Map,
+        }
+    }
+}
+
+impl<'de> serde::Deserializer<'de> for Deserializer<'de> {
+    type Error = NBTSerdeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, NBTSerdeError> {
+        match self.tag {
+            NBTTag::Byte(b) => visitor.visit_i8(*b),
+            NBTTag::Short(s) => visitor.visit_i16(*s),
+            NBTTag::Int(i) => visitor.visit_i32(*i),
+            NBTTag::Long(l) => visitor.visit_i64(*l),
+            NBTTag::Float(f) => visitor.visit_f32(*f),
+            NBTTag::Double(d) => visitor.visit_f64(*d),
+            NBTTag::String(s) => visitor.visit_borrowed_str(s),
+            NBTTag::ByteArray(ba) => visitor.visit_seq(ByteArrayAccess { iter: ba.iter() }),
+            NBTTag::List(l) => visitor.visit_seq(ListAccess { iter: l.iter() }),
+            NBTTag::Compound(c) => visitor.visit_map(CompoundAccess { iter: c.iter(), value: None }),
+            NBTTag::IntArray(ia) => visitor.visit_seq(SeqDeserializer::new(ia.iter().copied())),
+            NBTTag::LongArray(la) => visitor.visit_seq(SeqDeserializer::new(la.iter().copied())),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, NBTSerdeError> {
+        match self.tag {
+            NBTTag::Byte(b) => visitor.visit_bool(*b != 0),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, NBTSerdeError> {
+        match self.tag {
+            NBTTag::Byte(b) => visitor.visit_u8(*b as u8),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, NBTSerdeError> {
+        match self.tag {
+            NBTTag::Short(s) => visitor.visit_u16(*s as u16),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, NBTSerdeError> {
+        match self.tag {
+            NBTTag::Int(i) => visitor.visit_u32(*i as u32),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, NBTSerdeError> {
+        match self.tag {
+            NBTTag::Long(l) => visitor.visit_u64(*l as u64),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_i128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, NBTSerdeError> {
+        match self.tag {
+            NBTTag::IntArray(ia) if ia.len() == 4 => visitor.visit_i128(int_array_to_u128(ia) as i128),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_u128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, NBTSerdeError> {
+        match self.tag {
+            NBTTag::IntArray(ia) if ia.len() == 4 => visitor.visit_u128(int_array_to_u128(ia)),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, NBTSerdeError> {
+        match self.tag {
+            NBTTag::ByteArray(ba) => visitor.visit_borrowed_bytes(ba),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, NBTSerdeError> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, NBTSerdeError> {
+        // a missing compound entry is the only way to store None, so a tag that is present is always Some
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, NBTSerdeError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, NBTSerdeError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, NBTSerdeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, _name: &'static str, _variants: &'static [&'static str], visitor: V) -> Result<V::Value, NBTSerdeError> {
+        match self.tag {
+            NBTTag::String(s) => visitor.visit_enum(BorrowedStrDeserializer::new(s)),
+            NBTTag::Compound(c) if c.len() == 1 => {
+                let (variant, value) = c.get_index(0).expect("the compound has one entry");
+                visitor.visit_enum(VariantDeserializer { variant, value })
+            }
+            _ => Err(NBTSerdeError::invalid_type(self.unexpected(), &"a string or a compound with a single entry")),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        i8 i16 i32 i64 f32 f64 char str string seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+fn int_array_to_u128(ia: &[i32]) -> u128 {
+    ia.iter().fold(0, |out, i| (out << 32) | (*i as u32 as u128))
+}
+
+struct ListAccess<'de> {
+    iter: std::slice::Iter<'de, NBTTag>,
+}
+
+impl<'de> SeqAccess<'de> for ListAccess<'de> {
+    type Error = NBTSerdeError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, NBTSerdeError> {
+        self.iter.next().map(|tag| seed.deserialize(Deserializer::new(tag))).transpose()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+struct ByteArrayAccess<'de> {
+    iter: std::slice::Iter<'de, u8>,
+}
+
+impl<'de> SeqAccess<'de> for ByteArrayAccess<'de> {
+    type Error = NBTSerdeError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, NBTSerdeError> {
+        self.iter.next().map(|b| seed.deserialize(ByteDeserializer(*b))).transpose()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+/// An element of a `ByteArray`, which is an `i8` unless it is read as a `u8`, so that bytes from 0x80 up fit into a `Vec<u8>`
+struct ByteDeserializer(u8);
+
+impl<'de> serde::Deserializer<'de> for ByteDeserializer {
+    type Error = NBTSerdeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, NBTSerdeError> {
+        visitor.visit_i8(self.0 as i8)
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, NBTSerdeError> {
+        visitor.visit_u8(self.0)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf option unit unit_struct newtype_struct seq tuple tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+struct CompoundAccess<'de> {
+    iter: compound::Iter<'de, String, NBTTag>,
+    value: Option<&'de NBTTag>,
+}
+
+impl<'de> MapAccess<'de> for CompoundAccess<'de> {
+    type Error = NBTSerdeError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, NBTSerdeError> {
+        let Some((key, value)) = self.iter.next() else {
+            return Ok(None);
+        };
+
+        self.value = Some(value);
+        seed.deserialize(BorrowedStrDeserializer::new(key)).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, NBTSerdeError> {
+        let value = self.value.take().ok_or_else(|| NBTSerdeError::custom("next_value_seed was called before next_key_seed"))?;
+
+        seed.deserialize(Deserializer::new(value))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+/// An enum variant stored as a compound with a single entry, where the key is the variant name
+struct VariantDeserializer<'de> {
+    variant: &'de str,
+    value: &'de NBTTag,
+}
+
+impl<'de> EnumAccess<'de> for VariantDeserializer<'de> {
+    type Error = NBTSerdeError;
+    type Variant = Deserializer<'de>;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Deserializer<'de>), NBTSerdeError> {
+        let variant = seed.deserialize(BorrowedStrDeserializer::<NBTSerdeError>::new(self.variant))?;
+
+        Ok((variant, Deserializer::new(self.value)))
+    }
+}
+
+impl<'de> VariantAccess<'de> for Deserializer<'de> {
+    type Error = NBTSerdeError;
+
+    fn unit_variant(self) -> Result<(), NBTSerdeError> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, NBTSerdeError> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, NBTSerdeError> {
+        serde::Deserializer::deserialize_seq(self, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value, NBTSerdeError> {
+        serde::Deserializer::deserialize_map(self, visitor)
+    }
+}
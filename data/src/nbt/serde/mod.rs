@@ -0,0 +1,100 @@
+//! Conversion between `NBTTag`s and any type that implements `serde::Serialize` or `serde::Deserialize`
+//!
+//! Rust types are mapped to tags like this:
+//! - `bool`, `i8` and `u8` are `Byte`s, `i16` and `u16` are `Short`s, `i32` and `u32` are `Int`s, `i64` and `u64` are `Long`s. Unsigned numbers keep their bits, so `255u8` is stored as `Byte(-1)`.
+//! - `i128` and `u128` are `IntArray`s of 4 ints, which is how vanilla stores UUIDs
+//! - `f32` is a `Float` and `f64` is a `Double`
+//! - `String`, `&str` and `char` are `String`s, and unit enum variants are `String`s of the variant name
+//! - sequences and tuples are `List`s, unless the field uses [`byte_array`], [`int_array`] or [`long_array`]
+//! - structs and maps are `Compound`s, and `None` fields are left out of them
+//! - other enum variants are a `Compound` with a single entry named after the variant
+
+mod array;
+mod de;
+mod ser;
+
+use crate::nbt::tag::{NBTTag, NBTTagDeserializeError};
+use crate::nbt::NBT;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+pub use array::{byte_array, int_array, long_array};
+pub use de::Deserializer;
+pub use ser::Serializer;
+
+/// An error from converting between a Rust value and NBT
+#[derive(Clone, PartialEq, Debug)]
+pub enum NBTSerdeError {
+    /// A message from a `Serialize` or `Deserialize` impl
+    Custom(String),
+    /// The value can't be represented as NBT
+    UnsupportedType(&'static str),
+    /// A map key wasn't a string
+    KeyMustBeString,
+    /// A sequence had elements that would be different tag types
+    MixedList { expected: &'static str, found: &'static str },
+    /// `None` was serialized somewhere other than a struct field or map value
+    NoneValue,
+    /// The binary NBT couldn't be decoded
+    Decode(NBTTagDeserializeError),
+}
+
+impl Display for NBTSerdeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Custom(message) => write!(f, "{message}"),
+            Self::UnsupportedType(ty) => write!(f, "{ty} can't be stored as NBT"),
+            Self::KeyMustBeString => write!(f, "compound keys must be strings"),
+            Self::MixedList { expected, found } => write!(f, "can't insert {found} into a list of {expected}"),
+            Self::NoneValue => write!(f, "None can only be stored as a compound entry"),
+            Self::Decode(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl Error for NBTSerdeError {}
+
+impl serde::ser::Error for NBTSerdeError {
+    fn custom<T: Display>(msg: T) -> Self {
+        Self::Custom(msg.to_string())
+    }
+}
+
+impl serde::de::Error for NBTSerdeError {
+    fn custom<T: Display>(msg: T) -> Self {
+        Self::Custom(msg.to_string())
+    }
+}
+
+impl From<NBTTagDeserializeError> for NBTSerdeError {
+    fn from(value: NBTTagDeserializeError) -> Self {
+        Self::Decode(value)
+    }
+}
+
+/// Converts a value into an `NBTTag`
+pub fn to_tag<T: Serialize + ?Sized>(value: &T) -> Result<NBTTag, NBTSerdeError> {
+    value.serialize(Serializer)
+}
+
+/// Converts an `NBTTag` into a value. Strings can be borrowed from the tag.
+pub fn from_tag<'de, T: Deserialize<'de>>(tag: &'de NBTTag) -> Result<T, NBTSerdeError> {
+    T::deserialize(Deserializer::new(tag))
+}
+
+/// Converts a value into named, uncompressed binary NBT
+pub fn to_bytes<T: Serialize + ?Sized>(value: &T, root_name: impl Into<String>) -> Result<Vec<u8>, NBTSerdeError> {
+    let mut out = Vec::new();
+    NBT::new(root_name, to_tag(value)?).write_to(&mut out).map_err(|error| NBTSerdeError::Custom(error.to_string()))?;
+
+    Ok(out)
+}
+
+/// Reads a value from named binary NBT, decompressing it first if it is gzip or zlib compressed
+pub fn from_bytes<T: DeserializeOwned>(bytes: impl Into<Vec<u8>>) -> Result<T, NBTSerdeError> {
+    let nbt = NBT::from_bytes_disk(bytes)?;
+
+    from_tag(&nbt.root_tag)
+}
@@ -0,0 +1,365 @@
+use crate::nbt::compound::NBTCompound;
+use crate::nbt::serde::array::{BYTE_ARRAY_NAME, INT_ARRAY_NAME, LONG_ARRAY_NAME};
+use crate::nbt::serde::NBTSerdeError;
+use crate::nbt::tag::NBTTag;
+use serde::ser::{Impossible, SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant, SerializeTuple, SerializeTupleStruct, SerializeTupleVariant};
+use serde::Serialize;
+
+/// A `serde::Serializer` that turns a value into an `NBTTag`
+pub struct Serializer;
+
+impl serde::Serializer for Serializer {
+    type Ok = NBTTag;
+    type Error = NBTSerdeError;
+    type SerializeSeq = ListSerializer;
+    type SerializeTuple = ListSerializer;
+    type SerializeTupleStruct = ListSerializer;
+    type SerializeTupleVariant = VariantSerializer<ListSerializer>;
+    type SerializeMap = CompoundSerializer;
+    type SerializeStruct = CompoundSerializer;
+    type SerializeStructVariant = VariantSerializer<CompoundSerializer>;
+
+    fn serialize_bool(self, v: bool) -> Result<NBTTag, NBTSerdeError> {
+        Ok(NBTTag::Byte(v as i8))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<NBTTag, NBTSerdeError> {
+        Ok(NBTTag::Byte(v))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<NBTTag, NBTSerdeError> {
+        Ok(NBTTag::Short(v))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<NBTTag, NBTSerdeError> {
+        Ok(NBTTag::Int(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<NBTTag, NBTSerdeError> {
+        Ok(NBTTag::Long(v))
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<NBTTag, NBTSerdeError> {
+        self.serialize_u128(v as u128)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<NBTTag, NBTSerdeError> {
+        Ok(NBTTag::Byte(v as i8))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<NBTTag, NBTSerdeError> {
+        Ok(NBTTag::Short(v as i16))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<NBTTag, NBTSerdeError> {
+        Ok(NBTTag::Int(v as i32))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<NBTTag, NBTSerdeError> {
+        Ok(NBTTag::Long(v as i64))
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<NBTTag, NBTSerdeError> {
+        Ok(NBTTag::IntArray(vec![(v >> 96) as i32, (v >> 64) as i32, (v >> 32) as i32, v as i32]))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<NBTTag, NBTSerdeError> {
+        Ok(NBTTag::Float(v))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<NBTTag, NBTSerdeError> {
+        Ok(NBTTag::Double(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<NBTTag, NBTSerdeError> {
+        Ok(NBTTag::String(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<NBTTag, NBTSerdeError> {
+        Ok(NBTTag::String(v.to_string()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<NBTTag, NBTSerdeError> {
+        Ok(NBTTag::ByteArray(v.to_vec()))
+    }
+
+    fn serialize_none(self) -> Result<NBTTag, NBTSerdeError> {
+        Err(NBTSerdeError::NoneValue)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<NBTTag, NBTSerdeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<NBTTag, NBTSerdeError> {
+        Ok(NBTTag::Compound(NBTCompound::new()))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<NBTTag, NBTSerdeError> {
+        Ok(NBTTag::Compound(NBTCompound::new()))
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _variant_index: u32, variant: &'static str) -> Result<NBTTag, NBTSerdeError> {
+        Ok(NBTTag::String(variant.to_string()))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, name: &'static str, value: &T) -> Result<NBTTag, NBTSerdeError> {
+        let tag = value.serialize(self)?;
+
+        // the array helpers wrap their values in a newtype struct with a special name, so that the list can be turned into the right array
+        match (name, tag) {
+            (BYTE_ARRAY_NAME, NBTTag::List(list)) => list.into_iter().map(|tag| match tag {
+                NBTTag::Byte(b) => Ok(b as u8),
+                tag => Err(NBTSerdeError::MixedList { expected: "Byte", found: tag.name() }),
+            }).collect::<Result<_, _>>().map(NBTTag::ByteArray),
+            (INT_ARRAY_NAME, NBTTag::List(list)) => list.into_iter().map(|tag| match tag {
+                NBTTag::Int(i) => Ok(i),
+                tag => Err(NBTSerdeError::MixedList { expected: "Int", found: tag.name() }),
+            }).collect::<Result<_, _>>().map(NBTTag::IntArray),
+            (LONG_ARRAY_NAME, NBTTag::List(list)) => list.into_iter().map(|tag| match tag {
+                NBTTag::Long(l) => Ok(l),
+                tag => Err(NBTSerdeError::MixedList { expected: "Long", found: tag.name() }),
+            }).collect::<Result<_, _>>().map(NBTTag::LongArray),
+            (_, tag) => Ok(tag),
+        }
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self, _name: &'static str, _variant_index: u32, variant: &'static str, value: &T) -> Result<NBTTag, NBTSerdeError> {
+        let tag = value.serialize(self).map_err(|error| nested_none(error, "None in an enum variant"))?;
+
+        Ok(NBTTag::Compound(NBTCompound::from(vec![(variant.to_string(), tag)])))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<ListSerializer, NBTSerdeError> {
+        Ok(ListSerializer { list: Vec::with_capacity(len.unwrap_or_default()) })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<ListSerializer, NBTSerdeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<ListSerializer, NBTSerdeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, _variant_index: u32, variant: &'static str, len: usize) -> Result<VariantSerializer<ListSerializer>, NBTSerdeError> {
+        Ok(VariantSerializer { variant, inner: self.serialize_seq(Some(len))? })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<CompoundSerializer, NBTSerdeError> {
+        Ok(CompoundSerializer { entries: NBTCompound::with_capacity(len.unwrap_or_default()), key: None })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<CompoundSerializer, NBTSerdeError> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, _variant_index: u32, variant: &'static str, len: usize) -> Result<VariantSerializer<CompoundSerializer>, NBTSerdeError> {
+        Ok(VariantSerializer { variant, inner: self.serialize_map(Some(len))? })
+    }
+}
+
+/// `NoneValue` is only caught by the compound directly containing the `None`, so it has to be turned into a different error anywhere else that it could be passed up into a compound
+fn nested_none(error: NBTSerdeError, ty: &'static str) -> NBTSerdeError {
+    match error {
+        NBTSerdeError::NoneValue => NBTSerdeError::UnsupportedType(ty),
+        error => error,
+    }
+}
+
+pub struct ListSerializer {
+    list: Vec<NBTTag>,
+}
+
+impl SerializeSeq for ListSerializer {
+    type Ok = NBTTag;
+    type Error = NBTSerdeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), NBTSerdeError> {
+        let tag = value.serialize(Serializer).map_err(|error| nested_none(error, "None in a list"))?;
+        if let Some(first) = self.list.first() {
+            if first.id() != tag.id() {
+                return Err(NBTSerdeError::MixedList { expected: first.name(), found: tag.name() });
+            }
+        }
+
+        self.list.push(tag);
+        Ok(())
+    }
+
+    fn end(self) -> Result<NBTTag, NBTSerdeError> {
+        Ok(NBTTag::List(self.list))
+    }
+}
+
+impl SerializeTuple for ListSerializer {
+    type Ok = NBTTag;
+    type Error = NBTSerdeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), NBTSerdeError> {
+        SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<NBTTag, NBTSerdeError> {
+        SerializeSeq::end(self)
+    }
+}
+
+impl SerializeTupleStruct for ListSerializer {
+    type Ok = NBTTag;
+    type Error = NBTSerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), NBTSerdeError> {
+        SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<NBTTag, NBTSerdeError> {
+        SerializeSeq::end(self)
+    }
+}
+
+pub struct CompoundSerializer {
+    entries: NBTCompound,
+    key: Option<String>,
+}
+
+impl CompoundSerializer {
+    fn insert<T: Serialize + ?Sized>(&mut self, key: String, value: &T) -> Result<(), NBTSerdeError> {
+        match value.serialize(Serializer) {
+            Ok(tag) => {
+                self.entries.insert(key, tag);
+            }
+            // None values are left out of the compound completely
+            Err(NBTSerdeError::NoneValue) => {}
+            Err(error) => return Err(error),
+        }
+
+        Ok(())
+    }
+}
+
+impl SerializeMap for CompoundSerializer {
+    type Ok = NBTTag;
+    type Error = NBTSerdeError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), NBTSerdeError> {
+        self.key = Some(key.serialize(KeySerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), NBTSerdeError> {
+        let key = self.key.take().ok_or_else(|| NBTSerdeError::Custom("serialize_value was called before serialize_key".to_string()))?;
+        self.insert(key, value)
+    }
+
+    fn end(self) -> Result<NBTTag, NBTSerdeError> {
+        Ok(NBTTag::Compound(self.entries))
+    }
+}
+
+impl SerializeStruct for CompoundSerializer {
+    type Ok = NBTTag;
+    type Error = NBTSerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), NBTSerdeError> {
+        self.insert(key.to_string(), value)
+    }
+
+    fn end(self) -> Result<NBTTag, NBTSerdeError> {
+        SerializeMap::end(self)
+    }
+}
+
+/// Wraps the serializer of an enum variant's data, putting the result in a compound under the variant's name
+pub struct VariantSerializer<S> {
+    variant: &'static str,
+    inner: S,
+}
+
+impl<S> VariantSerializer<S> {
+    fn wrap(variant: &'static str, tag: NBTTag) -> NBTTag {
+        NBTTag::Compound(NBTCompound::from(vec![(variant.to_string(), tag)]))
+    }
+}
+
+impl SerializeTupleVariant for VariantSerializer<ListSerializer> {
+    type Ok = NBTTag;
+    type Error = NBTSerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), NBTSerdeError> {
+        SerializeSeq::serialize_element(&mut self.inner, value)
+    }
+
+    fn end(self) -> Result<NBTTag, NBTSerdeError> {
+        Ok(Self::wrap(self.variant, SerializeSeq::end(self.inner)?))
+    }
+}
+
+impl SerializeStructVariant for VariantSerializer<CompoundSerializer> {
+    type Ok = NBTTag;
+    type Error = NBTSerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), NBTSerdeError> {
+        SerializeStruct::serialize_field(&mut self.inner, key, value)
+    }
+
+    fn end(self) -> Result<NBTTag, NBTSerdeError> {
+        Ok(Self::wrap(self.variant, SerializeMap::end(self.inner)?))
+    }
+}
+
+/// Only allows strings and chars to be used as compound keys
+struct KeySerializer;
+
+impl serde::Serializer for KeySerializer {
+    type Ok = String;
+    type Error = NBTSerdeError;
+    type SerializeSeq = Impossible<String, NBTSerdeError>;
+    type SerializeTuple = Impossible<String, NBTSerdeError>;
+    type SerializeTupleStruct = Impossible<String, NBTSerdeError>;
+    type SerializeTupleVariant = Impossible<String, NBTSerdeError>;
+    type SerializeMap = Impossible<String, NBTSerdeError>;
+    type SerializeStruct = Impossible<String, NBTSerdeError>;
+    type SerializeStructVariant = Impossible<String, NBTSerdeError>;
+
+    fn serialize_str(self, v: &str) -> Result<String, NBTSerdeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_char(self, v: char) -> Result<String, NBTSerdeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _variant_index: u32, variant: &'static str) -> Result<String, NBTSerdeError> {
+        Ok(variant.to_string())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T) -> Result<String, NBTSerdeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_bool(self, _v: bool) -> Result<String, NBTSerdeError> { Err(NBTSerdeError::KeyMustBeString) }
+    fn serialize_i8(self, _v: i8) -> Result<String, NBTSerdeError> { Err(NBTSerdeError::KeyMustBeString) }
+    fn serialize_i16(self, _v: i16) -> Result<String, NBTSerdeError> { Err(NBTSerdeError::KeyMustBeString) }
+    fn serialize_i32(self, _v: i32) -> Result<String, NBTSerdeError> { Err(NBTSerdeError::KeyMustBeString) }
+    fn serialize_i64(self, _v: i64) -> Result<String, NBTSerdeError> { Err(NBTSerdeError::KeyMustBeString) }
+    fn serialize_u8(self, _v: u8) -> Result<String, NBTSerdeError> { Err(NBTSerdeError::KeyMustBeString) }
+    fn serialize_u16(self, _v: u16) -> Result<String, NBTSerdeError> { Err(NBTSerdeError::KeyMustBeString) }
+    fn serialize_u32(self, _v: u32) -> Result<String, NBTSerdeError> { Err(NBTSerdeError::KeyMustBeString) }
+    fn serialize_u64(self, _v: u64) -> Result<String, NBTSerdeError> { Err(NBTSerdeError::KeyMustBeString) }
+    fn serialize_f32(self, _v: f32) -> Result<String, NBTSerdeError> { Err(NBTSerdeError::KeyMustBeString) }
+    fn serialize_f64(self, _v: f64) -> Result<String, NBTSerdeError> { Err(NBTSerdeError::KeyMustBeString) }
+    fn serialize_bytes(self, _v: &[u8]) -> Result<String, NBTSerdeError> { Err(NBTSerdeError::KeyMustBeString) }
+    fn serialize_none(self) -> Result<String, NBTSerdeError> { Err(NBTSerdeError::KeyMustBeString) }
+    fn serialize_some<T: Serialize + ?Sized>(self, _value: &T) -> Result<String, NBTSerdeError> { Err(NBTSerdeError::KeyMustBeString) }
+    fn serialize_unit(self) -> Result<String, NBTSerdeError> { Err(NBTSerdeError::KeyMustBeString) }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<String, NBTSerdeError> { Err(NBTSerdeError::KeyMustBeString) }
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self, _name: &'static str, _variant_index: u32, _variant: &'static str, _value: &T) -> Result<String, NBTSerdeError> { Err(NBTSerdeError::KeyMustBeString) }
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, NBTSerdeError> { Err(NBTSerdeError::KeyMustBeString) }
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, NBTSerdeError> { Err(NBTSerdeError::KeyMustBeString) }
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeTupleStruct, NBTSerdeError> { Err(NBTSerdeError::KeyMustBeString) }
+    fn serialize_tuple_variant(self, _name: &'static str, _variant_index: u32, _variant: &'static str, _len: usize) -> Result<Self::SerializeTupleVariant, NBTSerdeError> { Err(NBTSerdeError::KeyMustBeString) }
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, NBTSerdeError> { Err(NBTSerdeError::KeyMustBeString) }
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct, NBTSerdeError> { Err(NBTSerdeError::KeyMustBeString) }
+    fn serialize_struct_variant(self, _name: &'static str, _variant_index: u32, _variant: &'static str, _len: usize) -> Result<Self::SerializeStructVariant, NBTSerdeError> { Err(NBTSerdeError::KeyMustBeString) }
+}
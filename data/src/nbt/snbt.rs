@@ -0,0 +1,517 @@
+use crate::nbt::compound::NBTCompound;
+use crate::nbt::tag::{NBTTag, MAX_DEPTH};
+use crate::nbt::NBT;
+use std::error::Error;
+use std::fmt::{Display, Formatter, Write};
+
+/// The reason that parsing an SNBT string failed
+#[derive(Clone, PartialEq, Debug)]
+pub enum SNBTParseErrorKind {
+    /// The input ended in the middle of a tag
+    UnexpectedEnd,
+    /// A character was found where a different one was expected
+    Expected { expected: &'static str, found: char },
+    /// A compound key or value was empty
+    EmptyValue,
+    /// A `\` in a quoted string was followed by something that isn't an escape
+    InvalidEscape(char),
+    /// A typed array started with something other than `B`, `I` or `L`
+    InvalidArrayType(char),
+    /// An element of a list or typed array didn't have the type of the rest of the elements
+    MixedTypes { expected: &'static str, found: &'static str },
+    /// Lists and compounds were nested deeper than `MAX_DEPTH`
+    DepthLimitExceeded,
+    /// There was more data after the end of the tag
+    TrailingData,
+}
+
+/// An error from parsing SNBT, along with the character offset in the input where it happened
+#[derive(Clone, PartialEq, Debug)]
+pub struct SNBTParseError {
+    pub kind: SNBTParseErrorKind,
+    pub position: usize,
+}
+
+impl Display for SNBTParseErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnexpectedEnd => write!(f, "unexpected end of input"),
+            Self::Expected { expected, found } => write!(f, "expected {expected} but found '{found}'"),
+            Self::EmptyValue => write!(f, "expected a value"),
+            Self::InvalidEscape(c) => write!(f, "invalid escape sequence '\\{c}'"),
+            Self::InvalidArrayType(c) => write!(f, "invalid array type '{c}', expected 'B', 'I' or 'L'"),
+            Self::MixedTypes { expected, found } => write!(f, "can't insert {found} into a list of {expected}"),
+            Self::DepthLimitExceeded => write!(f, "tags are nested deeper than the limit of {MAX_DEPTH}"),
+            Self::TrailingData => write!(f, "unexpected data after the end of the tag"),
+        }
+    }
+}
+
+impl Display for SNBTParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at position {}", self.kind, self.position)
+    }
+}
+
+impl Error for SNBTParseError {}
+
+impl NBTTag {
+    /// Writes this tag as SNBT on a single line, e.g. `{name:"Bananrama",count:3b}`
+    pub fn as_snbt(&self) -> String {
+        let mut out = String::new();
+        write_tag(&mut out, self, None);
+        out
+    }
+
+    /// Writes this tag as SNBT with every list and compound entry on its own line, indented by 4 spaces
+    pub fn as_snbt_pretty(&self) -> String {
+        let mut out = String::new();
+        write_tag(&mut out, self, Some(0));
+        out
+    }
+
+    /// Parses a tag from SNBT
+    ///
+    /// Numbers without a suffix are read as `Int`s, or as `Double`s if they have a decimal point or exponent. `true` and `false` are read as `Byte`s. Any unquoted value that isn't a number is read as a `String`, just like vanilla does.
+    pub fn from_snbt(snbt: &str) -> Result<NBTTag, SNBTParseError> {
+        let chars: Vec<char> = snbt.chars().collect();
+        let mut parser = Parser { chars: &chars, cursor: 0 };
+
+        let tag = parser.parse_tag(0)?;
+        parser.skip_whitespace();
+        if parser.cursor < parser.chars.len() {
+            return Err(parser.error(SNBTParseErrorKind::TrailingData));
+        }
+
+        Ok(tag)
+    }
+}
+
+impl NBT {
+    /// Writes the root tag of this NBT as single line SNBT. SNBT has no way to store the root name, so it is left out.
+    pub fn as_snbt(&self) -> String {
+        self.root_tag.as_snbt()
+    }
+
+    /// Writes the root tag of this NBT as indented, multi-line SNBT. SNBT has no way to store the root name, so it is left out.
+    pub fn as_snbt_pretty(&self) -> String {
+        self.root_tag.as_snbt_pretty()
+    }
+
+    /// Parses the root tag from SNBT and gives it the passed name
+    pub fn from_snbt(name: impl Into<String>, snbt: &str) -> Result<Self, SNBTParseError> {
+        Ok(Self::new(name, NBTTag::from_snbt(snbt)?))
+    }
+}
+
+const INDENT: &str = "    ";
+
+/// Writes a tag to `out`. `indent` is the current indentation level when pretty printing, or `None` for compact output.
+fn write_tag(out: &mut String, tag: &NBTTag, indent: Option<usize>) {
+    let separator = if indent.is_some() { ", " } else { "," };
+
+    match tag {
+        NBTTag::Byte(b) => { let _ = write!(out, "{b}b"); }
+        NBTTag::Short(s) => { let _ = write!(out, "{s}s"); }
+        NBTTag::Int(i) => { let _ = write!(out, "{i}"); }
+        NBTTag::Long(l) => { let _ = write!(out, "{l}L"); }
+        NBTTag::Float(f) => write_decimal(out, *f as f64, 'f'),
+        NBTTag::Double(d) => write_decimal(out, *d, 'd'),
+        NBTTag::String(s) => write_quoted(out, s),
+        NBTTag::ByteArray(ba) => write_array(out, 'B', ba.iter().map(|b| format!("{}b", *b as i8)), separator, indent),
+        NBTTag::IntArray(ia) => write_array(out, 'I', ia.iter().map(|i| i.to_string()), separator, indent),
+        NBTTag::LongArray(la) => write_array(out, 'L', la.iter().map(|l| format!("{l}L")), separator, indent),
+        NBTTag::List(l) => {
+            out.push('[');
+            for (i, tag) in l.iter().enumerate() {
+                if i != 0 {
+                    out.push(',');
+                }
+                write_newline(out, indent.map(|indent| indent + 1));
+                write_tag(out, tag, indent.map(|indent| indent + 1));
+            }
+            if !l.is_empty() {
+                write_newline(out, indent);
+            }
+            out.push(']');
+        }
+        NBTTag::Compound(c) => {
+            out.push('{');
+            for (i, (name, tag)) in c.iter().enumerate() {
+                if i != 0 {
+                    out.push(',');
+                }
+                write_newline(out, indent.map(|indent| indent + 1));
+                write_key(out, name);
+                out.push_str(if indent.is_some() { ": " } else { ":" });
+                write_tag(out, tag, indent.map(|indent| indent + 1));
+            }
+            if !c.is_empty() {
+                write_newline(out, indent);
+            }
+            out.push('}');
+        }
+    }
+}
+
+fn write_array(out: &mut String, ty: char, values: impl Iterator<Item = String>, separator: &str, indent: Option<usize>) {
+    out.push('[');
+    out.push(ty);
+    out.push(';');
+    for (i, value) in values.enumerate() {
+        out.push_str(if i != 0 { separator } else if indent.is_some() { " " } else { "" });
+        out.push_str(&value);
+    }
+    out.push(']');
+}
+
+fn write_newline(out: &mut String, indent: Option<usize>) {
+    if let Some(indent) = indent {
+        out.push('\n');
+        for _ in 0..indent {
+            out.push_str(INDENT);
+        }
+    }
+}
+
+fn write_key(out: &mut String, key: &str) {
+    if !key.is_empty() && key.chars().all(is_unquoted_char) {
+        out.push_str(key);
+    } else {
+        write_quoted(out, key);
+    }
+}
+
+/// Writes a float or double with its suffix. NaN and the infinities are written the way Java prints them, as `NaNf`, `Infinityf` and `-Infinityf`, which the parser reads back as numbers.
+fn write_decimal(out: &mut String, value: f64, suffix: char) {
+    let _ = match value {
+        value if value.is_nan() => write!(out, "NaN{suffix}"),
+        f64::INFINITY => write!(out, "Infinity{suffix}"),
+        f64::NEG_INFINITY => write!(out, "-Infinity{suffix}"),
+        value if suffix == 'f' => write!(out, "{:?}{suffix}", value as f32),
+        value => write!(out, "{value:?}{suffix}"),
+    };
+}
+
+/// Writes a quoted and escaped string, using single quotes if the string contains a double quote but no single quotes
+pub(crate) fn write_quoted(out: &mut String, s: &str) {
+    let quote = if s.contains('"') && !s.contains('\'') { '\'' } else { '"' };
+
+    out.push(quote);
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c == quote => {
+                out.push('\\');
+                out.push(c);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push(quote);
+}
+
+fn is_unquoted_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '+')
+}
+
+/// Parses a compound starting at `cursor`, for syntaxes that embed SNBT like NBT paths. Returns the compound and the position after it.
+pub(crate) fn parse_compound_at(chars: &[char], cursor: usize) -> Result<(NBTTag, usize), SNBTParseError> {
+    let mut parser = Parser { chars, cursor };
+    let tag = parser.parse_compound(1)?;
+
+    Ok((tag, parser.cursor))
+}
+
+/// Parses a quoted string starting at `cursor`, returning the string and the position after the closing quote
+pub(crate) fn parse_quoted_at(chars: &[char], cursor: usize) -> Result<(String, usize), SNBTParseError> {
+    let mut parser = Parser { chars, cursor };
+    let s = parser.parse_quoted(chars[cursor])?;
+
+    Ok((s, parser.cursor))
+}
+
+struct Parser<'a> {
+    chars: &'a [char],
+    cursor: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, kind: SNBTParseErrorKind) -> SNBTParseError {
+        SNBTParseError { kind, position: self.cursor }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.get(self.cursor).is_some_and(|c| c.is_whitespace()) {
+            self.cursor += 1;
+        }
+    }
+
+    /// Skips whitespace and returns the next character without consuming it
+    fn peek(&mut self) -> Result<char, SNBTParseError> {
+        self.skip_whitespace();
+        self.chars.get(self.cursor).copied().ok_or_else(|| self.error(SNBTParseErrorKind::UnexpectedEnd))
+    }
+
+    fn expect(&mut self, expected: char, description: &'static str) -> Result<(), SNBTParseError> {
+        let found = self.peek()?;
+        if found != expected {
+            return Err(self.error(SNBTParseErrorKind::Expected { expected: description, found }));
+        }
+
+        self.cursor += 1;
+        Ok(())
+    }
+
+    /// Consumes a `,` if there is one, returning whether there was
+    fn separator(&mut self) -> Result<bool, SNBTParseError> {
+        if self.peek()? == ',' {
+            self.cursor += 1;
+            return Ok(true);
+        }
+
+        Ok(false)
+    }
+
+    fn parse_tag(&mut self, depth: usize) -> Result<NBTTag, SNBTParseError> {
+        match self.peek()? {
+            '{' => self.parse_compound(depth + 1),
+            '[' => self.parse_list(depth + 1),
+            _ => self.parse_value(),
+        }
+    }
+
+    fn check_depth(&self, depth: usize) -> Result<(), SNBTParseError> {
+        if depth > MAX_DEPTH {
+            return Err(self.error(SNBTParseErrorKind::DepthLimitExceeded));
+        }
+
+        Ok(())
+    }
+
+    fn parse_compound(&mut self, depth: usize) -> Result<NBTTag, SNBTParseError> {
+        self.check_depth(depth)?;
+        self.expect('{', "'{'")?;
+
+        let mut entries = NBTCompound::new();
+        while self.peek()? != '}' {
+            let key_start = self.cursor;
+            let key = self.parse_string()?;
+            if key.is_empty() && self.chars[key_start] != '"' && self.chars[key_start] != '\'' {
+                return Err(self.error(SNBTParseErrorKind::EmptyValue));
+            }
+
+            self.expect(':', "':'")?;
+            let tag = self.parse_tag(depth)?;
+            entries.insert(key, tag);
+
+            if !self.separator()? {
+                break;
+            }
+        }
+
+        self.expect('}', "',' or '}'")?;
+        Ok(NBTTag::Compound(entries))
+    }
+
+    fn parse_list(&mut self, depth: usize) -> Result<NBTTag, SNBTParseError> {
+        self.check_depth(depth)?;
+        self.expect('[', "'['")?;
+
+        // a typed array looks like `[B;`, anything else is a list
+        if let (Some(&ty), Some(';')) = (self.chars.get(self.cursor), self.chars.get(self.cursor + 1)) {
+            if ty.is_ascii_alphabetic() {
+                self.cursor += 2;
+                return self.parse_array(ty);
+            }
+        }
+
+        let mut list: Vec<NBTTag> = Vec::new();
+        while self.peek()? != ']' {
+            let start = self.cursor;
+            let tag = self.parse_tag(depth)?;
+            if let Some(first) = list.first() {
+                if first.id() != tag.id() {
+                    return Err(SNBTParseError { kind: SNBTParseErrorKind::MixedTypes { expected: first.name(), found: tag.name() }, position: start });
+                }
+            }
+
+            list.push(tag);
+
+            if !self.separator()? {
+                break;
+            }
+        }
+
+        self.expect(']', "',' or ']'")?;
+        Ok(NBTTag::List(list))
+    }
+
+    fn parse_array(&mut self, ty: char) -> Result<NBTTag, SNBTParseError> {
+        let (expected, mut out) = match ty {
+            'B' => ("Byte", NBTTag::ByteArray(Vec::new())),
+            'I' => ("Int", NBTTag::IntArray(Vec::new())),
+            'L' => ("Long", NBTTag::LongArray(Vec::new())),
+            _ => return Err(SNBTParseError { kind: SNBTParseErrorKind::InvalidArrayType(ty), position: self.cursor - 2 }),
+        };
+
+        while self.peek()? != ']' {
+            let start = self.cursor;
+            let tag = self.parse_value()?;
+            match (&mut out, tag) {
+                (NBTTag::ByteArray(ba), NBTTag::Byte(b)) => ba.push(b as u8),
+                (NBTTag::IntArray(ia), NBTTag::Int(i)) => ia.push(i),
+                (NBTTag::LongArray(la), NBTTag::Long(l)) => la.push(l),
+                (_, tag) => return Err(SNBTParseError { kind: SNBTParseErrorKind::MixedTypes { expected, found: tag.name() }, position: start }),
+            }
+
+            if !self.separator()? {
+                break;
+            }
+        }
+
+        self.expect(']', "',' or ']'")?;
+        Ok(out)
+    }
+
+    fn parse_value(&mut self) -> Result<NBTTag, SNBTParseError> {
+        let c = self.peek()?;
+        if c == '"' || c == '\'' {
+            return Ok(NBTTag::String(self.parse_quoted(c)?));
+        }
+
+        let token = self.parse_unquoted();
+        if token.is_empty() {
+            return Err(self.error(SNBTParseErrorKind::EmptyValue));
+        }
+
+        Ok(parse_unquoted_value(token))
+    }
+
+    /// Parses a quoted or unquoted string, used for compound keys
+    fn parse_string(&mut self) -> Result<String, SNBTParseError> {
+        let c = self.peek()?;
+        if c == '"' || c == '\'' {
+            return self.parse_quoted(c);
+        }
+
+        Ok(self.parse_unquoted())
+    }
+
+    fn parse_unquoted(&mut self) -> String {
+        let start = self.cursor;
+        while self.chars.get(self.cursor).copied().is_some_and(is_unquoted_char) {
+            self.cursor += 1;
+        }
+
+        self.chars[start..self.cursor].iter().collect()
+    }
+
+    fn parse_quoted(&mut self, quote: char) -> Result<String, SNBTParseError> {
+        self.cursor += 1;
+
+        let mut out = String::new();
+        loop {
+            let Some(&c) = self.chars.get(self.cursor) else {
+                return Err(self.error(SNBTParseErrorKind::UnexpectedEnd));
+            };
+            self.cursor += 1;
+
+            match c {
+                c if c == quote => return Ok(out),
+                '\\' => {
+                    let Some(&escaped) = self.chars.get(self.cursor) else {
+                        return Err(self.error(SNBTParseErrorKind::UnexpectedEnd));
+                    };
+
+                    out.push(match escaped {
+                        '\\' | '"' | '\'' => escaped,
+                        'n' => '\n',
+                        'r' => '\r',
+                        't' => '\t',
+                        'b' => '\u{8}',
+                        'f' => '\u{c}',
+                        's' => ' ',
+                        'u' => {
+                            let hex = self.chars.get(self.cursor + 1..self.cursor + 5).map(|hex| hex.iter().collect::<String>());
+                            let Some(c) = hex.and_then(|hex| u32::from_str_radix(&hex, 16).ok()).and_then(char::from_u32) else {
+                                return Err(self.error(SNBTParseErrorKind::InvalidEscape('u')));
+                            };
+
+                            self.cursor += 4;
+                            c
+                        }
+                        _ => return Err(self.error(SNBTParseErrorKind::InvalidEscape(escaped))),
+                    });
+                    self.cursor += 1;
+                }
+                c => out.push(c),
+            }
+        }
+    }
+}
+
+/// Turns an unquoted token into a number or boolean if it looks like one, otherwise it is a string
+fn parse_unquoted_value(token: String) -> NBTTag {
+    match token.as_str() {
+        "true" => return NBTTag::Byte(1),
+        "false" => return NBTTag::Byte(0),
+        _ => {}
+    }
+
+    let (number, suffix) = match token.char_indices().last() {
+        Some((i, c)) if c.is_ascii_alphabetic() => (&token[..i], Some(c.to_ascii_lowercase())),
+        _ => (token.as_str(), None),
+    };
+
+    let parsed = match suffix {
+        Some('b') if is_integer(number) => number.parse().ok().map(NBTTag::Byte),
+        Some('s') if is_integer(number) => number.parse().ok().map(NBTTag::Short),
+        Some('l') if is_integer(number) => number.parse().ok().map(NBTTag::Long),
+        Some('f') if is_decimal(number, true) || is_non_finite(number) => number.parse().ok().map(NBTTag::Float),
+        Some('d') if is_decimal(number, true) || is_non_finite(number) => number.parse().ok().map(NBTTag::Double),
+        None if is_integer(number) => number.parse().ok().map(NBTTag::Int),
+        None if is_decimal(number, false) => number.parse().ok().map(NBTTag::Double),
+        _ => None,
+    };
+
+    parsed.unwrap_or(NBTTag::String(token))
+}
+
+/// Matches `[-+]?(0|[1-9][0-9]*)`
+fn is_integer(s: &str) -> bool {
+    let digits = s.strip_prefix(['-', '+']).unwrap_or(s);
+
+    digits == "0" || (!digits.is_empty() && !digits.starts_with('0') && digits.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Matches the `NaN`, `Infinity` and `-Infinity` that non-finite floats and doubles are written as
+fn is_non_finite(s: &str) -> bool {
+    matches!(s, "NaN" | "Infinity" | "+Infinity" | "-Infinity")
+}
+
+/// Matches `[-+]?([0-9]+[.]?|[0-9]*[.][0-9]+)(e[-+]?[0-9]+)?`. When the number has no suffix it also needs a `.` or exponent so that it isn't an int.
+fn is_decimal(s: &str, suffixed: bool) -> bool {
+    let s = s.strip_prefix(['-', '+']).unwrap_or(s);
+    let (mantissa, exponent) = match s.find(['e', 'E']) {
+        Some(i) => (&s[..i], Some(&s[i + 1..])),
+        None => (s, None),
+    };
+
+    let (whole, fraction) = match mantissa.split_once('.') {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (mantissa, None),
+    };
+
+    let all_digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+    let valid_mantissa = all_digits(whole) && fraction.is_none_or(all_digits) && (!whole.is_empty() || fraction.is_some_and(|f| !f.is_empty()));
+    let valid_exponent = exponent.is_none_or(|e| {
+        let e = e.strip_prefix(['-', '+']).unwrap_or(e);
+        !e.is_empty() && all_digits(e)
+    });
+
+    valid_mantissa && valid_exponent && (suffixed || fraction.is_some() || exponent.is_some())
+}
@@ -1,5 +1,13 @@
+use crate::nbt::codec::{JavaCodec, NBTCodec};
+use crate::nbt::compound::NBTCompound;
+use crate::nbt::mutf8;
+use std::borrow::Cow;
+use std::error::Error;
 use std::fmt::{Display, Formatter};
+use std::io;
+use std::io::Write;
 use crate::queue::Queue;
+use num_traits::FromBytes;
 use std::ops::{Index, IndexMut};
 
 #[derive(Clone, PartialEq, Debug)]
@@ -13,16 +21,189 @@ pub enum NBTTag {
     ByteArray(Vec<u8>),
     String(String),
     List(Vec<NBTTag>),
-    Compound(Vec<(String, NBTTag)>),
+    Compound(NBTCompound),
     IntArray(Vec<i32>),
     LongArray(Vec<i64>),
 }
 
-pub enum NBTTagDeserializeError {
-    MissingID,
-    MissingName,
-    MissingLength,
-    MissingData,
+/// The reason that decoding an NBT tag failed
+#[derive(Clone, PartialEq, Debug)]
+pub enum NBTTagDeserializeErrorKind {
+    /// A tag id that isn't part of the NBT format was read
+    UnknownTagId(u8),
+    /// The data ended before the tag was complete
+    UnexpectedEnd { needed: usize, remaining: usize },
+    /// A string or tag name wasn't valid Modified UTF-8
+    InvalidUtf8,
+    /// An array or list had a length prefix below zero
+    NegativeLength(i32),
+    /// Lists and compounds were nested deeper than the given limit
+    DepthLimitExceeded(usize),
+    /// An array or list was longer than `NBTDecodeOptions` allows
+    LengthLimitExceeded { len: usize, max: usize },
+    /// The decoded tags would take up more memory than `NBTDecodeOptions` allows
+    SizeLimitExceeded(usize),
+    /// A varint was longer than the type it encodes allows
+    InvalidVarInt,
+    /// The compressed data could not be decompressed
+    Decompression(io::ErrorKind),
+}
+
+/// An error from decoding an NBT tag, along with where in the data it happened
+///
+/// `offset` is the byte offset into the (decompressed) data where the error was found, and `path` is the path of the tag that was being read, e.g. `Level.sections[3].Palette`. The path is empty if the error happened in the root tag.
+#[derive(Clone, PartialEq, Debug)]
+pub struct NBTTagDeserializeError {
+    pub kind: NBTTagDeserializeErrorKind,
+    pub offset: usize,
+    pub path: String,
+}
+
+impl NBTTagDeserializeError {
+    pub fn new(kind: NBTTagDeserializeErrorKind, offset: usize) -> Self {
+        Self {
+            kind,
+            offset,
+            path: String::new(),
+        }
+    }
+
+    /// Prefixes the path with a compound key, used as the error is passed up out of a compound
+    pub fn within_key(mut self, key: &str) -> Self {
+        self.path = path_within_key(key, &self.path);
+        self
+    }
+
+    /// Prefixes the path with a list index, used as the error is passed up out of a list
+    pub fn within_index(mut self, index: usize) -> Self {
+        self.path = path_within_index(index, &self.path);
+        self
+    }
+}
+
+pub(crate) fn path_within_key(key: &str, path: &str) -> String {
+    let key = if !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '+') {
+        key.to_string()
+    } else {
+        format!("{key:?}")
+    };
+
+    match path.chars().next() {
+        None => key,
+        Some('[') => format!("{key}{path}"),
+        Some(_) => format!("{key}.{path}"),
+    }
+}
+
+pub(crate) fn path_within_index(index: usize, path: &str) -> String {
+    match path.chars().next() {
+        None | Some('[') => format!("[{index}]{path}"),
+        Some(_) => format!("[{index}].{path}"),
+    }
+}
+
+impl Display for NBTTagDeserializeErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownTagId(id) => write!(f, "unknown tag id {id}"),
+            Self::UnexpectedEnd { needed, remaining } => write!(f, "unexpected end of data, needed {needed} bytes but only {remaining} were left"),
+            Self::InvalidUtf8 => write!(f, "string is not valid Modified UTF-8"),
+            Self::NegativeLength(len) => write!(f, "negative length {len}"),
+            Self::DepthLimitExceeded(max) => write!(f, "tags are nested deeper than the limit of {max}"),
+            Self::LengthLimitExceeded { len, max } => write!(f, "length {len} is over the limit of {max}"),
+            Self::SizeLimitExceeded(max) => write!(f, "tags take up more than the limit of {max} bytes"),
+            Self::InvalidVarInt => write!(f, "varint is too long"),
+            Self::Decompression(kind) => write!(f, "failed to decompress data: {kind}"),
+        }
+    }
+}
+
+impl Display for NBTTagDeserializeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at byte {}", self.kind, self.offset)?;
+        if !self.path.is_empty() {
+            write!(f, " (in '{}')", self.path)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Error for NBTTagDeserializeError {}
+
+/// The reason that encoding an NBT tag failed
+#[derive(Debug)]
+pub enum NBTTagSerializeErrorKind {
+    /// A list had tags of different types in it
+    MixedList { expected: &'static str, found: &'static str },
+    /// A string or tag name was longer than the 65535 bytes that its length prefix can hold
+    StringTooLong(usize),
+    /// The writer returned an error
+    Io(io::Error),
+}
+
+/// An error from encoding an NBT tag, along with the path of the tag that couldn't be written
+#[derive(Debug)]
+pub struct NBTTagSerializeError {
+    pub kind: NBTTagSerializeErrorKind,
+    pub path: String,
+}
+
+impl NBTTagSerializeError {
+    pub fn new(kind: NBTTagSerializeErrorKind) -> Self {
+        Self {
+            kind,
+            path: String::new(),
+        }
+    }
+
+    /// Prefixes the path with a compound key, used as the error is passed up out of a compound
+    pub fn within_key(mut self, key: &str) -> Self {
+        self.path = path_within_key(key, &self.path);
+        self
+    }
+
+    /// Prefixes the path with a list index, used as the error is passed up out of a list
+    pub fn within_index(mut self, index: usize) -> Self {
+        self.path = path_within_index(index, &self.path);
+        self
+    }
+}
+
+impl From<io::Error> for NBTTagSerializeError {
+    fn from(value: io::Error) -> Self {
+        Self::new(NBTTagSerializeErrorKind::Io(value))
+    }
+}
+
+impl Display for NBTTagSerializeErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MixedList { expected, found } => write!(f, "can't write {found} into a list of {expected}"),
+            Self::StringTooLong(len) => write!(f, "string is {len} bytes long, but the most that can be written is {}", u16::MAX),
+            Self::Io(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl Display for NBTTagSerializeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if !self.path.is_empty() {
+            write!(f, " (in '{}')", self.path)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Error for NBTTagSerializeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.kind {
+            NBTTagSerializeErrorKind::Io(error) => Some(error),
+            _ => None,
+        }
+    }
 }
 
 impl NBTTag {
@@ -60,193 +241,354 @@ impl NBTTag {
         }
     }
 
-    pub fn get(&self, str: impl Into<String>) -> Option<&NBTTag> {
+    pub fn get(&self, key: impl AsRef<str>) -> Option<&NBTTag> {
         let Self::Compound(inner) = self else {
             return None;
         };
 
-        let str = str.into();
-
-        for (name, data) in inner {
-            if name == &str {
-                return Some(data);
-            }
-        }
-
-        None
+        inner.get(key.as_ref())
     }
 
-    pub fn get_mut(&mut self, str: impl Into<String>) -> Option<&mut NBTTag> {
+    pub fn get_mut(&mut self, key: impl AsRef<str>) -> Option<&mut NBTTag> {
         let Self::Compound(inner) = self else {
             return None;
         };
 
-        let str = str.into();
+        inner.get_mut(key.as_ref())
+    }
 
-        for (name, data) in inner {
-            if name == &str {
-                return Some(data);
-            }
+    /// Merges `other` into this tag with `NBTCompound::merge`. Does nothing unless both tags are compounds.
+    pub fn merge(&mut self, other: &NBTTag) {
+        if let (Self::Compound(inner), Self::Compound(other)) = (self, other) {
+            inner.merge(other);
         }
-
-        None
     }
 
+    /// Encodes the payload of this tag into a new `Vec<u8>`
+    ///
+    /// # Panics
+    /// Panics if a list has tags of different types in it or a string is too long, use `write_to` to get an error instead
     pub fn as_bytes(&self) -> Vec<u8> {
-        match self {
-            NBTTag::Byte(b) => b.to_be_bytes().to_vec(),
-            NBTTag::Short(s) => s.to_be_bytes().to_vec(),
-            NBTTag::Int(i) => i.to_be_bytes().to_vec(),
-            NBTTag::Long(l) => l.to_be_bytes().to_vec(),
-            NBTTag::Float(f) => f.to_be_bytes().to_vec(),
-            NBTTag::Double(d) => d.to_be_bytes().to_vec(),
-            NBTTag::ByteArray(ba) => {
-                let mut out = Vec::with_capacity(ba.len() + size_of::<i32>());
-                out.extend((ba.len() as i32).to_be_bytes());
-
-                for b in ba {
-                    out.push(*b);
-                }
+        let mut out = Vec::new();
+        if let Err(error) = self.write_to(&mut out) {
+            panic!("Failed to encode NBT Tag: {error}");
+        }
 
-                out
-            }
-            NBTTag::String(s) => {
-                let mut out = Vec::with_capacity(s.len() + size_of::<u16>());
+        out
+    }
 
-                out.extend((s.len() as u16).to_be_bytes());
-                out.extend(s.bytes());
+    /// Writes the payload of this tag straight into `writer`, without building any intermediate buffers
+    ///
+    /// The tag's id and name are not written here, since those are stored by whatever contains the tag (or by the root `NBT`). Every number is written with its own call to `write_all`, so slow writers like files should be wrapped in a `BufWriter`.
+    pub fn write_to<W: Write + ?Sized>(&self, writer: &mut W) -> Result<(), NBTTagSerializeError> {
+        self.write_with::<JavaCodec, W>(writer)
+    }
 
-                out
+    /// Writes the payload of this tag like `write_to`, but with the numbers and strings encoded by `C`
+    pub fn write_with<C: NBTCodec, W: Write + ?Sized>(&self, writer: &mut W) -> Result<(), NBTTagSerializeError> {
+        match self {
+            NBTTag::Byte(b) => writer.write_all(&b.to_be_bytes())?,
+            NBTTag::Short(s) => C::write_short(writer, *s)?,
+            NBTTag::Int(i) => C::write_int(writer, *i)?,
+            NBTTag::Long(l) => C::write_long(writer, *l)?,
+            NBTTag::Float(f) => C::write_float(writer, *f)?,
+            NBTTag::Double(d) => C::write_double(writer, *d)?,
+            NBTTag::ByteArray(ba) => {
+                C::write_len(writer, ba.len())?;
+                writer.write_all(ba)?;
             }
+            NBTTag::String(s) => C::write_string(writer, s)?,
             NBTTag::List(l) => {
-                let list_id = l.get(0).and_then(|tag| Some(tag.id())).unwrap_or_default();
+                let list_id = l.first().map(|tag| tag.id()).unwrap_or_default();
 
-                let mut out = Vec::new();
-                out.push(list_id);
-                out.extend((l.len() as i32).to_be_bytes());
+                writer.write_all(&[list_id])?;
+                C::write_len(writer, l.len())?;
 
-                for tag in l {
+                for (i, tag) in l.iter().enumerate() {
                     if tag.id() != list_id {
-                        panic!("Every NBT Tag in a List Tag must be the same type!");
+                        let kind = NBTTagSerializeErrorKind::MixedList { expected: l[0].name(), found: tag.name() };
+                        return Err(NBTTagSerializeError::new(kind).within_index(i));
                     }
 
-                    out.extend(tag.as_bytes());
+                    tag.write_with::<C, W>(writer).map_err(|e| e.within_index(i))?;
                 }
-
-                out
             }
             NBTTag::Compound(c) => {
-                let mut out = Vec::new();
-
                 for (name, tag) in c {
-                    out.push(tag.id());
-                    out.extend((name.len() as u16).to_be_bytes());
-                    out.extend(name.bytes());
-                    out.extend(tag.as_bytes());
+                    writer.write_all(&[tag.id()])?;
+                    C::write_string(writer, name).map_err(|e| e.within_key(name))?;
+                    tag.write_with::<C, W>(writer).map_err(|e| e.within_key(name))?;
                 }
 
-                out.push(0);
-
-                out
+                writer.write_all(&[0])?;
             }
             NBTTag::IntArray(ia) => {
-                let mut out = Vec::with_capacity((ia.len() * size_of::<i32>()) + size_of::<i32>());
-                out.extend((ia.len() as i32).to_be_bytes());
+                C::write_len(writer, ia.len())?;
 
                 for i in ia {
-                    out.extend(i.to_be_bytes())
+                    C::write_int(writer, *i)?;
                 }
-
-                out
             }
             NBTTag::LongArray(la) => {
-                let mut out = Vec::with_capacity((la.len() * size_of::<i64>()) + size_of::<i32>());
-                out.extend((la.len() as i32).to_be_bytes());
+                C::write_len(writer, la.len())?;
 
                 for l in la {
-                    out.extend(l.to_be_bytes())
+                    C::write_long(writer, *l)?;
                 }
-
-                out
             }
         }
+
+        Ok(())
+    }
+
+    /// Reads the payload of a tag with the given id off of the queue
+    ///
+    /// The tag's id and name are not read here, since those are stored by whatever contains the tag (or by the root `NBT`).
+    ///
+    /// This function returns an `NBTTagDeserializeError` instead of panicking when the data is invalid or incomplete, so it is safe to use on untrusted data.
+    pub fn from_bytes(queue: &mut Queue, id: u8) -> Result<NBTTag, NBTTagDeserializeError> {
+        Self::from_bytes_with::<JavaCodec>(queue, id)
     }
 
-    // TODO: change the return to a Result with the error given
-    pub fn from_bytes(queue: &mut Queue, id: u8) -> Option<NBTTag> {
+    /// Reads the payload of a tag like `from_bytes`, but with the numbers and strings decoded by `C`
+    pub fn from_bytes_with<C: NBTCodec>(queue: &mut Queue, id: u8) -> Result<NBTTag, NBTTagDeserializeError> {
+        Self::from_bytes_with_options::<C>(queue, id, &NBTDecodeOptions::default())
+    }
+
+    /// Reads the payload of a tag like `from_bytes_with`, failing once the tag goes over any of the limits in `options`
+    pub fn from_bytes_with_options<C: NBTCodec>(queue: &mut Queue, id: u8, options: &NBTDecodeOptions) -> Result<NBTTag, NBTTagDeserializeError> {
+        let mut limiter = Limiter { options, used: 0 };
+        limiter.charge(queue, size_of::<NBTTag>())?;
+
+        Self::from_bytes_nested::<C>(queue, id, 0, &mut limiter)
+    }
+
+    fn from_bytes_nested<C: NBTCodec>(queue: &mut Queue, id: u8, depth: usize, limiter: &mut Limiter) -> Result<NBTTag, NBTTagDeserializeError> {
+        // lists and compounds are read in their own functions to keep each recursive stack frame small
         match id {
-            1 => Some(NBTTag::Byte(queue.pop().expect(""))),
-            2 => Some(NBTTag::Short(queue.pop().expect(""))),
-            3 => Some(NBTTag::Int(queue.pop().expect(""))),
-            4 => Some(NBTTag::Long(queue.pop().expect(""))),
-            5 => Some(NBTTag::Float(queue.pop().expect(""))),
-            6 => Some(NBTTag::Double(queue.pop().expect(""))),
+            9 => Self::list_from_bytes::<C>(queue, limiter.enter(queue, depth)?, limiter),
+            10 => Self::compound_from_bytes::<C>(queue, limiter.enter(queue, depth)?, limiter),
+            _ => Self::value_from_bytes::<C>(queue, id, limiter),
+        }
+    }
+
+    fn value_from_bytes<C: NBTCodec>(queue: &mut Queue, id: u8, limiter: &mut Limiter) -> Result<NBTTag, NBTTagDeserializeError> {
+        match id {
+            1 => Ok(NBTTag::Byte(pop(queue)?)),
+            2 => Ok(NBTTag::Short(C::read_short(queue)?)),
+            3 => Ok(NBTTag::Int(C::read_int(queue)?)),
+            4 => Ok(NBTTag::Long(C::read_long(queue)?)),
+            5 => Ok(NBTTag::Float(C::read_float(queue)?)),
+            6 => Ok(NBTTag::Double(C::read_double(queue)?)),
             7 => {
-                let len = queue.pop::<i32>().expect("") as usize;
-                let mut bytes = vec![0u8; len];
-                for b in &mut bytes {
-                    *b = queue.pop().expect("");
-                }
+                let len = limiter.array_len::<C, u8>(queue)?;
+                ensure_left(queue, len)?;
 
-                Some(NBTTag::ByteArray(bytes))
+                Ok(NBTTag::ByteArray(queue.pop_bytes(len).expect("the queue was checked to have enough bytes left")))
             }
             8 => {
-                let len = queue.pop::<u16>().expect("") as usize;
-                Some(NBTTag::String(queue.pop_str(len).expect("")))
-            }
-            9 => {
-                let list_id = queue.pop::<u8>().expect("");
-                let len = queue.pop::<i32>().expect("");
-                if len <= 0 {
-                    Some(NBTTag::List(vec![]))
-                } else {
-                    let mut list = Vec::with_capacity(len as usize);
-                    for _ in 0..len {
-                        list.push(Self::from_bytes(queue, list_id).expect(""));
-                    }
+                let s = C::read_string(queue)?;
+                limiter.charge(queue, s.len())?;
 
-                    Some(NBTTag::List(list))
-                }
-            }
-            10 => {
-                let mut vec = Vec::new();
-                let mut popped_id = queue.pop::<u8>().expect("");
-                while popped_id != 0 {
-                    let name_len = queue.pop::<u16>().expect("") as usize;
-                    let name = queue.pop_str(name_len).expect("");
-
-                    vec.push((name, Self::from_bytes(queue, popped_id).expect("")));
-
-                    popped_id = queue.pop::<u8>().expect("");
-                }
-
-                Some(NBTTag::Compound(vec))
+                Ok(NBTTag::String(s))
             }
             11 => {
-                let len = queue.pop::<i32>().expect("") as usize;
+                let len = limiter.array_len::<C, i32>(queue)?;
+                ensure_left(queue, len.saturating_mul(C::MIN_INT_SIZE))?;
+
                 let mut ints = vec![0i32; len];
                 for i in &mut ints {
-                    *i = queue.pop().expect("");
+                    *i = C::read_int(queue)?;
                 }
 
-                Some(NBTTag::IntArray(ints))
+                Ok(NBTTag::IntArray(ints))
             }
             12 => {
-                {
-                    let len = queue.pop::<i32>().expect("") as usize;
-                    let mut longs = vec![0i64; len];
-                    for l in &mut longs {
-                        *l = queue.pop().expect("");
-                    }
+                let len = limiter.array_len::<C, i64>(queue)?;
+                ensure_left(queue, len.saturating_mul(C::MIN_LONG_SIZE))?;
 
-                    Some(NBTTag::LongArray(longs))
+                let mut longs = vec![0i64; len];
+                for l in &mut longs {
+                    *l = C::read_long(queue)?;
                 }
+
+                Ok(NBTTag::LongArray(longs))
             }
-            _ => None
+            _ => Err(NBTTagDeserializeError::new(NBTTagDeserializeErrorKind::UnknownTagId(id), queue.position().saturating_sub(1)))
+        }
+    }
+
+    fn list_from_bytes<C: NBTCodec>(queue: &mut Queue, depth: usize, limiter: &mut Limiter) -> Result<NBTTag, NBTTagDeserializeError> {
+        let list_id = pop::<u8>(queue)?;
+        let len = limiter.list_len::<C>(queue)?;
+
+        let mut list = Vec::with_capacity(len.min(queue.bytes_left()));
+        for i in 0..len {
+            list.push(Self::from_bytes_nested::<C>(queue, list_id, depth, limiter).map_err(|e| e.within_index(i))?);
+        }
+
+        Ok(NBTTag::List(list))
+    }
+
+    fn compound_from_bytes<C: NBTCodec>(queue: &mut Queue, depth: usize, limiter: &mut Limiter) -> Result<NBTTag, NBTTagDeserializeError> {
+        let mut compound = NBTCompound::new();
+        let mut popped_id = pop::<u8>(queue)?;
+        while popped_id != 0 {
+            let name = C::read_string(queue)?;
+            limiter.charge(queue, name.len() + size_of::<String>() + size_of::<NBTTag>())?;
+            let tag = Self::from_bytes_nested::<C>(queue, popped_id, depth, limiter).map_err(|e| e.within_key(&name))?;
+
+            compound.insert(name, tag);
+
+            popped_id = pop::<u8>(queue)?;
+        }
+
+        Ok(NBTTag::Compound(compound))
+    }
+}
+
+/// Writes a u16 length prefixed Modified UTF-8 string, which is how both string tags and tag names are stored
+pub(crate) fn write_string<W: Write + ?Sized>(writer: &mut W, s: &str) -> Result<(), NBTTagSerializeError> {
+    let bytes = mutf8::encode(s);
+    let len = u16::try_from(bytes.len()).map_err(|_| NBTTagSerializeError::new(NBTTagSerializeErrorKind::StringTooLong(bytes.len())))?;
+
+    writer.write_all(&len.to_be_bytes())?;
+    writer.write_all(&bytes)?;
+
+    Ok(())
+}
+
+/// The deepest that lists and compounds can be nested inside each other, the same limit that vanilla uses
+pub const MAX_DEPTH: usize = 512;
+
+/// Limits on how much decoding a tag is allowed to read, so that untrusted data can't use up the stack or memory
+///
+/// The memory a tag takes up is estimated from the size of each tag and compound key, and the contents of arrays and strings.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct NBTDecodeOptions {
+    /// The deepest that lists and compounds can be nested inside each other
+    pub max_depth: usize,
+    /// The most memory that the decoded tags can take up
+    pub max_bytes: usize,
+    /// The most elements that a byte, int or long array can have
+    pub max_array_len: usize,
+    /// The most elements that a list can have
+    pub max_list_len: usize,
+}
+
+impl NBTDecodeOptions {
+    /// The limits for files on disk, where only the depth is limited like in vanilla
+    pub const DISK: Self = Self {
+        max_depth: MAX_DEPTH,
+        max_bytes: usize::MAX,
+        max_array_len: usize::MAX,
+        max_list_len: usize::MAX,
+    };
+
+    /// The limits for NBT sent by clients, which allows 2 MiB of tags like vanilla does
+    pub const NETWORK: Self = Self {
+        max_depth: MAX_DEPTH,
+        max_bytes: 2 * 1024 * 1024,
+        max_array_len: 64 * 1024,
+        max_list_len: 64 * 1024,
+    };
+}
+
+impl Default for NBTDecodeOptions {
+    fn default() -> Self {
+        Self::DISK
+    }
+}
+
+/// Keeps track of how much has been decoded so far, checked against the limits in `NBTDecodeOptions`
+struct Limiter<'a> {
+    options: &'a NBTDecodeOptions,
+    used: usize,
+}
+
+impl Limiter<'_> {
+    fn charge(&mut self, queue: &Queue, bytes: usize) -> Result<(), NBTTagDeserializeError> {
+        self.used = self.used.saturating_add(bytes);
+        if self.used > self.options.max_bytes {
+            return Err(NBTTagDeserializeError::new(NBTTagDeserializeErrorKind::SizeLimitExceeded(self.options.max_bytes), queue.position()));
+        }
+
+        Ok(())
+    }
+
+    fn enter(&self, queue: &Queue, depth: usize) -> Result<usize, NBTTagDeserializeError> {
+        if depth >= self.options.max_depth {
+            return Err(NBTTagDeserializeError::new(NBTTagDeserializeErrorKind::DepthLimitExceeded(self.options.max_depth), queue.position()));
+        }
+
+        Ok(depth + 1)
+    }
+
+    /// Reads the length of an array of `T`, charging for its elements before they are allocated
+    fn array_len<C: NBTCodec, T>(&mut self, queue: &mut Queue) -> Result<usize, NBTTagDeserializeError> {
+        let len = self.len::<C>(queue, self.options.max_array_len)?;
+        self.charge(queue, len.saturating_mul(size_of::<T>()))?;
+
+        Ok(len)
+    }
+
+    /// Reads the length of a list, charging for its elements' tags before they are allocated
+    fn list_len<C: NBTCodec>(&mut self, queue: &mut Queue) -> Result<usize, NBTTagDeserializeError> {
+        let len = self.len::<C>(queue, self.options.max_list_len)?;
+        self.charge(queue, len.saturating_mul(size_of::<NBTTag>()))?;
+
+        Ok(len)
+    }
+
+    fn len<C: NBTCodec>(&self, queue: &mut Queue, max: usize) -> Result<usize, NBTTagDeserializeError> {
+        let offset = queue.position();
+        let len = C::read_len(queue)?;
+        if len > max {
+            return Err(NBTTagDeserializeError::new(NBTTagDeserializeErrorKind::LengthLimitExceeded { len, max }, offset));
         }
+
+        Ok(len)
     }
 }
 
+/// Pops a big endian number off of the queue, failing with `UnexpectedEnd` if there isn't enough data left
+pub(crate) fn pop<T: FromBytes>(queue: &mut Queue) -> Result<T, NBTTagDeserializeError> where for<'a> T::Bytes: TryFrom<&'a [u8]> {
+    ensure_left(queue, size_of::<T>())?;
+
+    Ok(queue.read_be().expect("the queue was checked to have enough bytes left"))
+}
+
+/// Pops a little endian number off of the queue, failing with `UnexpectedEnd` if there isn't enough data left
+pub(crate) fn pop_le<T: FromBytes>(queue: &mut Queue) -> Result<T, NBTTagDeserializeError> where for<'a> T::Bytes: TryFrom<&'a [u8]> {
+    ensure_left(queue, size_of::<T>())?;
+
+    Ok(queue.read_le().expect("the queue was checked to have enough bytes left"))
+}
+
+/// Pops a u16 length prefixed Modified UTF-8 string, which is how both string tags and tag names are stored
+pub(crate) fn pop_string(queue: &mut Queue) -> Result<String, NBTTagDeserializeError> {
+    let len = pop::<u16>(queue)? as usize;
+    ensure_left(queue, len)?;
+
+    let offset = queue.position();
+    let bytes = queue.pop_bytes(len).expect("the queue was checked to have enough bytes left");
+
+    // most strings are also valid UTF-8, so they can be used without being decoded again
+    match String::from_utf8(bytes) {
+        Ok(s) => Ok(s),
+        Err(error) => mutf8::decode(error.as_bytes()).map(Cow::into_owned).ok_or_else(|| NBTTagDeserializeError::new(NBTTagDeserializeErrorKind::InvalidUtf8, offset)),
+    }
+}
+
+pub(crate) fn ensure_left(queue: &Queue, needed: usize) -> Result<(), NBTTagDeserializeError> {
+    let remaining = queue.bytes_left();
+    if needed > remaining {
+        return Err(NBTTagDeserializeError::new(NBTTagDeserializeErrorKind::UnexpectedEnd { needed, remaining }, queue.position()));
+    }
+
+    Ok(())
+}
+
 impl Display for NBTTag {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -287,32 +629,16 @@ impl Index<&str> for NBTTag {
 
     fn index(&self, index: &str) -> &Self::Output {
         match self {
-            Self::Compound(inner) => {
-                for (name, nbt_tag) in inner {
-                    if name == index {
-                        return nbt_tag;
-                    }
-                }
-            },
+            Self::Compound(inner) => &inner[index],
             _ => panic!("Attempted to index into a non-compound NBT Tag.")
         }
-
-        panic!("Could not find tag '{}' in NBT Tag.", index);
     }
 }
 impl IndexMut<&str> for NBTTag {
     fn index_mut(&mut self, index: &str) -> &mut Self::Output {
         match self {
-            Self::Compound(inner) => {
-                for (name, nbt_tag) in inner {
-                    if name == index {
-                        return nbt_tag;
-                    }
-                }
-            },
+            Self::Compound(inner) => &mut inner[index],
             _ => panic!("Attempted to index into a non-compound NBT Tag.")
         }
-
-        panic!("Could not find tag '{}' in NBT Tag.", index);
     }
-}
\ No newline at end of file
+}
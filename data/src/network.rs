@@ -1,5 +1,14 @@
+use crate::encryption::{StreamDecryptor, StreamEncryptor};
+use crate::frame::{FrameDecoder, FrameEncoder};
+use crate::writer::ByteWriter;
+use bytes::BytesMut;
 use shipyard::Component;
-use crate::queue::Queue;
+use std::net::SocketAddr;
+
+/// The version that the server runs, which is shown in the server list
+pub const VERSION_NAME: &str = "1.21.4";
+/// The protocol version of `VERSION_NAME`, which clients send in Handshake
+pub const PROTOCOL_VERSION: i32 = 769;
 
 #[derive(Component)]
 pub enum ConnectionState {
@@ -11,10 +20,49 @@ pub enum ConnectionState {
     Play,
 }
 
-#[derive(Component)]
+#[derive(Component, Default)]
 pub struct Connection {
+    pub decoder: FrameDecoder,
+    pub encoder: FrameEncoder,
+    /// The address that the client connected from, which is set when the connection is accepted
+    pub address: Option<SocketAddr>,
+}
+
+impl Connection {
+    /// Compresses packets that are at least `threshold` bytes long in both directions, or stops compressing with `None`
+    ///
+    /// This has to be called right after sending Set Compression, since every packet after it is compressed.
+    pub fn set_compression(&mut self, threshold: Option<usize>) {
+        self.decoder.set_compression(threshold);
+        self.encoder.set_compression(threshold);
+    }
+
+    /// Encrypts both directions with the shared secret from Encryption Response, which has to be called as soon as that packet is decoded
+    ///
+    /// Anything still buffered after Encryption Response was already encrypted by the client, so it is decrypted too.
+    pub fn enable_encryption(&mut self, shared_secret: &[u8; 16]) {
+        self.decoder.enable_encryption(StreamDecryptor::new(shared_secret));
+        self.encoder.enable_encryption(StreamEncryptor::new(shared_secret));
+    }
+}
 
+/// The player that a connection is logging in as, between Encryption Request and Encryption Response
+#[derive(Component)]
+pub struct PendingLogin {
+    /// The name from Login Start
+    pub name: String,
+    /// The token that was sent in Encryption Request
+    pub verify_token: [u8; 4],
 }
 
+/// Bytes that have been read from a connection but not decoded yet
+#[derive(Component)]
+pub struct ReadBytes(pub BytesMut);
+
+/// Bytes that have been encoded for a connection but not sent yet
+#[derive(Component, Default)]
+pub struct WriteBytes(pub ByteWriter);
+
+/// Marks a connection to be closed once its `WriteBytes` have been sent
 #[derive(Component)]
-pub struct ReadBytes(pub Vec<u8>);
\ No newline at end of file
+pub struct Disconnect;
@@ -1,13 +1,14 @@
-use std::mem;
-use num_traits::{FromBytes, ToBytes};
+use bytes::{Buf, Bytes, BytesMut};
+use num_traits::FromBytes;
 
-/// A byte queue that allows for numbers to be popped off the end
-///
-/// This struct can only be built from a Vec<u8> or anything that impls Into<Vec<u8>>, and does not allow data to be pushed to the end.
+const SEGMENT_BITS: u8 = 0x7F;
+const CONTINUE_BIT: u8 = 0x80;
+
+/// A cursor over a byte buffer that numbers and byte slices can be read off the front of
 ///
-/// Data for the queue is held in heap memory for the entire lifetime of the Queue
+/// This struct can be built from a `Vec<u8>`, or without copying from the `Bytes` and `BytesMut` that network reads fill, and does not allow data to be pushed to the end. It also implements `bytes::Buf`, so anything that reads from a `Buf` can read from it.
 pub struct Queue {
-    data: Box<[u8]>,
+    data: Bytes,
     cursor: usize,
 }
 
@@ -17,72 +18,169 @@ impl Queue {
         self.data.len() - self.cursor
     }
 
-    /// Pops a number from the beginning of the Queue
-    ///
-    /// T is a data type that impls num_traits::FromBytes
+    /// Returns the number of bytes that have been popped off the queue so far
+    pub fn position(&self) -> usize {
+        self.cursor
+    }
+
+    /// Remembers the current position, so that the queue can be `reset` back to it
+    pub fn mark(&self) -> QueueMark {
+        QueueMark(self.cursor)
+    }
+
+    /// Moves back to a position from `mark`, so that everything read since then can be read again
+    pub fn reset(&mut self, mark: QueueMark) {
+        self.cursor = mark.0;
+    }
+
+    /// Runs `read`, and moves back to where the queue was before it if it returns `None`
     ///
-    /// This function returns `None` when there is not enough space remaining in the Queue for the number to be popped off
-    pub fn pop<T: ToBytes + FromBytes>(&mut self) -> Option<T> where <T as FromBytes>::Bytes: From<<T as ToBytes>::Bytes> {
-        if size_of::<T>() > self.bytes_left() {
-            return None;
+    /// This is how a decoder can try to read something that may not have fully arrived yet, without losing the bytes that it did read.
+    pub fn try_read<T>(&mut self, read: impl FnOnce(&mut Self) -> Option<T>) -> Option<T> {
+        let mark = self.mark();
+        let value = read(self);
+        if value.is_none() {
+            self.reset(mark);
         }
 
-        let value = unsafe {
-            let mut value = mem::zeroed::<T>();
-            std::ptr::copy_nonoverlapping(
-                self.data.as_ptr().add(self.cursor),
-                &mut value as *mut T as *mut u8,
-                size_of::<T>()
-            );
-            value
-        };
+        value
+    }
 
+    /// Reads a big endian number off the front of the queue, which is how the Java protocol and NBT store numbers
+    ///
+    /// T is any primitive number, including `i128` and `u128`. This function returns `None` when there are not enough bytes left for the number, and nothing is read.
+    pub fn read_be<T: FromBytes>(&mut self) -> Option<T> where for<'a> T::Bytes: TryFrom<&'a [u8]> {
+        let value = self.peek_be()?;
         self.cursor += size_of::<T>();
 
-        Some(T::from_be_bytes(&value.to_le_bytes().into()))
+        Some(value)
     }
 
-    pub fn peek<T: ToBytes + FromBytes>(&mut self) -> Option<T> where <T as FromBytes>::Bytes: From<<T as ToBytes>::Bytes> {
-        if size_of::<T>() > self.bytes_left() {
-            return None;
-        }
+    /// Reads a little endian number off the front of the queue, which is how Bedrock NBT stores numbers
+    pub fn read_le<T: FromBytes>(&mut self) -> Option<T> where for<'a> T::Bytes: TryFrom<&'a [u8]> {
+        let value = self.peek_le()?;
+        self.cursor += size_of::<T>();
 
-        let value = unsafe {
-            let mut value = mem::zeroed::<T>();
-            std::ptr::copy_nonoverlapping(
-                self.data.as_ptr().add(self.cursor),
-                &mut value as *mut T as *mut u8,
-                size_of::<T>()
-            );
-            value
-        };
+        Some(value)
+    }
 
-        Some(T::from_be_bytes(&value.to_le_bytes().into()))
+    /// Reads a big endian number without moving past it
+    pub fn peek_be<T: FromBytes>(&self) -> Option<T> where for<'a> T::Bytes: TryFrom<&'a [u8]> {
+        Some(T::from_be_bytes(&self.peek_array::<T>()?))
     }
 
-    pub fn pop_str(&mut self, len: usize) -> Option<String> {
-        let mut bytes = vec![0u8; len];
-        for b in &mut bytes {
-            *b = self.pop()?;
+    /// Reads a little endian number without moving past it
+    pub fn peek_le<T: FromBytes>(&self) -> Option<T> where for<'a> T::Bytes: TryFrom<&'a [u8]> {
+        Some(T::from_le_bytes(&self.peek_array::<T>()?))
+    }
+
+    fn peek_array<T: FromBytes>(&self) -> Option<T::Bytes> where for<'a> T::Bytes: TryFrom<&'a [u8]> {
+        let bytes = self.data.get(self.cursor..self.cursor.checked_add(size_of::<T>())?)?;
+
+        T::Bytes::try_from(bytes).ok()
+    }
+
+    /// Reads a number in the protocol's VarInt format, reading nothing if it is incomplete or longer than 5 bytes
+    pub fn read_var_int(&mut self) -> Option<i32> {
+        self.try_read(|queue| queue.read_var(5)).map(|value| value as u32 as i32)
+    }
+
+    /// Reads a number in the protocol's VarLong format, reading nothing if it is incomplete or longer than 10 bytes
+    pub fn read_var_long(&mut self) -> Option<i64> {
+        self.try_read(|queue| queue.read_var(10)).map(|value| value as i64)
+    }
+
+    fn read_var(&mut self, max_len: usize) -> Option<u64> {
+        let mut value = 0;
+        for i in 0..max_len {
+            let byte = self.read_be::<u8>()?;
+            value |= ((byte & SEGMENT_BITS) as u64) << (i * 7);
+
+            if byte & CONTINUE_BIT == 0 {
+                return Some(value);
+            }
         }
 
-        String::from_utf8(bytes).ok()
+        None
+    }
+
+    /// Reads `len` raw bytes off of the queue without copying them, returning `None` if there aren't that many left
+    pub fn read_bytes(&mut self, len: usize) -> Option<&[u8]> {
+        let end = self.cursor.checked_add(len).filter(|end| *end <= self.data.len())?;
+        let bytes = &self.data[self.cursor..end];
+        self.cursor = end;
+
+        Some(bytes)
+    }
+
+    /// Splits the next `len` bytes off into their own queue without copying them, returning `None` if there aren't that many left
+    pub fn split_to(&mut self, len: usize) -> Option<Queue> {
+        let end = self.cursor.checked_add(len).filter(|end| *end <= self.data.len())?;
+        let data = self.data.slice(self.cursor..end);
+        self.cursor = end;
+
+        Some(data.into())
+    }
+
+    /// Pops `len` raw bytes off of the queue into a new `Vec`, returning `None` if there aren't that many left
+    pub fn pop_bytes(&mut self, len: usize) -> Option<Vec<u8>> {
+        self.read_bytes(len).map(<[u8]>::to_vec)
+    }
+
+    pub fn pop_str(&mut self, len: usize) -> Option<String> {
+        String::from_utf8(self.pop_bytes(len)?).ok()
     }
 }
 
-impl<T: Into<Vec<u8>>> From<T> for Queue {
-    fn from(value: T) -> Self {
-        let value = value.into();
+/// A position in a `Queue` that it can be reset back to
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct QueueMark(usize);
+
+impl Buf for Queue {
+    fn remaining(&self) -> usize {
+        self.bytes_left()
+    }
+
+    fn chunk(&self) -> &[u8] {
+        &self.data[self.cursor..]
+    }
+
+    fn advance(&mut self, cnt: usize) {
+        assert!(cnt <= self.bytes_left(), "cannot advance past the end of the queue");
+        self.cursor += cnt;
+    }
+}
 
+impl From<Bytes> for Queue {
+    fn from(value: Bytes) -> Self {
         Self {
-            data: value.into_boxed_slice(),
+            data: value,
             cursor: 0,
         }
     }
 }
 
-impl Into<Vec<u8>> for Queue {
-    fn into(self) -> Vec<u8> {
-        self.data[self.cursor..].to_vec()
+impl From<BytesMut> for Queue {
+    fn from(value: BytesMut) -> Self {
+        value.freeze().into()
     }
-}
\ No newline at end of file
+}
+
+impl From<Vec<u8>> for Queue {
+    fn from(value: Vec<u8>) -> Self {
+        Bytes::from(value).into()
+    }
+}
+
+/// Returns the bytes that haven't been read yet, without copying them
+impl From<Queue> for Bytes {
+    fn from(value: Queue) -> Self {
+        value.data.slice(value.cursor..)
+    }
+}
+
+impl From<Queue> for Vec<u8> {
+    fn from(value: Queue) -> Self {
+        value.data[value.cursor..].to_vec()
+    }
+}
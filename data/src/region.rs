@@ -0,0 +1,354 @@
+//! Anvil region files (`r.<x>.<z>.mca`), which each store the chunks of a 32x32 chunk area of a world
+//!
+//! A region file starts with an 8 KiB header: 1024 big endian offsets, where the upper 3 bytes are the sector the chunk starts at and the low byte is how many 4 KiB sectors it takes up, followed by 1024 last-modified timestamps. Each chunk is a big endian length, a compression type and the compressed NBT. Chunks too big for 255 sectors are stored in their own `c.<x>.<z>.mcc` file next to the region file.
+
+use crate::nbt::tag::{NBTTagDeserializeError, NBTTagSerializeError};
+use crate::nbt::{NBTCompression, NBT};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{fs, io};
+
+pub const SECTOR_SIZE: usize = 4096;
+/// The number of chunks along each side of a region
+pub const REGION_WIDTH: usize = 32;
+
+const CHUNK_COUNT: usize = REGION_WIDTH * REGION_WIDTH;
+const HEADER_SECTORS: usize = 2;
+const MAX_SECTORS: usize = 255;
+/// Set in the compression type when the chunk is stored in a `.mcc` file
+const EXTERNAL_FLAG: u8 = 0x80;
+
+/// The reason that reading or writing a region file failed
+#[derive(Debug)]
+pub enum RegionErrorKind {
+    /// The file couldn't be read or written
+    Io(io::Error),
+    /// The file is too short to hold the header
+    TruncatedHeader(u64),
+    /// A chunk's header entry points at the header or past the end of the file
+    InvalidOffset { sector: usize, count: usize },
+    /// A chunk's length doesn't fit in the sectors it was given
+    InvalidLength(u32),
+    /// A chunk was compressed with a compression type that isn't known
+    UnknownCompression(u8),
+    /// The chunk's NBT couldn't be decoded
+    Decode(NBTTagDeserializeError),
+    /// The chunk's NBT couldn't be encoded
+    Encode(NBTTagSerializeError),
+}
+
+/// An error from reading or writing a region file, along with the chunk it happened in if it was about a chunk
+#[derive(Debug)]
+pub struct RegionError {
+    pub kind: RegionErrorKind,
+    pub chunk: Option<(i32, i32)>,
+}
+
+impl RegionError {
+    pub fn new(kind: RegionErrorKind) -> Self {
+        Self { kind, chunk: None }
+    }
+
+    fn in_chunk(mut self, x: i32, z: i32) -> Self {
+        self.chunk = Some((x, z));
+        self
+    }
+}
+
+impl From<io::Error> for RegionError {
+    fn from(value: io::Error) -> Self {
+        Self::new(RegionErrorKind::Io(value))
+    }
+}
+
+impl From<NBTTagDeserializeError> for RegionError {
+    fn from(value: NBTTagDeserializeError) -> Self {
+        Self::new(RegionErrorKind::Decode(value))
+    }
+}
+
+impl From<NBTTagSerializeError> for RegionError {
+    fn from(value: NBTTagSerializeError) -> Self {
+        Self::new(RegionErrorKind::Encode(value))
+    }
+}
+
+impl Display for RegionErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "{error}"),
+            Self::TruncatedHeader(len) => write!(f, "file is {len} bytes long, which is too short for the header"),
+            Self::InvalidOffset { sector, count } => write!(f, "chunk is {count} sectors at sector {sector}, which is outside of the file"),
+            Self::InvalidLength(len) => write!(f, "chunk length {len} doesn't fit in its sectors"),
+            Self::UnknownCompression(id) => write!(f, "unknown compression type {id}"),
+            Self::Decode(error) => write!(f, "{error}"),
+            Self::Encode(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl Display for RegionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if let Some((x, z)) = self.chunk {
+            write!(f, " (in chunk {x}, {z})")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Error for RegionError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.kind {
+            RegionErrorKind::Io(error) => Some(error),
+            RegionErrorKind::Decode(error) => Some(error),
+            RegionErrorKind::Encode(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+fn compression_id(compression: NBTCompression) -> u8 {
+    match compression {
+        NBTCompression::Gzip => 1,
+        NBTCompression::Zlib => 2,
+        NBTCompression::None => 3,
+        NBTCompression::Lz4 => 4,
+    }
+}
+
+fn compression_from_id(id: u8) -> Option<NBTCompression> {
+    match id {
+        1 => Some(NBTCompression::Gzip),
+        2 => Some(NBTCompression::Zlib),
+        3 => Some(NBTCompression::None),
+        4 => Some(NBTCompression::Lz4),
+        _ => None,
+    }
+}
+
+/// An open region file. Chunks are addressed with their absolute chunk coordinates, only the lowest 5 bits of which are used to find them in the file.
+pub struct RegionFile {
+    file: File,
+    directory: PathBuf,
+    offsets: [u32; CHUNK_COUNT],
+    timestamps: [u32; CHUNK_COUNT],
+    used_sectors: Vec<bool>,
+    compression: NBTCompression,
+}
+
+impl RegionFile {
+    /// Opens a region file, creating it with an empty header if it doesn't exist
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, RegionError> {
+        let path = path.as_ref();
+        let mut file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path)?;
+
+        let mut len = file.metadata()?.len();
+        let mut header = [0; HEADER_SECTORS * SECTOR_SIZE];
+        if len == 0 {
+            file.write_all(&header)?;
+            len = header.len() as u64;
+        } else if len < header.len() as u64 {
+            return Err(RegionError::new(RegionErrorKind::TruncatedHeader(len)));
+        } else {
+            file.read_exact(&mut header)?;
+        }
+
+        // pad the file to a whole number of sectors, like vanilla does, so that the last chunk can always be read in whole sectors
+        if len % SECTOR_SIZE as u64 != 0 {
+            let padded = len.next_multiple_of(SECTOR_SIZE as u64);
+            file.set_len(padded)?;
+            len = padded;
+        }
+
+        let read_u32 = |i: usize| u32::from_be_bytes([header[i], header[i + 1], header[i + 2], header[i + 3]]);
+        let offsets = std::array::from_fn(|i| read_u32(i * 4));
+        let timestamps = std::array::from_fn(|i| read_u32(SECTOR_SIZE + i * 4));
+
+        let mut region = Self {
+            file,
+            directory: path.parent().map(Path::to_path_buf).unwrap_or_default(),
+            offsets,
+            timestamps,
+            used_sectors: vec![false; len as usize / SECTOR_SIZE],
+            compression: NBTCompression::Zlib,
+        };
+
+        region.used_sectors[..HEADER_SECTORS].fill(true);
+        for offset in offsets {
+            // entries that point outside of the file are left for `read_chunk` to report
+            if let Some((sector, count)) = region.sectors(offset) {
+                region.used_sectors[sector..sector + count].fill(true);
+            }
+        }
+
+        Ok(region)
+    }
+
+    pub fn compression(&self) -> NBTCompression {
+        self.compression
+    }
+
+    /// Sets the compression that chunks are written with, which is zlib by default
+    pub fn set_compression(&mut self, compression: NBTCompression) {
+        self.compression = compression;
+    }
+
+    pub fn has_chunk(&self, x: i32, z: i32) -> bool {
+        self.offsets[index(x, z)] != 0
+    }
+
+    /// Returns when the chunk was last written, in seconds since the Unix epoch, or 0 if it has never been written
+    pub fn timestamp(&self, x: i32, z: i32) -> u32 {
+        self.timestamps[index(x, z)]
+    }
+
+    /// Reads and decompresses a chunk, returning `None` if the chunk isn't in the region
+    pub fn read_chunk(&self, x: i32, z: i32) -> Result<Option<NBT>, RegionError> {
+        self.read_chunk_inner(x, z).map_err(|error| error.in_chunk(x, z))
+    }
+
+    fn read_chunk_inner(&self, x: i32, z: i32) -> Result<Option<NBT>, RegionError> {
+        let offset = self.offsets[index(x, z)];
+        if offset == 0 {
+            return Ok(None);
+        }
+
+        let Some((sector, count)) = self.sectors(offset) else {
+            return Err(RegionError::new(RegionErrorKind::InvalidOffset { sector: (offset >> 8) as usize, count: (offset & 0xFF) as usize }));
+        };
+
+        let mut data = vec![0; count * SECTOR_SIZE];
+        let mut file = &self.file;
+        file.seek(SeekFrom::Start((sector * SECTOR_SIZE) as u64))?;
+        file.read_exact(&mut data)?;
+
+        let len = u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
+        if len == 0 || len as usize > data.len() - 4 {
+            return Err(RegionError::new(RegionErrorKind::InvalidLength(len)));
+        }
+
+        let id = data[4];
+        let compression = compression_from_id(id & !EXTERNAL_FLAG).ok_or_else(|| RegionError::new(RegionErrorKind::UnknownCompression(id)))?;
+        let compressed = if id & EXTERNAL_FLAG != 0 {
+            fs::read(self.external_path(x, z))?
+        } else {
+            data.truncate(4 + len as usize);
+            data.split_off(5)
+        };
+
+        Ok(Some(NBT::from_bytes_disk(compression.decompress(compressed)?)?))
+    }
+
+    /// Compresses and writes a chunk, replacing the chunk that was there before
+    ///
+    /// The chunk is written to newly allocated sectors before the header is updated, so that the old chunk is still intact if writing fails part way through.
+    pub fn write_chunk(&mut self, x: i32, z: i32, nbt: &NBT) -> Result<(), RegionError> {
+        self.write_chunk_inner(x, z, nbt).map_err(|error| error.in_chunk(x, z))
+    }
+
+    fn write_chunk_inner(&mut self, x: i32, z: i32, nbt: &NBT) -> Result<(), RegionError> {
+        let mut compressed = Vec::new();
+        nbt.write_compressed_to(&mut compressed, self.compression)?;
+
+        let id = compression_id(self.compression);
+        let external_path = self.external_path(x, z);
+        let mut data = Vec::with_capacity(compressed.len() + 5);
+        if compressed.len() + 5 > MAX_SECTORS * SECTOR_SIZE {
+            fs::write(&external_path, &compressed)?;
+            data.extend_from_slice(&1u32.to_be_bytes());
+            data.push(id | EXTERNAL_FLAG);
+        } else {
+            data.extend_from_slice(&(compressed.len() as u32 + 1).to_be_bytes());
+            data.push(id);
+            data.extend_from_slice(&compressed);
+        }
+        data.resize(data.len().next_multiple_of(SECTOR_SIZE), 0);
+
+        let count = data.len() / SECTOR_SIZE;
+        let sector = self.allocate(count);
+        self.file.seek(SeekFrom::Start((sector * SECTOR_SIZE) as u64))?;
+        self.file.write_all(&data)?;
+
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs() as u32);
+        let old = self.set_header(index(x, z), ((sector as u32) << 8) | count as u32, timestamp)?;
+        self.free(old);
+
+        if data[4] & EXTERNAL_FLAG == 0 {
+            remove_if_exists(&external_path)?;
+        }
+
+        Ok(())
+    }
+
+    /// Removes a chunk from the region, freeing its sectors
+    pub fn remove_chunk(&mut self, x: i32, z: i32) -> Result<(), RegionError> {
+        let old = self.set_header(index(x, z), 0, 0).map_err(|error| error.in_chunk(x, z))?;
+        self.free(old);
+
+        remove_if_exists(&self.external_path(x, z)).map_err(|error| RegionError::from(error).in_chunk(x, z))
+    }
+
+    /// Writes a header entry, returning the old offset
+    fn set_header(&mut self, index: usize, offset: u32, timestamp: u32) -> Result<u32, RegionError> {
+        self.file.seek(SeekFrom::Start(index as u64 * 4))?;
+        self.file.write_all(&offset.to_be_bytes())?;
+        self.file.seek(SeekFrom::Start((SECTOR_SIZE + index * 4) as u64))?;
+        self.file.write_all(&timestamp.to_be_bytes())?;
+
+        self.timestamps[index] = timestamp;
+        Ok(std::mem::replace(&mut self.offsets[index], offset))
+    }
+
+    /// Splits a header offset into its first sector and sector count, if it is inside of the file
+    fn sectors(&self, offset: u32) -> Option<(usize, usize)> {
+        let (sector, count) = ((offset >> 8) as usize, (offset & 0xFF) as usize);
+
+        (sector >= HEADER_SECTORS && count != 0 && sector + count <= self.used_sectors.len()).then_some((sector, count))
+    }
+
+    /// Finds the first run of `count` free sectors, growing the file if there isn't one, and marks them as used
+    fn allocate(&mut self, count: usize) -> usize {
+        let mut start = HEADER_SECTORS;
+        while start + count <= self.used_sectors.len() {
+            match self.used_sectors[start..start + count].iter().rposition(|used| *used) {
+                Some(used) => start += used + 1,
+                None => break,
+            }
+        }
+
+        if start + count > self.used_sectors.len() {
+            self.used_sectors.resize(start + count, false);
+        }
+
+        self.used_sectors[start..start + count].fill(true);
+        start
+    }
+
+    fn free(&mut self, offset: u32) {
+        if let Some((sector, count)) = self.sectors(offset) {
+            self.used_sectors[sector..sector + count].fill(false);
+        }
+    }
+
+    fn external_path(&self, x: i32, z: i32) -> PathBuf {
+        self.directory.join(format!("c.{x}.{z}.mcc"))
+    }
+}
+
+/// The index of a chunk in the header
+fn index(x: i32, z: i32) -> usize {
+    (x as usize & (REGION_WIDTH - 1)) + (z as usize & (REGION_WIDTH - 1)) * REGION_WIDTH
+}
+
+fn remove_if_exists(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error),
+        _ => Ok(()),
+    }
+}
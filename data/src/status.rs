@@ -0,0 +1,77 @@
+//! The status that is shown for the server in the client's server list
+//!
+//! The client asks for it with Status Request after a Handshake with a `next_state` of 1, and the server answers with this as JSON in Status Response.
+
+use crate::network::{PROTOCOL_VERSION, VERSION_NAME};
+use crate::text::TextComponent;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde_json::{json, Value};
+use shipyard::Unique;
+use uuid::Uuid;
+
+/// The server's status, which the status flow reads for every Status Request
+#[derive(Unique)]
+pub struct ServerStatus {
+    pub version_name: String,
+    pub protocol: i32,
+    pub max_players: i32,
+    pub online_players: i32,
+    /// The players that are listed when hovering over the player count
+    pub sample: Vec<(String, Uuid)>,
+    /// The MOTD
+    pub description: TextComponent,
+    favicon: Option<String>,
+    pub enforces_secure_chat: bool,
+}
+
+impl ServerStatus {
+    pub fn new(description: TextComponent, max_players: i32) -> Self {
+        Self {
+            version_name: VERSION_NAME.to_string(),
+            protocol: PROTOCOL_VERSION,
+            max_players,
+            online_players: 0,
+            sample: Vec::new(),
+            description,
+            favicon: None,
+            enforces_secure_chat: false,
+        }
+    }
+
+    /// Sets the icon shown next to the server, which should be a 64x64 PNG
+    pub fn set_favicon(&mut self, png: &[u8]) {
+        self.favicon = Some(format!("data:image/png;base64,{}", STANDARD.encode(png)));
+    }
+
+    pub fn clear_favicon(&mut self) {
+        self.favicon = None;
+    }
+
+    /// Returns the favicon as the data URL that is sent to the client
+    pub fn favicon(&self) -> Option<&str> {
+        self.favicon.as_deref()
+    }
+
+    pub fn to_json(&self) -> Value {
+        let sample = self.sample.iter().map(|(name, id)| json!({ "name": name, "id": id.to_string() })).collect::<Vec<_>>();
+
+        let mut json = json!({
+            "version": { "name": self.version_name, "protocol": self.protocol },
+            "players": { "max": self.max_players, "online": self.online_players, "sample": sample },
+            "description": self.description.to_json(),
+            "enforcesSecureChat": self.enforces_secure_chat,
+        });
+        if let Some(favicon) = &self.favicon {
+            json["favicon"] = favicon.as_str().into();
+        }
+
+        json
+    }
+}
+
+impl Default for ServerStatus {
+    fn default() -> Self {
+        Self::new(TextComponent::text("A Minecraft Server"), 20)
+    }
+}
@@ -1,5 +1,5 @@
-use crate::nbt::tag::NBTTag;
 use crate::resource::Identifier;
+use serde_json::{json, Map, Value};
 use std::fmt::{Display, Formatter};
 use uuid::Uuid;
 
@@ -8,7 +8,7 @@ enum TextComponentType {
     Text { text: String },
     Translatable { translate: String, fallback: Option<String>, with: Option<Vec<TextComponent>> },
     ScoreboardValue { score_holder: String, objective: String },
-    EntityNames { selector: String, separator: Option<TextComponent> },
+    EntityNames { selector: String, separator: Option<Box<TextComponent>> },
     Keybind { keybind: String },
 }
 
@@ -51,15 +51,15 @@ impl Display for TextColor {
             TextColor::LightPurple => write!(f, "light_purple"),
             TextColor::Yellow => write!(f, "yellow"),
             TextColor::White => write!(f, "white"),
-            TextColor::Custom(hex) => write!(f, "#{hex:x}")
+            TextColor::Custom(hex) => write!(f, "#{hex:06x}")
         }
     }
 }
 
 pub enum HoverEvent {
-    ShowText { text: TextComponent },
+    ShowText { text: Box<TextComponent> },
     ShowItem { id: Identifier, count: u8, },
-    ShowEntity { name: Option<TextComponent>, ty: Identifier, id: Uuid }
+    ShowEntity { name: Option<Box<TextComponent>>, ty: Identifier, id: Uuid }
 }
 
 pub enum ClickEvent {
@@ -87,6 +87,26 @@ pub struct TextComponent {
     hover_event: Option<HoverEvent>,
 }
 
+impl Default for TextComponent {
+    fn default() -> Self {
+        Self {
+            content: TextComponentType::Text { text: String::new() },
+            extra: None,
+            color: None,
+            font: None,
+            bold: false,
+            italic: false,
+            underlined: false,
+            strikethrough: false,
+            obfuscated: false,
+            include: 0,
+            insertion: None,
+            click_event: None,
+            hover_event: None,
+        }
+    }
+}
+
 impl TextComponent {
     pub fn text(text: impl Into<String>) -> Self {
         Self {
@@ -103,7 +123,7 @@ impl TextComponent {
     }
     pub fn translatable(translate: impl Into<String>, fallback: Option<impl Into<String>>, with: Option<Vec<TextComponent>>) -> Self {
         Self {
-            content: TextComponentType::Translatable { translate: translate.into(), fallback: fallback.and_then(|fallback| Some(fallback.into())), with },
+            content: TextComponentType::Translatable { translate: translate.into(), fallback: fallback.map(Into::into), with },
             .. Default::default()
         }
     }
@@ -113,6 +133,12 @@ impl TextComponent {
             .. Default::default()
         }
     }
+    pub fn selector(selector: impl Into<String>, separator: Option<TextComponent>) -> Self {
+        Self {
+            content: TextComponentType::EntityNames { selector: selector.into(), separator: separator.map(Box::new) },
+            .. Default::default()
+        }
+    }
     pub fn keybind(keybind: impl Into<String>) -> Self {
         Self {
             content: TextComponentType::Keybind { keybind: keybind.into() },
@@ -120,7 +146,7 @@ impl TextComponent {
         }
     }
 
-    pub fn append(mut self, other: TextComponent) -> Self { self.extra.as_mut().and_then(|extras| Some(extras.push(other))); self }
+    pub fn append(mut self, other: TextComponent) -> Self { self.extra.get_or_insert_with(Vec::new).push(other); self }
     pub fn with_color(mut self, color: TextColor) -> Self { self.color = Some(color); self }
     pub fn with_font(mut self, font: Identifier) -> Self { self.font = Some(font); self }
     pub fn bold(mut self) -> Self { self.bold = true; self.include |= 0x1; self }
@@ -136,4 +162,122 @@ impl TextComponent {
     pub fn on_hover(mut self, event: HoverEvent) -> Self { self.hover_event = Some(event); self }
     pub fn on_click(mut self, event: ClickEvent) -> Self { self.click_event = Some(event); self }
     pub fn shift_click(mut self, insertion: impl Into<String>) -> Self { self.insertion = Some(insertion.into()); self }
-}
\ No newline at end of file
+}
+
+impl TextComponent {
+    /// Converts this component to the JSON format that the status response and chat use, leaving out any style that wasn't set
+    pub fn to_json(&self) -> Value {
+        let mut json = Map::new();
+        match &self.content {
+            TextComponentType::Text { text } => {
+                json.insert("text".into(), text.as_str().into());
+            }
+            TextComponentType::Translatable { translate, fallback, with } => {
+                json.insert("translate".into(), translate.as_str().into());
+                if let Some(fallback) = fallback {
+                    json.insert("fallback".into(), fallback.as_str().into());
+                }
+                if let Some(with) = with {
+                    json.insert("with".into(), components_to_json(with));
+                }
+            }
+            TextComponentType::ScoreboardValue { score_holder, objective } => {
+                json.insert("score".into(), json!({ "name": score_holder, "objective": objective }));
+            }
+            TextComponentType::EntityNames { selector, separator } => {
+                json.insert("selector".into(), selector.as_str().into());
+                if let Some(separator) = separator {
+                    json.insert("separator".into(), separator.to_json());
+                }
+            }
+            TextComponentType::Keybind { keybind } => {
+                json.insert("keybind".into(), keybind.as_str().into());
+            }
+        }
+
+        if let Some(color) = &self.color {
+            json.insert("color".into(), color.to_string().into());
+        }
+        if let Some(font) = &self.font {
+            json.insert("font".into(), font.to_string().into());
+        }
+        for (bit, key, value) in [(0x1, "bold", self.bold), (0x2, "italic", self.italic), (0x4, "underlined", self.underlined), (0x8, "strikethrough", self.strikethrough), (0x10, "obfuscated", self.obfuscated)] {
+            if self.include & bit != 0 {
+                json.insert(key.into(), value.into());
+            }
+        }
+        if let Some(insertion) = &self.insertion {
+            json.insert("insertion".into(), insertion.as_str().into());
+        }
+        if let Some(event) = &self.click_event {
+            json.insert("clickEvent".into(), event.to_json());
+        }
+        if let Some(event) = &self.hover_event {
+            json.insert("hoverEvent".into(), event.to_json());
+        }
+        if let Some(extra) = &self.extra {
+            json.insert("extra".into(), components_to_json(extra));
+        }
+
+        Value::Object(json)
+    }
+}
+
+impl TextComponent {
+    /// Returns the text without any formatting, using the fallback or key of translatable components
+    pub fn plain_text(&self) -> String {
+        let mut text = match &self.content {
+            TextComponentType::Text { text } => text.clone(),
+            TextComponentType::Translatable { translate, fallback, .. } => fallback.clone().unwrap_or_else(|| translate.clone()),
+            TextComponentType::ScoreboardValue { .. } | TextComponentType::EntityNames { .. } => String::new(),
+            TextComponentType::Keybind { keybind } => keybind.clone(),
+        };
+        for extra in self.extra.iter().flatten() {
+            text.push_str(&extra.plain_text());
+        }
+
+        text
+    }
+}
+
+fn components_to_json(components: &[TextComponent]) -> Value {
+    Value::Array(components.iter().map(TextComponent::to_json).collect())
+}
+
+impl Display for TextComponent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_json())
+    }
+}
+
+impl ClickEvent {
+    fn to_json(&self) -> Value {
+        let (action, value) = match self {
+            ClickEvent::OpenURL(value) => ("open_url", value),
+            ClickEvent::OpenFile(value) => ("open_file", value),
+            ClickEvent::RunCommand(value) => ("run_command", value),
+            ClickEvent::SuggestCommand(value) => ("suggest_command", value),
+            ClickEvent::ChangePage(value) => ("change_page", value),
+            ClickEvent::CopyToClipboard(value) => ("copy_to_clipboard", value),
+        };
+
+        json!({ "action": action, "value": value })
+    }
+}
+
+impl HoverEvent {
+    fn to_json(&self) -> Value {
+        match self {
+            HoverEvent::ShowText { text } => json!({ "action": "show_text", "contents": text.to_json() }),
+            HoverEvent::ShowItem { id, count } => json!({ "action": "show_item", "contents": { "id": id.to_string(), "count": count } }),
+            HoverEvent::ShowEntity { name, ty, id } => {
+                let mut contents = json!({ "type": ty.to_string(), "id": id.to_string() });
+                if let Some(name) = name {
+                    contents["name"] = name.to_json();
+                }
+
+                json!({ "action": "show_entity", "contents": contents })
+            }
+        }
+    }
+}
@@ -0,0 +1,185 @@
+use bytes::buf::UninitSlice;
+use bytes::{BufMut, Bytes, BytesMut};
+use num_traits::ToBytes;
+use std::io;
+use std::io::Write;
+
+const SEGMENT_BITS: u8 = 0x7F;
+const CONTINUE_BIT: u8 = 0x80;
+
+/// A growable byte buffer that numbers and byte slices can be written onto the end of, the counterpart to `Queue`
+///
+/// Lengths that are only known after their data has been written can be filled in afterwards, either with `reserve` and `patch_be` for fixed size lengths, or with `write_length_prefixed` for VarInt lengths.
+///
+/// The buffer is a `BytesMut`, so finished packets can be split off with `split` and sent without copying them. It also implements `bytes::BufMut`, so anything that writes to a `BufMut` can write to it.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct ByteWriter {
+    data: BytesMut,
+}
+
+impl ByteWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self { data: BytesMut::with_capacity(capacity) }
+    }
+
+    /// Returns the number of bytes written so far
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+
+    /// Empties the buffer while keeping its memory, so that it can be reused for the next packet
+    pub fn clear(&mut self) {
+        self.data.clear();
+    }
+
+    /// Takes everything written so far without copying it, leaving the writer empty with its spare capacity
+    pub fn split(&mut self) -> Bytes {
+        self.data.split().freeze()
+    }
+
+    /// Writes a big endian number, which is how the Java protocol and NBT store numbers
+    pub fn write_be<T: ToBytes>(&mut self, value: T) {
+        self.data.extend_from_slice(value.to_be_bytes().as_ref());
+    }
+
+    /// Writes a little endian number, which is how Bedrock NBT stores numbers
+    pub fn write_le<T: ToBytes>(&mut self, value: T) {
+        self.data.extend_from_slice(value.to_le_bytes().as_ref());
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.data.extend_from_slice(bytes);
+    }
+
+    /// Writes an `i32` in the protocol's VarInt format, where negative numbers always take 5 bytes
+    pub fn write_var_int(&mut self, value: i32) {
+        let (bytes, len) = var_int_bytes(value as u32 as u64);
+        self.write_bytes(&bytes[..len]);
+    }
+
+    /// Writes an `i64` in the protocol's VarLong format, where negative numbers always take 10 bytes
+    pub fn write_var_long(&mut self, value: i64) {
+        let (bytes, len) = var_int_bytes(value as u64);
+        self.write_bytes(&bytes[..len]);
+    }
+
+    /// Writes `len` zeroed bytes to be filled in later with `patch_be` or `patch_le`, and returns where they start
+    pub fn reserve(&mut self, len: usize) -> usize {
+        let offset = self.data.len();
+        self.data.resize(offset + len, 0);
+        offset
+    }
+
+    /// Overwrites the bytes at `offset` with a big endian number, panicking if they haven't been written yet
+    pub fn patch_be<T: ToBytes>(&mut self, offset: usize, value: T) {
+        let bytes = value.to_be_bytes();
+        self.data[offset..offset + bytes.as_ref().len()].copy_from_slice(bytes.as_ref());
+    }
+
+    /// Overwrites the bytes at `offset` with a little endian number, panicking if they haven't been written yet
+    pub fn patch_le<T: ToBytes>(&mut self, offset: usize, value: T) {
+        let bytes = value.to_le_bytes();
+        self.data[offset..offset + bytes.as_ref().len()].copy_from_slice(bytes.as_ref());
+    }
+
+    /// Writes whatever `f` writes, prefixed with its length as a VarInt
+    ///
+    /// A single byte is reserved for the length up front, so the data only has to be moved when it is 128 bytes or longer.
+    pub fn write_length_prefixed(&mut self, f: impl FnOnce(&mut Self)) {
+        let start = self.reserve(1);
+        f(self);
+
+        let end = self.data.len();
+        let (bytes, prefix_len) = var_int_bytes((end - start - 1) as u32 as u64);
+        if prefix_len > 1 {
+            self.data.resize(end + prefix_len - 1, 0);
+            self.data.copy_within(start + 1..end, start + prefix_len);
+        }
+
+        self.data[start..start + prefix_len].copy_from_slice(&bytes[..prefix_len]);
+    }
+}
+
+/// Encodes the low bits of `value` 7 at a time, returning the bytes and how many of them were used
+fn var_int_bytes(mut value: u64) -> ([u8; 10], usize) {
+    let mut bytes = [0; 10];
+    let mut len = 0;
+    loop {
+        if value & !(SEGMENT_BITS as u64) == 0 {
+            bytes[len] = value as u8;
+            return (bytes, len + 1);
+        }
+
+        bytes[len] = (value as u8 & SEGMENT_BITS) | CONTINUE_BIT;
+        value >>= 7;
+        len += 1;
+    }
+}
+
+/// Lets anything that writes to an `io::Write`, like NBT, write straight into the buffer
+impl Write for ByteWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.data.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// SAFETY: every method is forwarded to the `BytesMut`, which upholds the trait's contract itself
+unsafe impl BufMut for ByteWriter {
+    fn remaining_mut(&self) -> usize {
+        self.data.remaining_mut()
+    }
+
+    unsafe fn advance_mut(&mut self, cnt: usize) {
+        // SAFETY: the caller has initialized the next `cnt` bytes of `chunk_mut`, which is the `BytesMut`'s own chunk
+        unsafe { self.data.advance_mut(cnt) }
+    }
+
+    fn chunk_mut(&mut self) -> &mut UninitSlice {
+        self.data.chunk_mut()
+    }
+}
+
+impl From<ByteWriter> for Vec<u8> {
+    fn from(value: ByteWriter) -> Self {
+        value.data.into()
+    }
+}
+
+impl From<ByteWriter> for Bytes {
+    fn from(value: ByteWriter) -> Self {
+        value.data.freeze()
+    }
+}
+
+impl From<BytesMut> for ByteWriter {
+    fn from(value: BytesMut) -> Self {
+        Self { data: value }
+    }
+}
+
+impl From<Vec<u8>> for ByteWriter {
+    fn from(value: Vec<u8>) -> Self {
+        Self { data: Bytes::from(value).into() }
+    }
+}
@@ -0,0 +1,38 @@
+import java.io.*;
+import java.util.zip.GZIPOutputStream;
+
+// Writes test_mutf8.nbt the way NbtIo.writeCompressed does: a DataOutputStream over a GZIPOutputStream
+//
+// Run with `javac WriteMutf8.java && java WriteMutf8 test_mutf8.nbt`. The non-ASCII strings are written as unicode escapes so that javac reads
+// them the same way whatever the platform encoding is, instead of needing `-encoding UTF-8` in non-UTF-8 locales.
+public class WriteMutf8 {
+    public static void main(String[] args) throws IOException {
+        try (DataOutputStream out = new DataOutputStream(new BufferedOutputStream(new GZIPOutputStream(new FileOutputStream(args[0]))))) {
+            out.writeByte(10);
+            out.writeUTF("mutf8");
+
+            string(out, "emoji", "Steve \ud83c\udfae\ud83c\udf4c");
+            string(out, "nul", "a\0b");
+            string(out, "\u540d\u524d", "\u30d0\u30ca\u30ca");
+
+            out.writeByte(3);
+            out.writeUTF("\ud83d\ude00 key");
+            out.writeInt(1);
+
+            out.writeByte(9);
+            out.writeUTF("names");
+            out.writeByte(8);
+            out.writeInt(2);
+            out.writeUTF("\ud83c\udf4c");
+            out.writeUTF("plain");
+
+            out.writeByte(0);
+        }
+    }
+
+    static void string(DataOutputStream out, String key, String value) throws IOException {
+        out.writeByte(8);
+        out.writeUTF(key);
+        out.writeUTF(value);
+    }
+}
@@ -3,6 +3,8 @@ use quote::quote;
 use syn::parse::{Parse, ParseStream};
 use syn::{parse_macro_input, DeriveInput, LitInt};
 
+mod nbt;
+
 struct PacketAttributeArgs {
     id: LitInt,
 }
@@ -36,24 +38,23 @@ pub fn packet(attr: TokenStream, input: TokenStream) -> TokenStream {
         #item
 
         impl crate::packets::Packet for #struct_name {
-            fn serialize(self) -> Vec<u8> {
+            fn serialize(self, writer: &mut data::writer::ByteWriter) {
                 use crate::data::PacketData;
 
-                let mut out = Vec::new();
                 #(
-                    out.extend(self.#field_names.serialize());
+                    self.#field_names.serialize(writer);
                 )*
-                out
             }
 
             fn deserialize(queue: &mut data::queue::Queue) -> Option<Self> {
                 use crate::data::PacketData;
 
-                Some(
+                // nothing is consumed if the packet hasn't fully arrived yet
+                queue.try_read(|queue| Some(
                     Self {
                         #(#field_names: <#field_types as PacketData>::deserialize(queue)?,)*
                     }
-                )
+                ))
             }
 
             fn id(&self) -> u8 {
@@ -63,4 +64,12 @@ pub fn packet(attr: TokenStream, input: TokenStream) -> TokenStream {
     };
 
     out.into()
-}
\ No newline at end of file
+}
+
+/// Implements `NbtCompound` and `NBTValue` from `data::nbt::convert` for a struct, see the `nbt` module for its attributes
+#[proc_macro_derive(NbtCompound, attributes(nbt))]
+pub fn nbt_compound(input: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(input as DeriveInput);
+
+    nbt::derive(item).unwrap_or_else(syn::Error::into_compile_error).into()
+}
@@ -0,0 +1,180 @@
+//! `#[derive(NbtCompound)]`, which implements `data::nbt::convert::NbtCompound` and `NBTValue` for a struct with named fields
+//!
+//! The struct can be annotated with `#[nbt(rename_all = "...")]` to rename every field to `PascalCase`, `camelCase` or `snake_case`. Fields can be annotated with:
+//! - `#[nbt(rename = "...")]` to use a different key
+//! - `#[nbt(default)]` or `#[nbt(default = "path::to::function")]` to use a default when the key is missing
+//! - `#[nbt(flatten)]` to store a struct that implements `NbtCompound` in the same compound as its parent
+//! - `#[nbt(byte_array)]`, `#[nbt(int_array)]` or `#[nbt(long_array)]` to store a `Vec<i8>`, `Vec<i32>` or `Vec<i64>` as a typed array instead of a `List`
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Fields, Ident, LitStr, Path};
+
+#[derive(Clone, Copy)]
+enum Casing {
+    Pascal,
+    Camel,
+    Snake,
+}
+
+impl Casing {
+    fn parse(name: &LitStr) -> syn::Result<Self> {
+        match name.value().as_str() {
+            "PascalCase" => Ok(Self::Pascal),
+            "camelCase" => Ok(Self::Camel),
+            "snake_case" => Ok(Self::Snake),
+            _ => Err(syn::Error::new(name.span(), "expected \"PascalCase\", \"camelCase\" or \"snake_case\"")),
+        }
+    }
+
+    /// Renames a snake case field name
+    fn apply(self, field: &str) -> String {
+        let capitalized = field.split('_').filter(|word| !word.is_empty()).map(|word| {
+            let mut chars = word.chars();
+            chars.next().map(|first| first.to_ascii_uppercase().to_string() + chars.as_str()).unwrap_or_default()
+        });
+
+        match self {
+            Self::Pascal => capitalized.collect(),
+            Self::Camel => {
+                let pascal = capitalized.collect::<String>();
+                let mut chars = pascal.chars();
+                chars.next().map(|first| first.to_ascii_lowercase().to_string() + chars.as_str()).unwrap_or_default()
+            }
+            Self::Snake => field.to_string(),
+        }
+    }
+}
+
+enum FieldDefault {
+    None,
+    Default,
+    Function(Path),
+}
+
+struct FieldAttributes {
+    rename: Option<String>,
+    default: FieldDefault,
+    flatten: bool,
+    /// The `data::nbt::convert` module that converts the field to a typed array
+    array: Option<Ident>,
+}
+
+impl FieldAttributes {
+    fn parse(field: &syn::Field) -> syn::Result<Self> {
+        let mut attributes = Self { rename: None, default: FieldDefault::None, flatten: false, array: None };
+        for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("nbt")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    attributes.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("default") {
+                    attributes.default = match meta.value() {
+                        Ok(value) => FieldDefault::Function(value.parse::<LitStr>()?.parse()?),
+                        Err(_) => FieldDefault::Default,
+                    };
+                } else if meta.path.is_ident("flatten") {
+                    attributes.flatten = true;
+                } else if let Some(array) = ["byte_array", "int_array", "long_array"].into_iter().find(|array| meta.path.is_ident(array)) {
+                    if attributes.array.is_some() {
+                        return Err(meta.error("a field can only be one type of array"));
+                    }
+                    attributes.array = Some(Ident::new(array, meta.path.get_ident().expect("matched an ident").span()));
+                } else {
+                    return Err(meta.error("expected `rename`, `default`, `flatten`, `byte_array`, `int_array` or `long_array`"));
+                }
+
+                Ok(())
+            })?;
+        }
+
+        if attributes.flatten && (attributes.rename.is_some() || !matches!(attributes.default, FieldDefault::None) || attributes.array.is_some()) {
+            return Err(syn::Error::new_spanned(field, "flattened fields can't be renamed, have a default or be an array"));
+        }
+
+        Ok(attributes)
+    }
+}
+
+pub fn derive(item: DeriveInput) -> syn::Result<TokenStream> {
+    let mut casing = Casing::Snake;
+    for attr in item.attrs.iter().filter(|attr| attr.path().is_ident("nbt")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename_all") {
+                casing = Casing::parse(&meta.value()?.parse()?)?;
+                Ok(())
+            } else {
+                Err(meta.error("expected `rename_all`"))
+            }
+        })?;
+    }
+
+    let fields = match &item.data {
+        Data::Struct(s) => match &s.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(syn::Error::new_spanned(&item.ident, "NbtCompound can only be derived for structs with named fields")),
+        },
+        _ => return Err(syn::Error::new_spanned(&item.ident, "NbtCompound can only be derived for structs")),
+    };
+
+    let mut writes = Vec::new();
+    let mut reads = Vec::new();
+    for field in fields {
+        let attributes = FieldAttributes::parse(field)?;
+        let ident = field.ident.as_ref().expect("named fields have names");
+        let ty = &field.ty;
+
+        if attributes.flatten {
+            writes.push(quote! { data::nbt::convert::NbtCompound::write_compound(&self.#ident, compound); });
+            reads.push(quote! { #ident: <#ty as data::nbt::convert::NbtCompound>::from_compound(compound)?, });
+            continue;
+        }
+
+        let name = ident.to_string();
+        let key = attributes.rename.unwrap_or_else(|| casing.apply(name.trim_start_matches("r#")));
+        let read = match &attributes.array {
+            Some(array) => {
+                writes.push(quote! { data::nbt::convert::#array::write_field(&self.#ident, compound, #key); });
+                quote! { data::nbt::convert::#array::read_field(compound, #key)? }
+            }
+            None => {
+                writes.push(quote! { data::nbt::convert::NBTValue::write_field(&self.#ident, compound, #key); });
+                quote! { <#ty as data::nbt::convert::NBTValue>::read_field(compound, #key)? }
+            }
+        };
+        let default = match attributes.default {
+            FieldDefault::None => None,
+            FieldDefault::Default => Some(quote! { ::core::default::Default::default() }),
+            FieldDefault::Function(path) => Some(quote! { #path() }),
+        };
+        reads.push(match default {
+            Some(default) => quote! { #ident: if compound.contains_key(#key) { #read } else { #default }, },
+            None => quote! { #ident: #read, },
+        });
+    }
+
+    let struct_name = &item.ident;
+    let (impl_generics, ty_generics, where_clause) = item.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics data::nbt::convert::NbtCompound for #struct_name #ty_generics #where_clause {
+            fn write_compound(&self, compound: &mut data::nbt::compound::NBTCompound) {
+                #(#writes)*
+            }
+
+            fn from_compound(compound: &data::nbt::compound::NBTCompound) -> Result<Self, data::nbt::convert::NBTConvertError> {
+                Ok(Self {
+                    #(#reads)*
+                })
+            }
+        }
+
+        impl #impl_generics data::nbt::convert::NBTValue for #struct_name #ty_generics #where_clause {
+            fn to_tag(&self) -> data::nbt::tag::NBTTag {
+                data::nbt::tag::NBTTag::Compound(data::nbt::convert::NbtCompound::to_compound(self))
+            }
+
+            fn from_tag(tag: &data::nbt::tag::NBTTag) -> Result<Self, data::nbt::convert::NBTConvertError> {
+                <Self as data::nbt::convert::NbtCompound>::from_compound_tag(tag)
+            }
+        }
+    })
+}
@@ -3,23 +3,26 @@ mod variable;
 mod optional;
 
 use uuid::Uuid;
+use data::auth::ProfileProperty;
+use data::nbt::tag::{NBTDecodeOptions, NBTTagSerializeError};
 use data::nbt::NBT;
 use data::queue::Queue;
+use data::writer::ByteWriter;
 use data::resource::Identifier;
 use data::transform::{Angle, Position};
-pub use primitive::*;
 pub use variable::*;
 
 pub trait PacketData {
-    fn serialize(self) -> Vec<u8>;
+    /// Writes this value onto the end of `writer`
+    fn serialize(self, writer: &mut ByteWriter);
     fn deserialize(queue: &mut Queue) -> Option<Self> where Self: Sized;
 }
 
 // TODO: text components
 
 impl PacketData for Identifier {
-    fn serialize(self) -> Vec<u8> {
-        self.to_string().serialize()
+    fn serialize(self, writer: &mut ByteWriter) {
+        self.to_string().serialize(writer);
     }
 
     fn deserialize(queue: &mut Queue) -> Option<Self>
@@ -31,8 +34,8 @@ impl PacketData for Identifier {
 }
 
 impl PacketData for Position {
-    fn serialize(self) -> Vec<u8> {
-        (((self.x as i64 & 0x3FFFFFF) << 38) | ((self.z as i64 & 0x3FFFFFF) << 12) | ((self.y as i64 & 0xFFF))).serialize()
+    fn serialize(self, writer: &mut ByteWriter) {
+        (((self.x as i64 & 0x3FFFFFF) << 38) | ((self.z as i64 & 0x3FFFFFF) << 12) | (self.y as i64 & 0xFFF)).serialize(writer);
     }
 
     fn deserialize(queue: &mut Queue) -> Option<Self>
@@ -48,23 +51,57 @@ impl PacketData for Position {
     }
 }
 
-impl PacketData for NBT {
-    fn serialize(mut self) -> Vec<u8> {
-        self.as_network();
-        self.as_bytes()
+/// NBT for a packet, which is encoded as soon as it is created, so that NBT that can't be written, like a list with mixed tags, is caught while building the packet instead of while sending it
+#[derive(Debug, PartialEq)]
+pub struct NetworkNBT {
+    nbt: NBT,
+    bytes: Vec<u8>,
+}
+
+impl NetworkNBT {
+    /// Encodes `nbt` without its root name, the way packets send it
+    pub fn new(mut nbt: NBT) -> Result<Self, NBTTagSerializeError> {
+        nbt.as_network();
+        let mut bytes = Vec::new();
+        nbt.write_to(&mut bytes)?;
+
+        Ok(Self { nbt, bytes })
+    }
+
+    pub fn nbt(&self) -> &NBT {
+        &self.nbt
+    }
+
+    pub fn into_nbt(self) -> NBT {
+        self.nbt
+    }
+}
+
+impl TryFrom<NBT> for NetworkNBT {
+    type Error = NBTTagSerializeError;
+
+    fn try_from(nbt: NBT) -> Result<Self, Self::Error> {
+        Self::new(nbt)
+    }
+}
+
+impl PacketData for NetworkNBT {
+    fn serialize(self, writer: &mut ByteWriter) {
+        writer.write_bytes(&self.bytes);
     }
 
     fn deserialize(queue: &mut Queue) -> Option<Self>
     where
         Self: Sized,
     {
-        NBT::from_bytes_network(queue)
+        // NBT in packets comes from clients, so it is always read within the strict network limits
+        Self::new(NBT::from_bytes_network_with_options(queue, &NBTDecodeOptions::NETWORK).ok()?).ok()
     }
 }
 
 impl PacketData for Angle {
-    fn serialize(self) -> Vec<u8> {
-        self.0.serialize()
+    fn serialize(self, writer: &mut ByteWriter) {
+        self.0.serialize(writer);
     }
 
     fn deserialize(queue: &mut Queue) -> Option<Self>
@@ -76,14 +113,33 @@ impl PacketData for Angle {
 }
 
 impl PacketData for Uuid {
-    fn serialize(self) -> Vec<u8> {
-        self.as_u128().to_be_bytes().to_vec()
+    fn serialize(self, writer: &mut ByteWriter) {
+        writer.write_be(self.as_u128());
+    }
+
+    fn deserialize(queue: &mut Queue) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        Some(Uuid::from_u128(queue.read_be::<u128>()?))
+    }
+}
+
+impl PacketData for ProfileProperty {
+    fn serialize(self, writer: &mut ByteWriter) {
+        self.name.serialize(writer);
+        self.value.serialize(writer);
+        self.signature.serialize(writer);
     }
 
     fn deserialize(queue: &mut Queue) -> Option<Self>
     where
         Self: Sized,
     {
-        Some(Uuid::from_u128(queue.pop::<u128>()?))
+        Some(Self {
+            name: String::deserialize(queue)?,
+            value: String::deserialize(queue)?,
+            signature: Option::<String>::deserialize(queue)?,
+        })
     }
 }
\ No newline at end of file
@@ -1,15 +1,14 @@
 use data::queue::Queue;
+use data::writer::ByteWriter;
 use crate::data::PacketData;
 
 /// This Option<T> is prefixed with a boolean that tells if the Option is present or not. The majority of Options in the packets are not like this, and instead must be inferred from previous data.
 impl<T: PacketData> PacketData for Option<T> {
-    fn serialize(self) -> Vec<u8> {
-        let mut out = self.is_some().serialize();
+    fn serialize(self, writer: &mut ByteWriter) {
+        self.is_some().serialize(writer);
         if let Some(data) = self {
-            out.extend(data.serialize());
+            data.serialize(writer);
         }
-        
-        out
     }
 
     fn deserialize(queue: &mut Queue) -> Option<Self>
@@ -18,9 +17,9 @@ impl<T: PacketData> PacketData for Option<T> {
     {
         let present = bool::deserialize(queue)?;
         if present {
-            Some(T::deserialize(queue))
+            Some(Some(T::deserialize(queue)?))
         } else {
-            None
+            Some(None)
         }
     }
 }
\ No newline at end of file
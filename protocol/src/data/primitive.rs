@@ -1,159 +1,158 @@
-use uuid::NonNilUuid;
 use data::queue::Queue;
+use data::writer::ByteWriter;
 use crate::data::PacketData;
 use crate::data::variable::VarInt;
 
 impl PacketData for bool {
-    fn serialize(self) -> Vec<u8> {
-        vec![self as u8]
+    fn serialize(self, writer: &mut ByteWriter) {
+        writer.write_be(self as u8);
     }
 
     fn deserialize(queue: &mut Queue) -> Option<Self>
     where
         Self: Sized,
     {
-        Some(queue.pop::<u8>()? != 0)
+        Some(queue.read_be::<u8>()? != 0)
     }
 }
 impl PacketData for i8 {
-    fn serialize(self) -> Vec<u8> {
-        vec![self as u8]
+    fn serialize(self, writer: &mut ByteWriter) {
+        writer.write_be(self as u8);
     }
 
     fn deserialize(queue: &mut Queue) -> Option<Self>
     where
         Self: Sized,
     {
-        queue.pop::<i8>()
+        queue.read_be::<i8>()
     }
 }
 impl PacketData for u8 {
-    fn serialize(self) -> Vec<u8> {
-        vec![self]
+    fn serialize(self, writer: &mut ByteWriter) {
+        writer.write_be(self);
     }
 
     fn deserialize(queue: &mut Queue) -> Option<Self>
     where
         Self: Sized,
     {
-        queue.pop()
+        queue.read_be()
     }
 }
 impl PacketData for i16 {
-    fn serialize(self) -> Vec<u8> {
-        self.to_be_bytes().to_vec()
+    fn serialize(self, writer: &mut ByteWriter) {
+        writer.write_be(self);
     }
 
     fn deserialize(queue: &mut Queue) -> Option<Self>
     where
         Self: Sized,
     {
-        queue.pop()
+        queue.read_be()
     }
 }
 impl PacketData for u16 {
-    fn serialize(self) -> Vec<u8> {
-        self.to_be_bytes().to_vec()
+    fn serialize(self, writer: &mut ByteWriter) {
+        writer.write_be(self);
     }
 
     fn deserialize(queue: &mut Queue) -> Option<Self>
     where
         Self: Sized,
     {
-        queue.pop()
+        queue.read_be()
     }
 }
 impl PacketData for i32 {
-    fn serialize(self) -> Vec<u8> {
-        self.to_be_bytes().to_vec()
+    fn serialize(self, writer: &mut ByteWriter) {
+        writer.write_be(self);
     }
 
     fn deserialize(queue: &mut Queue) -> Option<Self>
     where
         Self: Sized,
     {
-        queue.pop()
+        queue.read_be()
     }
 }
 impl PacketData for i64 {
-    fn serialize(self) -> Vec<u8> {
-        self.to_be_bytes().to_vec()
+    fn serialize(self, writer: &mut ByteWriter) {
+        writer.write_be(self);
     }
 
     fn deserialize(queue: &mut Queue) -> Option<Self>
     where
         Self: Sized,
     {
-        queue.pop()
+        queue.read_be()
     }
 }
 impl PacketData for f32 {
-    fn serialize(self) -> Vec<u8> {
-        self.to_be_bytes().to_vec()
+    fn serialize(self, writer: &mut ByteWriter) {
+        writer.write_be(self);
     }
 
     fn deserialize(queue: &mut Queue) -> Option<Self>
     where
         Self: Sized,
     {
-        queue.pop()
+        queue.read_be()
     }
 }
 impl PacketData for f64 {
-    fn serialize(self) -> Vec<u8> {
-        self.to_be_bytes().to_vec()
+    fn serialize(self, writer: &mut ByteWriter) {
+        writer.write_be(self);
     }
 
     fn deserialize(queue: &mut Queue) -> Option<Self>
     where
         Self: Sized,
     {
-        queue.pop()
+        queue.read_be()
     }
 }
 
 impl PacketData for String {
-    fn serialize(self) -> Vec<u8> {
-        let mut out = VarInt(self.len() as i32).serialize();
-
-        out.extend(self.as_bytes());
-
-        out
+    fn serialize(self, writer: &mut ByteWriter) {
+        VarInt(self.len() as i32).serialize(writer);
+        writer.write_bytes(self.as_bytes());
     }
 
     fn deserialize(queue: &mut Queue) -> Option<Self>
     where
         Self: Sized,
     {
-        let len = VarInt::deserialize(queue)?;
-        let mut bytes = Vec::with_capacity(len.0 as usize);
-        for _ in 0..len.0 {
-            bytes.push(queue.pop::<u8>()?);
-        }
+        let len = checked_len(queue)?;
 
-        String::from_utf8(bytes).ok()
+        String::from_utf8(queue.read_bytes(len)?.to_vec()).ok()
     }
 }
 
 impl<T: PacketData> PacketData for Vec<T> {
-    fn serialize(self) -> Vec<u8> {
-        let mut out = VarInt(self.len() as _).serialize();
+    fn serialize(self, writer: &mut ByteWriter) {
+        VarInt(self.len() as _).serialize(writer);
         for value in self {
-            out.extend(value.serialize());
+            value.serialize(writer);
         }
-        out
     }
 
     fn deserialize(queue: &mut Queue) -> Option<Self>
     where
         Self: Sized,
     {
-        let len = VarInt::deserialize(queue)?;
-        let mut out = Vec::with_capacity(len.0 as _);
-        for _ in 0..len.0 {
+        let len = checked_len(queue)?;
+        let mut out = Vec::with_capacity(len);
+        for _ in 0..len {
             out.push(T::deserialize(queue)?);
         }
 
         Some(out)
     }
-}
\ No newline at end of file
+}
+
+/// Reads a length prefix sent by the client, rejecting it if it is negative or longer than what is left, since every element takes at least a byte
+fn checked_len(queue: &mut Queue) -> Option<usize> {
+    let len = usize::try_from(VarInt::deserialize(queue)?.0).ok()?;
+
+    (len <= queue.bytes_left()).then_some(len)
+}
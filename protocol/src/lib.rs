@@ -1,5 +1,5 @@
-mod data;
-mod packets;
+pub mod data;
+pub mod packets;
 
 pub fn add(left: u64, right: u64) -> u64 {
     left + right
@@ -8,10 +8,316 @@ pub fn add(left: u64, right: u64) -> u64 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::data::{NetworkNBT, PacketData, VarInt};
+    use crate::packets::clientbound::login::{EncryptionRequest, LoginSuccess};
+    use crate::packets::clientbound::status::{PongResponse, StatusResponse};
+    use crate::packets::serverbound::{deserialize_serverbound_packets, finish_logins};
+    use crate::packets::serverbound::handshake::Handshake;
+    use crate::packets::serverbound::login::{EncryptionResponse, LoginAcknowledged, LoginStart};
+    use crate::packets::serverbound::status::{PingRequest, StatusRequest};
+    use crate::packets::Packet;
+    use ::data::auth::{server_hash, GameProfile, HttpBackend, HttpResponse, PendingAuth, SessionService};
+    use ::data::encryption::{ServerKey, StreamDecryptor, StreamEncryptor};
+    use ::data::frame::{Frame, FrameDecoder, FrameEncoder};
+    use ::data::nbt::tag::NBTTag;
+    use ::data::nbt::NBT;
+    use ::data::network::{Connection, ConnectionState, Disconnect, PendingLogin, ReadBytes, WriteBytes, PROTOCOL_VERSION};
+    use ::data::queue::Queue;
+    use ::data::status::ServerStatus;
+    use ::data::writer::ByteWriter;
+    use bytes::BytesMut;
+    use rsa::pkcs8::DecodePublicKey;
+    use rsa::{Pkcs1v15Encrypt, RsaPublicKey};
+    use shipyard::{EntityId, Get, View, ViewMut, World};
+    use std::sync::mpsc::{channel, Receiver};
+    use std::sync::Mutex;
+    use std::time::{Duration, Instant};
+    use uuid::Uuid;
 
     #[test]
     fn it_works() {
         let result = add(2, 2);
         assert_eq!(result, 4);
     }
+
+    /// Frames `packet` the way a client sends it before compression and encryption
+    fn framed(packet: impl Packet, writer: &mut ByteWriter) {
+        let id = packet.id() as i32;
+        let mut body = ByteWriter::new();
+        packet.serialize(&mut body);
+        FrameEncoder::new().encode(&Frame::new(id, body.split()), writer).unwrap();
+    }
+
+    fn handshake(next_state: i32) -> Handshake {
+        Handshake {
+            protocol_version: VarInt(PROTOCOL_VERSION),
+            server_address: "localhost".to_string(),
+            server_port: 25565,
+            next_state: VarInt(next_state),
+        }
+    }
+
+    /// The shared secret that clients log in with
+    const SECRET: [u8; 16] = [7; 16];
+
+    /// Stands in for the session server, which has only seen Steve join, and which doesn't answer until `gate` is opened if there is one
+    struct StandIn {
+        server_hash: String,
+        gate: Option<Mutex<Receiver<()>>>,
+    }
+
+    impl HttpBackend for StandIn {
+        fn get(&self, url: &str) -> Result<HttpResponse, String> {
+            if let Some(gate) = &self.gate {
+                gate.lock().unwrap().recv().map_err(|error| error.to_string())?;
+            }
+            if url != format!("http://localhost/session/minecraft/hasJoined?username=Steve&serverId={}", self.server_hash) {
+                return Ok(HttpResponse { status: 204, body: Vec::new() });
+            }
+
+            Ok(HttpResponse { status: 200, body: br#"{"id":"069a79f444e94726a5befca90e38aaf5","name":"Steve"}"#.to_vec() })
+        }
+    }
+
+    /// Creates a world with a connection that has just been accepted
+    fn connect() -> (World, EntityId) {
+        connect_with_gate(None)
+    }
+
+    fn connect_with_gate(gate: Option<Receiver<()>>) -> (World, EntityId) {
+        let mut world = World::new();
+        let server_key = ServerKey::generate().unwrap();
+        let server_hash = server_hash("", &SECRET, server_key.public_key_der());
+        world.add_unique(ServerStatus::default());
+        world.add_unique(server_key);
+        world.add_unique(SessionService::new(StandIn { server_hash, gate: gate.map(Mutex::new) }, "http://localhost"));
+        let entity = world.add_entity((ConnectionState::Handshake, Connection::default(), WriteBytes::default()));
+
+        (world, entity)
+    }
+
+    /// Takes the bytes that have been written to the connection
+    fn written(world: &World, entity: EntityId) -> BytesMut {
+        world.run(|mut vm_write_bytes: ViewMut<WriteBytes>| BytesMut::from((&mut vm_write_bytes).get(entity).unwrap().0.split()))
+    }
+
+    /// Sends `bytes` to the connection and returns the bytes that it answers with
+    fn receive_bytes(world: &mut World, entity: EntityId, bytes: &[u8]) -> BytesMut {
+        world.add_component(entity, ReadBytes(BytesMut::from(bytes)));
+        world.run(deserialize_serverbound_packets::<StandIn>);
+
+        written(world, entity)
+    }
+
+    /// Runs `finish_logins` until the session server has answered for the connection
+    fn finish_login(world: &World, entity: EntityId) {
+        let start = Instant::now();
+        while world.run(|v_pending_auth: View<PendingAuth>| v_pending_auth.contains(entity)) {
+            assert!(start.elapsed() < Duration::from_secs(10), "the session server didn't answer");
+            world.run(finish_logins);
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    /// Sends `bytes` to the connection and returns the frames that it answers with, before encryption is enabled
+    fn receive(world: &mut World, entity: EntityId, bytes: &[u8]) -> Vec<Frame> {
+        let mut decoder = FrameDecoder::new();
+        decoder.push(receive_bytes(world, entity, bytes));
+        std::iter::from_fn(|| decoder.decode().unwrap()).collect()
+    }
+
+    fn disconnected(world: &World, entity: EntityId) -> bool {
+        world.run(|v_disconnect: View<Disconnect>| v_disconnect.contains(entity))
+    }
+
+    #[test]
+    fn test_string_length() {
+        for len in [-1, 1_000] {
+            let mut writer = ByteWriter::new();
+            VarInt(PROTOCOL_VERSION).serialize(&mut writer);
+            VarInt(len).serialize(&mut writer);
+            writer.write_bytes(b"localhost");
+            writer.write_be(25565u16);
+            VarInt(1).serialize(&mut writer);
+
+            assert!(Handshake::deserialize(&mut Queue::from(writer.split())).is_none(), "length {len}");
+        }
+    }
+
+    #[test]
+    fn test_network_nbt() {
+        let mixed = NBT::new("", NBTTag::List(vec![NBTTag::Int(1), NBTTag::Byte(2)]));
+        assert!(NetworkNBT::new(mixed).is_err());
+
+        let compound = NBTTag::Compound(vec![("text".to_string(), NBTTag::String("hi".to_string()))].into());
+        let nbt = NetworkNBT::new(NBT::new("ignored", compound.clone())).unwrap();
+        assert_eq!(nbt.nbt(), &NBT::new_network(compound.clone()));
+        let mut writer = ByteWriter::new();
+        nbt.serialize(&mut writer);
+        assert_eq!(writer.as_slice(), [10, 8, 0, 4, b't', b'e', b'x', b't', 0, 2, b'h', b'i', 0]);
+        assert_eq!(NetworkNBT::deserialize(&mut Queue::from(writer.split())).map(NetworkNBT::into_nbt), Some(NBT::new_network(compound)));
+    }
+
+    #[test]
+    fn test_status_flow() {
+        let (mut world, entity) = connect();
+
+        let mut writer = ByteWriter::new();
+        framed(handshake(1), &mut writer);
+        framed(StatusRequest {}, &mut writer);
+        framed(PingRequest { payload: 0x0123_4567_89ab_cdef }, &mut writer);
+        let frames = receive(&mut world, entity, writer.as_slice());
+
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].id, 0x00);
+        let status = StatusResponse::deserialize(&mut frames[0].queue()).unwrap();
+        assert_eq!(status.json, ServerStatus::default().to_json().to_string());
+        assert_eq!(frames[1].id, 0x01);
+        assert_eq!(PongResponse::deserialize(&mut frames[1].queue()).unwrap().payload, 0x0123_4567_89ab_cdef);
+        assert!(!disconnected(&world, entity));
+    }
+
+    #[test]
+    fn test_invalid_frame_disconnects() {
+        let (mut world, entity) = connect();
+
+        assert!(receive(&mut world, entity, &[0x80, 0x80, 0x80, 0x00]).is_empty());
+        assert!(disconnected(&world, entity));
+    }
+
+    #[test]
+    fn test_unexpected_packets_disconnect() {
+        let mut malformed = ByteWriter::new();
+        FrameEncoder::new().encode(&Frame::new(0x00, vec![0x80]), &mut malformed).unwrap();
+        let mut invalid_state = ByteWriter::new();
+        framed(handshake(4), &mut invalid_state);
+        let mut unknown_id = ByteWriter::new();
+        framed(handshake(1), &mut unknown_id);
+        FrameEncoder::new().encode(&Frame::new(0x05, vec![]), &mut unknown_id).unwrap();
+        let mut malformed_ping = ByteWriter::new();
+        framed(handshake(1), &mut malformed_ping);
+        FrameEncoder::new().encode(&Frame::new(0x01, vec![0; 4]), &mut malformed_ping).unwrap();
+
+        for bytes in [malformed, invalid_state, unknown_id, malformed_ping] {
+            let (mut world, entity) = connect();
+            assert!(receive(&mut world, entity, bytes.as_slice()).is_empty());
+            assert!(disconnected(&world, entity));
+        }
+    }
+
+    #[test]
+    fn test_transfer_logs_in() {
+        let (mut world, entity) = connect();
+        let mut writer = ByteWriter::new();
+        framed(handshake(3), &mut writer);
+        framed(LoginStart { name: "Steve".to_string(), uuid: Uuid::nil() }, &mut writer);
+
+        assert_eq!(receive(&mut world, entity, writer.as_slice())[0].id, 0x01);
+        assert!(!disconnected(&world, entity));
+    }
+
+    /// Logs in as `name` until Encryption Request, and returns the client's answer to it
+    fn start_login(world: &mut World, entity: EntityId, name: &str) -> EncryptionResponse {
+        let mut writer = ByteWriter::new();
+        framed(handshake(2), &mut writer);
+        framed(LoginStart { name: name.to_string(), uuid: Uuid::nil() }, &mut writer);
+        let frames = receive(world, entity, writer.as_slice());
+
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].id, 0x01);
+        let request = EncryptionRequest::deserialize(&mut frames[0].queue()).unwrap();
+        assert_eq!(request.server_id, "");
+        assert!(request.should_authenticate);
+        world.run(|v_pending_login: View<PendingLogin>| assert_eq!(v_pending_login.get(entity).unwrap().name, name));
+
+        let public_key = RsaPublicKey::from_public_key_der(&request.public_key).unwrap();
+        let encrypt = |data: &[u8]| public_key.encrypt(&mut rand::thread_rng(), Pkcs1v15Encrypt, data).unwrap();
+        EncryptionResponse {
+            shared_secret: encrypt(&SECRET),
+            verify_token: encrypt(&request.verify_token),
+        }
+    }
+
+    #[test]
+    fn test_login() {
+        let (gate, closed_gate) = channel();
+        let (mut world, entity) = connect_with_gate(Some(closed_gate));
+        let response = start_login(&mut world, entity, "Steve");
+
+        // the tick goes on while the session server hasn't answered
+        let mut writer = ByteWriter::new();
+        framed(response, &mut writer);
+        assert!(receive(&mut world, entity, writer.as_slice()).is_empty());
+        world.run(finish_logins);
+        assert!(!disconnected(&world, entity));
+        world.run(|v_pending_login: View<PendingLogin>| assert!(!v_pending_login.contains(entity)));
+        world.run(|v_game_profile: View<GameProfile>| assert!(!v_game_profile.contains(entity)));
+
+        gate.send(()).unwrap();
+        finish_login(&world, entity);
+        assert!(!disconnected(&world, entity));
+        world.run(|v_game_profile: View<GameProfile>| {
+            let profile = v_game_profile.get(entity).unwrap();
+            assert_eq!(profile.name, "Steve");
+            assert_eq!(profile.id, Uuid::from_u128(0x069a79f444e94726a5befca90e38aaf5));
+        });
+
+        // everything that the server sends from now on is encrypted with the shared secret, starting with Login Success
+        let mut bytes = written(&world, entity);
+        StreamDecryptor::new(&SECRET).decrypt(&mut bytes);
+        let mut decoder = FrameDecoder::new();
+        decoder.push(bytes);
+        let frame = decoder.decode().unwrap().unwrap();
+        assert_eq!(frame.id, 0x02);
+        let login_success = LoginSuccess::deserialize(&mut frame.queue()).unwrap();
+        assert_eq!(login_success.uuid, Uuid::from_u128(0x069a79f444e94726a5befca90e38aaf5));
+        assert_eq!(login_success.username, "Steve");
+        assert!(login_success.properties.is_empty());
+
+        // and so is everything that the client sends
+        let mut writer = ByteWriter::new();
+        framed(LoginAcknowledged {}, &mut writer);
+        StreamEncryptor::new(&SECRET).encrypt(writer.as_mut_slice());
+        receive(&mut world, entity, writer.as_slice());
+        assert!(!disconnected(&world, entity));
+        world.run(|v_connection_state: View<ConnectionState>| assert!(matches!(v_connection_state.get(entity).unwrap(), ConnectionState::Configure)));
+    }
+
+    #[test]
+    fn test_login_encryption_failures() {
+        // the verify token has to be the one from Encryption Request
+        let (mut world, entity) = connect();
+        let mut response = start_login(&mut world, entity, "Steve");
+        response.verify_token = response.shared_secret.clone();
+        let mut writer = ByteWriter::new();
+        framed(response, &mut writer);
+        receive(&mut world, entity, writer.as_slice());
+        assert!(disconnected(&world, entity));
+
+        // Encryption Response can't be sent before Login Start
+        let (mut world, entity) = connect();
+        let mut writer = ByteWriter::new();
+        framed(handshake(2), &mut writer);
+        framed(EncryptionResponse { shared_secret: vec![0; 128], verify_token: vec![0; 128] }, &mut writer);
+        receive(&mut world, entity, writer.as_slice());
+        assert!(disconnected(&world, entity));
+
+        // the session server has to have seen the player join
+        let (mut world, entity) = connect();
+        let response = start_login(&mut world, entity, "Cracked");
+        let mut writer = ByteWriter::new();
+        framed(response, &mut writer);
+        receive(&mut world, entity, writer.as_slice());
+        finish_login(&world, entity);
+        assert!(disconnected(&world, entity));
+        world.run(|v_game_profile: View<GameProfile>| assert!(!v_game_profile.contains(entity)));
+
+        // Login Acknowledged can't be sent before Login Success
+        let (mut world, entity) = connect();
+        let mut writer = ByteWriter::new();
+        framed(handshake(2), &mut writer);
+        framed(LoginAcknowledged {}, &mut writer);
+        receive(&mut world, entity, writer.as_slice());
+        assert!(disconnected(&world, entity));
+    }
 }
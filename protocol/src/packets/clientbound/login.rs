@@ -0,0 +1,27 @@
+use shipyard::Component;
+use data::auth::ProfileProperty;
+use protocol_proc::packet;
+use uuid::Uuid;
+
+/// Asks the client to encrypt the connection, see `data::encryption`
+#[derive(Component)]
+#[packet(0x01)]
+pub struct EncryptionRequest {
+    /// Always empty since 1.7
+    pub server_id: String,
+    /// The server's public key, see `data::encryption::ServerKey::public_key_der`
+    pub public_key: Vec<u8>,
+    pub verify_token: Vec<u8>,
+    /// Whether the client should tell the session server that it is joining, which online mode servers check
+    pub should_authenticate: bool,
+}
+
+/// Tells the client that it has logged in, with the profile that the session server sent
+#[derive(Component)]
+#[packet(0x02)]
+pub struct LoginSuccess {
+    pub uuid: Uuid,
+    pub username: String,
+    /// The signed skin and cape, see `data::auth::GameProfile::textures`
+    pub properties: Vec<ProfileProperty>,
+}
@@ -0,0 +1,16 @@
+use shipyard::Component;
+use protocol_proc::packet;
+
+#[derive(Component)]
+#[packet(0x00)]
+pub struct StatusResponse {
+    /// The server's status as JSON, see `data::status::ServerStatus::to_json`
+    pub json: String,
+}
+
+#[derive(Component)]
+#[packet(0x01)]
+pub struct PongResponse {
+    /// The payload from Ping Request
+    pub payload: i64,
+}
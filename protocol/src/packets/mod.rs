@@ -1,11 +1,13 @@
-use shipyard::Component;
 use data::queue::Queue;
+use data::writer::ByteWriter;
 
 pub mod specific_types;
-mod serverbound;
+pub mod serverbound;
+pub mod clientbound;
 
 pub trait Packet {
-    fn serialize(self) -> Vec<u8>;
-    fn deserialize(queue: &mut Queue) -> Option<Self>;
+    /// Writes the packet's fields onto the end of `writer`, without its length or id
+    fn serialize(self, writer: &mut ByteWriter);
+    fn deserialize(queue: &mut Queue) -> Option<Self> where Self: Sized;
     fn id(&self) -> u8;
 }
\ No newline at end of file
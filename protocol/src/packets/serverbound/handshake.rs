@@ -5,8 +5,8 @@ use crate::data::VarInt;
 #[derive(Component)]
 #[packet(0x00)]
 pub struct Handshake {
-    protocol_version: VarInt,
-    server_address: String,
-    server_port: u16,
-    next_state: VarInt,
+    pub protocol_version: VarInt,
+    pub server_address: String,
+    pub server_port: u16,
+    pub next_state: VarInt,
 }
\ No newline at end of file
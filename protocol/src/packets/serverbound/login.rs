@@ -0,0 +1,24 @@
+use shipyard::Component;
+use protocol_proc::packet;
+use uuid::Uuid;
+
+#[derive(Component)]
+#[packet(0x00)]
+pub struct LoginStart {
+    pub name: String,
+    /// The UUID of the client's account, which isn't trusted
+    pub uuid: Uuid,
+}
+
+/// The shared secret and verify token, both encrypted with the server's public key
+#[derive(Component)]
+#[packet(0x01)]
+pub struct EncryptionResponse {
+    pub shared_secret: Vec<u8>,
+    pub verify_token: Vec<u8>,
+}
+
+/// Sent after Login Success, which moves the connection to the configuration state
+#[derive(Component)]
+#[packet(0x03)]
+pub struct LoginAcknowledged {}
@@ -1,40 +1,149 @@
-mod handshake;
+pub mod handshake;
+pub mod login;
+pub mod status;
 
-use shipyard::{AddComponent, IntoIter, IntoWithId, Remove, View, ViewMut};
-use data::network::{Connection, ConnectionState, ReadBytes};
-use data::queue::Queue;
-use crate::data::{PacketData, VarInt};
+use std::error::Error;
+use shipyard::{AddComponent, EntityId, Get, IntoIter, IntoWithId, Remove, UniqueView, View, ViewMut};
+use data::auth::{server_hash, GameProfile, HttpBackend, PendingAuth, SessionService};
+use data::encryption::{verify_token, ServerKey};
+use data::frame::{Frame, FrameError};
+use data::legacy::LegacyPing;
+use data::network::{Connection, ConnectionState, Disconnect, PendingLogin, ReadBytes, WriteBytes};
+use data::status::ServerStatus;
+use data::writer::ByteWriter;
+use crate::packets::clientbound::login::{EncryptionRequest, LoginSuccess};
+use crate::packets::clientbound::status::{PongResponse, StatusResponse};
 use crate::packets::Packet;
 use crate::packets::serverbound::handshake::Handshake;
+use crate::packets::serverbound::login::{EncryptionResponse, LoginAcknowledged, LoginStart};
+use crate::packets::serverbound::status::{PingRequest, StatusRequest};
 
-pub fn deserialize_serverbound_packets(mut vm_connection_state: ViewMut<ConnectionState>, v_connection: View<Connection>, mut vm_read_bytes: ViewMut<ReadBytes>) {
-    for (id, (connection_state, _)) in (&mut vm_connection_state, &v_connection).iter().with_id() {
-        let Some(bytes) = vm_read_bytes.remove(id) else { continue; };
-        let mut bytes = bytes.0;
-
-        while bytes.len() > 0 {
-            let mut queue = bytes.into();
-            let len = VarInt::deserialize(&mut queue).unwrap().0;
-            let queue: Vec<u8> = queue.into();
+#[allow(clippy::too_many_arguments)]
+pub fn deserialize_serverbound_packets<B: HttpBackend>(mut vm_connection_state: ViewMut<ConnectionState>, mut vm_connection: ViewMut<Connection>, mut vm_read_bytes: ViewMut<ReadBytes>, mut vm_write_bytes: ViewMut<WriteBytes>, mut vm_pending_login: ViewMut<PendingLogin>, mut vm_pending_auth: ViewMut<PendingAuth>, v_game_profile: View<GameProfile>, mut vm_disconnect: ViewMut<Disconnect>, (uv_status, uv_server_key, uv_session): (UniqueView<ServerStatus>, UniqueView<ServerKey>, UniqueView<SessionService<B>>)) {
+    for (entity, (connection_state, connection, write_bytes)) in (&mut vm_connection_state, &mut vm_connection, &mut vm_write_bytes).iter().with_id() {
+        let Some(bytes) = vm_read_bytes.remove(entity) else { continue; };
+        if vm_disconnect.contains(entity) {
+            continue;
+        }
 
-            let mut queue: Queue = queue.into();
-            let id = VarInt::deserialize(&mut queue).unwrap().0;
+        // a legacy ping can only be the first thing that a connection sends, and isn't framed
+        let first_read = connection.decoder.position() == 0 && connection.decoder.buffered() == 0;
+        if matches!(connection_state, ConnectionState::Handshake) && first_read {
+            if let Some(ping) = LegacyPing::detect(&bytes.0) {
+                write_bytes.0.write_bytes(ping.response(&uv_status).as_slice());
+                vm_disconnect.add_component_unchecked(entity, Disconnect);
+                continue;
+            }
+        }
 
-            match connection_state {
-                ConnectionState::Handshake => {
-                    if let Some(data) = Handshake::deserialize(&mut queue) {
+        connection.decoder.push(bytes.0);
 
-                    }
+        loop {
+            // the stream can't be read past an invalid frame, so the client is disconnected
+            let frame = match connection.decoder.decode() {
+                Ok(Some(frame)) => frame,
+                Ok(None) => break,
+                Err(_) => {
+                    vm_disconnect.add_component_unchecked(entity, Disconnect);
+                    break;
                 }
-                ConnectionState::Status => {}
-                ConnectionState::Login => {}
-                ConnectionState::Transfer => {}
-                ConnectionState::Configure => {}
-                ConnectionState::Play => {}
+            };
+
+            let handled = handle_frame(frame, entity, connection_state, connection, &mut write_bytes.0, &mut vm_pending_login, &mut vm_pending_auth, &v_game_profile, &uv_status, &uv_server_key, &uv_session);
+            if handled.is_err() {
+                vm_disconnect.add_component_unchecked(entity, Disconnect);
+                break;
+            }
+        }
+    }
+}
+
+/// Handles a frame from the connection of `entity`, failing if the connection has to be closed
+#[allow(clippy::too_many_arguments)]
+fn handle_frame<B: HttpBackend>(frame: Frame, entity: EntityId, connection_state: &mut ConnectionState, connection: &mut Connection, writer: &mut ByteWriter, vm_pending_login: &mut ViewMut<PendingLogin>, vm_pending_auth: &mut ViewMut<PendingAuth>, v_game_profile: &View<GameProfile>, status: &ServerStatus, server_key: &ServerKey, session: &SessionService<B>) -> Result<(), Box<dyn Error>> {
+    let mut queue = frame.queue();
+
+    match (&*connection_state, frame.id) {
+        (ConnectionState::Handshake, 0x00) => {
+            let handshake = Handshake::deserialize(&mut queue).ok_or("malformed Handshake")?;
+            *connection_state = match handshake.next_state.0 {
+                1 => ConnectionState::Status,
+                // a client that was transferred from another server logs in like any other
+                2 | 3 => ConnectionState::Login,
+                next_state => return Err(format!("invalid next state {next_state}").into()),
+            };
+        }
+        (ConnectionState::Status, 0x00) => {
+            StatusRequest::deserialize(&mut queue).ok_or("malformed Status Request")?;
+            send(connection, writer, StatusResponse { json: status.to_json().to_string() })?;
+        }
+        (ConnectionState::Status, 0x01) => {
+            let ping = PingRequest::deserialize(&mut queue).ok_or("malformed Ping Request")?;
+            send(connection, writer, PongResponse { payload: ping.payload })?;
+        }
+        (ConnectionState::Login, 0x00) => {
+            let login_start = LoginStart::deserialize(&mut queue).ok_or("malformed Login Start")?;
+            let verify_token = verify_token();
+            vm_pending_login.add_component_unchecked(entity, PendingLogin { name: login_start.name, verify_token });
+            send(connection, writer, EncryptionRequest {
+                server_id: String::new(),
+                public_key: server_key.public_key_der().to_vec(),
+                verify_token: verify_token.to_vec(),
+                should_authenticate: true,
+            })?;
+        }
+        (ConnectionState::Login, 0x01) => {
+            let response = EncryptionResponse::deserialize(&mut queue).ok_or("malformed Encryption Response")?;
+            let pending = vm_pending_login.remove(entity).ok_or("Encryption Response was sent before Login Start")?;
+            let shared_secret = server_key.decrypt_response(&response.shared_secret, &response.verify_token, &pending.verify_token)?;
+            connection.enable_encryption(&shared_secret);
+
+            // the client has told the session server that it is joining with this hash, unless its account is cracked, which `finish_logins` finds out
+            let server_hash = server_hash("", &shared_secret, server_key.public_key_der());
+            let ip = connection.address.map(|address| address.ip());
+            vm_pending_auth.add_component_unchecked(entity, session.spawn_has_joined(pending.name, server_hash, ip));
+        }
+        (ConnectionState::Login, 0x03) => {
+            LoginAcknowledged::deserialize(&mut queue).ok_or("malformed Login Acknowledged")?;
+            if !v_game_profile.contains(entity) {
+                return Err("Login Acknowledged was sent before Login Success".into());
             }
+            *connection_state = ConnectionState::Configure;
+        }
+        (_, id) => return Err(format!("unexpected packet {id:#04x}").into()),
+    }
+
+    Ok(())
+}
+
+/// Sends Login Success to the connections that the session server has answered for, and disconnects the ones that it didn't authenticate
+pub fn finish_logins(mut vm_connection: ViewMut<Connection>, mut vm_write_bytes: ViewMut<WriteBytes>, mut vm_pending_auth: ViewMut<PendingAuth>, mut vm_game_profile: ViewMut<GameProfile>, mut vm_disconnect: ViewMut<Disconnect>) {
+    let finished = vm_pending_auth.iter().with_id().filter(|(_, pending)| pending.is_finished()).map(|(entity, _)| entity).collect::<Vec<_>>();
+    for entity in finished {
+        let Some(pending) = vm_pending_auth.remove(entity) else { continue; };
+        let Ok((mut connection, mut write_bytes)) = (&mut vm_connection, &mut vm_write_bytes).get(entity) else { continue; };
 
-            bytes = queue.into();
-            bytes = bytes[len..];
+        let Ok(profile) = pending.join() else {
+            vm_disconnect.add_component_unchecked(entity, Disconnect);
+            continue;
+        };
+        let login_success = LoginSuccess {
+            uuid: profile.id,
+            username: profile.name.clone(),
+            properties: profile.properties.clone(),
+        };
+        match send(&mut connection, &mut write_bytes.0, login_success) {
+            Ok(()) => vm_game_profile.add_component_unchecked(entity, profile),
+            Err(_) => vm_disconnect.add_component_unchecked(entity, Disconnect),
         }
     }
-}
\ No newline at end of file
+}
+
+/// Frames `packet` and writes it onto the connection's outgoing bytes, failing if it is too long to send
+fn send(connection: &mut Connection, writer: &mut ByteWriter, packet: impl Packet) -> Result<(), FrameError> {
+    let id = packet.id() as i32;
+    let mut body = ByteWriter::new();
+    packet.serialize(&mut body);
+
+    connection.encoder.encode(&Frame::new(id, body.split()), writer)
+}
@@ -0,0 +1,14 @@
+use shipyard::Component;
+use protocol_proc::packet;
+
+/// Asks for the server's status, see `data::status`
+#[derive(Component)]
+#[packet(0x00)]
+pub struct StatusRequest {}
+
+#[derive(Component)]
+#[packet(0x01)]
+pub struct PingRequest {
+    /// Any number, which is sent back in Pong Response so the client can measure the latency
+    pub payload: i64,
+}